@@ -1,4 +1,5 @@
-import { useState, useCallback, useEffect, useMemo } from "react";
+import { useState, useCallback, useEffect, useMemo, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { Terminal } from "./components/Terminal";
 import { Preview } from "./components/Preview";
 import { SplitView, Pane } from "./components/layout";
@@ -7,6 +8,7 @@ import { useConfig } from "./hooks/useConfig";
 import { useSphinx } from "./hooks/useSphinx";
 import { useDevConfig } from "./hooks/useDevConfig";
 import { mergeConfig } from "./types/devConfig";
+import { logger } from "./utils/logger";
 import "./App.css";
 
 function App() {
@@ -33,6 +35,15 @@ function App() {
       setExited(false);
     }
   }, [projectPath]);
+
+  // 最近開いたプロジェクトに記録
+  useEffect(() => {
+    if (projectPath) {
+      invoke("record_recent_project", { path: projectPath }).catch((e) => {
+        logger.warn("Failed to record recent project:", e);
+      });
+    }
+  }, [projectPath]);
   const { config, loading: configLoading } = useConfig();
 
   // devConfigによる設定の上書きをマージ
@@ -74,6 +85,17 @@ function App() {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [effectiveConfig, projectPath, autoStartSphinx]);
 
+  // 最初のビルド完了でプレビューをブラウザで開く（セッションごとに1回）
+  const autoOpenedSessionRef = useRef<string | null>(null);
+  useEffect(() => {
+    if (!sphinxRunning || !devConfig?.auto_open_browser) return;
+    if (autoOpenedSessionRef.current === sessionId) return;
+    autoOpenedSessionRef.current = sessionId;
+    invoke("open_preview_after_first_build", { sessionId }).catch((e) => {
+      logger.warn("Failed to open preview after first build:", e);
+    });
+  }, [sphinxRunning, sessionId, devConfig?.auto_open_browser]);
+
   return (
     <main className="h-screen w-screen flex flex-col bg-gray-900">
       <header className="h-8 bg-gray-800 flex items-center justify-between px-4 text-gray-300 text-sm shrink-0">
@@ -141,6 +163,9 @@ function App() {
                   sessionId={sessionId}
                   cwd={projectPath}
                   shell={effectiveConfig.terminal.shell}
+                  shellArgs={effectiveConfig.terminal.shell_args}
+                  shellFallbacks={effectiveConfig.terminal.shell_fallbacks}
+                  deferSlaveDrop={effectiveConfig.terminal.defer_slave_drop}
                   fontFamily={effectiveConfig.terminal.font_family}
                   fontSize={effectiveConfig.terminal.font_size}
                   colorScheme={effectiveConfig.terminal.color_scheme}
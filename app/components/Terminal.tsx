@@ -4,6 +4,7 @@ import { FitAddon } from "@xterm/addon-fit";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { logger } from "../utils/logger";
+import { formatError } from "../utils/error";
 import { useSystemTheme } from "../hooks/useSystemTheme";
 import type { ColorScheme } from "../types/config";
 import "@xterm/xterm/css/xterm.css";
@@ -57,13 +58,37 @@ interface TerminalProps {
   sessionId: string;
   cwd?: string;
   shell?: string;
+  shellArgs?: string[];
+  shellFallbacks?: string[];
+  /** bash / zshにプロンプトの区切り（OSC 133）を出力させる */
+  shellIntegration?: boolean;
+  /** rcファイルを読まずに起動する（壊れたrcの修復用） */
+  safeMode?: boolean;
+  /** PTYのslaveを最初の出力を読むまで閉じない */
+  deferSlaveDrop?: boolean;
+  /** タブの見出しなどを色分けするためのアクセントカラー（16進） */
+  accent?: string;
   fontFamily?: string;
   fontSize?: number;
   colorScheme?: ColorScheme;
   onExit?: (code: number) => void;
 }
 
-export function Terminal({ sessionId, cwd, shell, fontFamily, fontSize, colorScheme, onExit }: TerminalProps) {
+export function Terminal({
+  sessionId,
+  cwd,
+  shell,
+  shellArgs,
+  shellFallbacks,
+  shellIntegration,
+  safeMode,
+  deferSlaveDrop,
+  accent,
+  fontFamily,
+  fontSize,
+  colorScheme,
+  onExit,
+}: TerminalProps) {
   const containerRef = useRef<HTMLDivElement>(null);
   const terminalRef = useRef<XTerm | null>(null);
   const fitAddonRef = useRef<FitAddon | null>(null);
@@ -139,30 +164,57 @@ export function Terminal({ sessionId, cwd, shell, fontFamily, fontSize, colorSch
 
     // PTYセッション開始
     const { cols, rows } = terminal;
-    invoke("spawn_terminal", { sessionId, cwd, shell, cols, rows }).catch((e) => {
+    invoke("spawn_terminal", {
+      sessionId,
+      options: {
+        cwd,
+        shell,
+        shellArgs,
+        shellFallbacks,
+        shellIntegration,
+        safeMode,
+        deferSlaveDrop,
+        accent,
+        cols,
+        rows,
+      },
+    }).catch((e) => {
       logger.error("Failed to spawn terminal:", e);
-      terminal.write(`\r\nError: ${e}\r\n`);
+      terminal.write(`\r\nError: ${formatError(e)}\r\n`);
     });
 
     // PTYからのデータを受信
     let unlistenData: UnlistenFn | null = null;
     let unlistenExit: UnlistenFn | null = null;
+    let unlistenTheme: UnlistenFn | null = null;
 
     const setupListeners = async () => {
-      unlistenData = await listen<[string, string]>("pty_data", (event) => {
+      unlistenData = await listen<[string, string, number]>("pty_data", (event) => {
         const [sid, data] = event.payload;
         if (sid === sessionId) {
           terminal.write(data);
         }
       });
 
-      unlistenExit = await listen<[string, number]>("pty_exit", (event) => {
-        const [sid, code] = event.payload;
+      unlistenExit = await listen<[string, number, boolean]>("pty_exit", (event) => {
+        const [sid, code, error] = event.payload;
         if (sid === sessionId) {
-          terminal.write(`\r\n[Process exited with code ${code}]\r\n`);
+          terminal.write(
+            error
+              ? "\r\n[Terminal output failed; session closed]\r\n"
+              : `\r\n[Process exited with code ${code}]\r\n`,
+          );
           onExit?.(code);
         }
       });
+
+      // set_all_terminals_color_scheme で全セッションに適用されたテーマ
+      unlistenTheme = await listen<[string, ColorScheme]>("pty_theme_changed", (event) => {
+        const [sid, scheme] = event.payload;
+        if (sid === sessionId) {
+          terminal.options.theme = mapToXtermTheme(scheme);
+        }
+      });
     };
 
     setupListeners();
@@ -179,6 +231,7 @@ export function Terminal({ sessionId, cwd, shell, fontFamily, fontSize, colorSch
       resizeObserver.disconnect();
       unlistenData?.();
       unlistenExit?.();
+      unlistenTheme?.();
       terminal.dispose();
 
       // PTYセッション終了
@@ -52,6 +52,26 @@ describe("useConfig", () => {
     expect(result.current.error).toBe("Error: Config not found");
   });
 
+  it("should show the message of a config parse error", async () => {
+    vi.mocked(invoke).mockRejectedValue({
+      kind: "configParse",
+      message: "設定のパースに失敗: config.toml:3:7: invalid value",
+      path: "config.toml",
+      line: 3,
+      column: 7,
+    });
+
+    const { result } = renderHook(() => useConfig());
+
+    await waitFor(() => {
+      expect(result.current.loading).toBe(false);
+    });
+
+    expect(result.current.error).toBe(
+      "設定のパースに失敗: config.toml:3:7: invalid value",
+    );
+  });
+
   it("should reload config when reload() is called", async () => {
     vi.mocked(invoke).mockResolvedValue(mockConfig);
 
@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import type { ProjectConfig } from "../types/config";
+import { formatError } from "../utils/error";
 
 interface UseConfigResult {
   config: ProjectConfig | null;
@@ -26,7 +27,7 @@ export function useConfig(): UseConfigResult {
       const loadedConfig = await invoke<ProjectConfig>("load_config");
       setConfig(loadedConfig);
     } catch (e) {
-      setError(String(e));
+      setError(formatError(e));
       setConfig(null);
     } finally {
       setLoading(false);
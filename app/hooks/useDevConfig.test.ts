@@ -1,5 +1,5 @@
 import { describe, it, expect, vi, beforeEach } from "vitest";
-import { renderHook, waitFor } from "@testing-library/react";
+import { renderHook, waitFor, act } from "@testing-library/react";
 import { useDevConfig } from "./useDevConfig";
 
 // @tauri-apps/api/core をモック
@@ -69,4 +69,23 @@ describe("useDevConfig", () => {
 
     expect(result.current.devConfig).toBeNull();
   });
+
+  it("should reload dev config", async () => {
+    vi.mocked(invoke).mockResolvedValueOnce({ project_path: "/a" });
+
+    const { result } = renderHook(() => useDevConfig());
+
+    await waitFor(() => {
+      expect(result.current.loaded).toBe(true);
+    });
+    expect(result.current.devConfig).toEqual({ project_path: "/a" });
+
+    vi.mocked(invoke).mockResolvedValueOnce({ project_path: "/b" });
+    await act(async () => {
+      await result.current.reload();
+    });
+
+    expect(invoke).toHaveBeenLastCalledWith("reload_dev_config");
+    expect(result.current.devConfig).toEqual({ project_path: "/b" });
+  });
 });
@@ -1,10 +1,11 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import type { DevConfig } from "../types/devConfig";
 
 /**
  * ローカル開発用設定を読み込むhook
  * .orthrus.dev.json が存在する場合のみ設定を返す
+ * reload で再起動せずにファイルを読み直す
  */
 export function useDevConfig() {
   const [devConfig, setDevConfig] = useState<DevConfig | null>(null);
@@ -21,5 +22,11 @@ export function useDevConfig() {
       });
   }, []);
 
-  return { devConfig, loaded };
+  const reload = useCallback(async () => {
+    const config = await invoke<DevConfig | null>("reload_dev_config");
+    setDevConfig(config);
+    return config;
+  }, []);
+
+  return { devConfig, loaded, reload };
 }
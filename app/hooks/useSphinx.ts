@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import type { ProjectConfig } from "../types/config";
+import { formatError, isAppError } from "../utils/error";
 
 interface UseSphinxOptions {
   sessionId: string;
@@ -44,12 +45,14 @@ export function useSphinx({ sessionId, projectPath, config }: UseSphinxOptions):
         buildDir: config.sphinx.build_dir,
         pythonPath: config.python.interpreter,
         port: config.sphinx.server.port,
-        extraArgs: config.sphinx.extra_args,
+        // 設定のextra_argsはoptions経由で渡る（こちらは呼び出し側の追加分）
+        extraArgs: [],
+        options: config.sphinx,
       });
       // ビルド中状態（ポートはまだ設定しない）
       setIsRunning(true);
     } catch (e) {
-      setError(String(e));
+      setError(formatError(e));
       setIsRunning(false);
     }
   }, [sessionId, projectPath, config]);
@@ -70,7 +73,17 @@ export function useSphinx({ sessionId, projectPath, config }: UseSphinxOptions):
       try {
         await invoke("open_in_browser", { url: previewUrl });
       } catch (e) {
-        setError(String(e));
+        // ブラウザを開けない環境ではURLをコピーして貼り付けてもらう
+        if (isAppError(e) && e.kind === "openFailed") {
+          try {
+            await navigator.clipboard.writeText(previewUrl);
+            setError(`${formatError(e)} (URL copied to clipboard)`);
+            return;
+          } catch {
+            // コピーもできなければ元のエラーを表示
+          }
+        }
+        setError(formatError(e));
       }
     }
   }, [previewUrl]);
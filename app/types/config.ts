@@ -1,14 +1,48 @@
 /** sphinx-autobuildサーバー設定 */
 export interface ServerConfig {
   port: number;
+  /** 指定ポートが使用中の場合に空きポートへ切り替えるか */
+  fallback_to_random: boolean;
+  /** sphinx-autobuild自身にブラウザを開かせるか */
+  open_browser: boolean;
+  /** ヘルスチェックの間隔（秒） */
+  health_check_interval_secs?: number;
+  /** プレビューURLのパスプレフィックス（リバースプロキシ配下用） */
+  base_url_path?: string;
+  /** 再起動待ちの初期値（ミリ秒）。試行ごとに倍になる */
+  restart_backoff_ms?: number;
+  /** 再起動待ちの上限（ミリ秒） */
+  restart_backoff_max_ms?: number;
+  /** 再起動待ちに加える揺らぎの割合（0.0〜1.0） */
+  restart_jitter?: number;
 }
 
 /** Sphinx関連設定 */
 export interface SphinxConfig {
   source_dir: string;
   build_dir: string;
+  /** conf.pyのディレクトリ（未指定 = source_dir） */
+  config_dir?: string;
   server: ServerConfig;
   extra_args: string[];
+  /** 各ビルドの前に実行するコマンド */
+  pre_build: string[];
+  /** sphinx_logの行からエスケープシーケンスを除去するか */
+  strip_ansi: boolean;
+  /** ブラウザを開くまでの待ち時間（秒、`--delay`。server.open_browser有効時のみ） */
+  delay_secs?: number;
+  /** 変更を無視するパスの正規表現 */
+  re_ignore: string[];
+  /** sphinx-autobuildのロケール（空なら環境のロケール） */
+  locale: string;
+  /** 起動時の初回ビルドを省略する（`--no-initial`） */
+  no_initial: boolean;
+  /** ビルドごとに更新された出力ファイルを `sphinx_output_changed` で通知する */
+  notify_output_changes: boolean;
+  /** `sphinx_log` の1行の上限（バイト、未指定 = 8KiB） */
+  max_log_line_bytes?: number;
+  /** 起動前にビルドディレクトリへ書き込めるか確認し、書き込めなければ起動しない */
+  check_build_dir: boolean;
 }
 
 /** Python環境設定 */
@@ -19,6 +53,8 @@ export interface PythonConfig {
 /** エディタ設定 */
 export interface EditorConfig {
   command: string;
+  /** エディタで開く拡張子（それ以外は既定のアプリで開く） */
+  text_extensions: string[];
 }
 
 /** カラースキーム（xterm.js ITheme互換） */
@@ -52,10 +88,32 @@ export interface ColorScheme {
 /** ターミナル設定 */
 export interface TerminalConfig {
   shell?: string;
+  /** シェルの引数（指定するとログインシェルの `-l` の代わりに使う。`~` は展開しない） */
+  shell_args?: string[];
+  /** shell未指定時に順に試すシェル（最初に実行できたものを使う） */
+  shell_fallbacks?: string[];
   font_family?: string;
   font_size?: number;
   theme_file?: string;
+  theme_name?: string;
   color_scheme?: ColorScheme;
+  /** 解決したテーマに重ねる個別の色 */
+  overrides?: Record<string, string>;
+  /** 入出力のない状態がこの秒数続いたセッションを自動終了 */
+  idle_kill_secs?: number;
+  /** 貼り付けサイズの上限（バイト、未指定 = 1MiB） */
+  max_paste_bytes?: number;
+  /** PTYのslaveを最初の出力を読むまで閉じない（何も出力せずに終了したシェルを検出できなくなる） */
+  defer_slave_drop: boolean;
+}
+
+/** カラースキームの出所 */
+export type ThemeSource = "inline" | "theme_file" | "none";
+
+/** resolved_terminal_theme の戻り値 */
+export interface ResolvedTheme {
+  color_scheme: ColorScheme | null;
+  source: ThemeSource;
 }
 
 /** プロジェクト設定全体 */
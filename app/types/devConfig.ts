@@ -5,23 +5,50 @@ export type ConfigOverride = {
   sphinx?: {
     source_dir?: string;
     build_dir?: string;
+    config_dir?: string;
     server?: {
       port?: number;
+      fallback_to_random?: boolean;
+      open_browser?: boolean;
+      health_check_interval_secs?: number;
+      base_url_path?: string;
+      restart_backoff_ms?: number;
+      restart_backoff_max_ms?: number;
+      restart_jitter?: number;
     };
     extra_args?: string[];
+    pre_build?: string[];
+    strip_ansi?: boolean;
+    delay_secs?: number;
+    re_ignore?: string[];
+    locale?: string;
+    no_initial?: boolean;
+    notify_output_changes?: boolean;
+    max_log_line_bytes?: number;
+    check_build_dir?: boolean;
   };
   python?: {
     interpreter?: string;
   };
   editor?: {
     command?: string;
+    text_extensions?: string[];
   };
   terminal?: {
     shell?: string;
+    shell_args?: string[];
+    shell_fallbacks?: string[];
     font_family?: string;
     font_size?: number;
     theme_file?: string;
+    theme_name?: string;
     color_scheme?: ColorScheme;
+    /** trueならベース設定のcolor_schemeを取り消す（theme_fileにフォールバック） */
+    clear_color_scheme?: boolean;
+    overrides?: Record<string, string>;
+    idle_kill_secs?: number;
+    max_paste_bytes?: number;
+    defer_slave_drop?: boolean;
   };
 };
 
@@ -31,6 +58,8 @@ export interface DevConfig {
   project_path?: string;
   /** sphinx-autobuildを自動起動するか */
   auto_start_sphinx?: boolean;
+  /** 最初のビルドが完了したらプレビューをブラウザで開くか */
+  auto_open_browser?: boolean;
   /** グローバル設定の上書き */
   config?: ConfigOverride;
 }
@@ -46,23 +75,57 @@ export function mergeConfig(
     sphinx: {
       source_dir: override.sphinx?.source_dir ?? base.sphinx.source_dir,
       build_dir: override.sphinx?.build_dir ?? base.sphinx.build_dir,
+      config_dir: override.sphinx?.config_dir ?? base.sphinx.config_dir,
       server: {
         port: override.sphinx?.server?.port ?? base.sphinx.server.port,
+        fallback_to_random:
+          override.sphinx?.server?.fallback_to_random ?? base.sphinx.server.fallback_to_random,
+        open_browser: override.sphinx?.server?.open_browser ?? base.sphinx.server.open_browser,
+        health_check_interval_secs:
+          override.sphinx?.server?.health_check_interval_secs ??
+          base.sphinx.server.health_check_interval_secs,
+        base_url_path: override.sphinx?.server?.base_url_path ?? base.sphinx.server.base_url_path,
+        restart_backoff_ms:
+          override.sphinx?.server?.restart_backoff_ms ?? base.sphinx.server.restart_backoff_ms,
+        restart_backoff_max_ms:
+          override.sphinx?.server?.restart_backoff_max_ms ??
+          base.sphinx.server.restart_backoff_max_ms,
+        restart_jitter: override.sphinx?.server?.restart_jitter ?? base.sphinx.server.restart_jitter,
       },
       extra_args: override.sphinx?.extra_args ?? base.sphinx.extra_args,
+      pre_build: override.sphinx?.pre_build ?? base.sphinx.pre_build,
+      strip_ansi: override.sphinx?.strip_ansi ?? base.sphinx.strip_ansi,
+      delay_secs: override.sphinx?.delay_secs ?? base.sphinx.delay_secs,
+      re_ignore: override.sphinx?.re_ignore ?? base.sphinx.re_ignore,
+      locale: override.sphinx?.locale ?? base.sphinx.locale,
+      no_initial: override.sphinx?.no_initial ?? base.sphinx.no_initial,
+      notify_output_changes:
+        override.sphinx?.notify_output_changes ?? base.sphinx.notify_output_changes,
+      max_log_line_bytes: override.sphinx?.max_log_line_bytes ?? base.sphinx.max_log_line_bytes,
+      check_build_dir: override.sphinx?.check_build_dir ?? base.sphinx.check_build_dir,
     },
     python: {
       interpreter: override.python?.interpreter ?? base.python.interpreter,
     },
     editor: {
       command: override.editor?.command ?? base.editor.command,
+      text_extensions: override.editor?.text_extensions ?? base.editor.text_extensions,
     },
     terminal: {
       shell: override.terminal?.shell ?? base.terminal.shell,
+      shell_args: override.terminal?.shell_args ?? base.terminal.shell_args,
+      shell_fallbacks: override.terminal?.shell_fallbacks ?? base.terminal.shell_fallbacks,
       font_family: override.terminal?.font_family ?? base.terminal.font_family,
       font_size: override.terminal?.font_size ?? base.terminal.font_size,
       theme_file: override.terminal?.theme_file ?? base.terminal.theme_file,
-      color_scheme: override.terminal?.color_scheme ?? base.terminal.color_scheme,
+      theme_name: override.terminal?.theme_name ?? base.terminal.theme_name,
+      color_scheme:
+        override.terminal?.color_scheme ??
+        (override.terminal?.clear_color_scheme ? undefined : base.terminal.color_scheme),
+      overrides: override.terminal?.overrides ?? base.terminal.overrides,
+      idle_kill_secs: override.terminal?.idle_kill_secs ?? base.terminal.idle_kill_secs,
+      max_paste_bytes: override.terminal?.max_paste_bytes ?? base.terminal.max_paste_bytes,
+      defer_slave_drop: override.terminal?.defer_slave_drop ?? base.terminal.defer_slave_drop,
     },
   };
 }
@@ -0,0 +1,27 @@
+import { describe, it, expect } from "vitest";
+import { formatError, isAppError } from "./error";
+
+describe("isAppError", () => {
+  it("should accept a structured backend error", () => {
+    expect(isAppError({ kind: "portInUse", message: "ポート 8000 は使用中です", port: 8000 })).toBe(
+      true
+    );
+  });
+
+  it("should reject plain strings and incomplete objects", () => {
+    expect(isAppError("failed")).toBe(false);
+    expect(isAppError(null)).toBe(false);
+    expect(isAppError({ kind: "other" })).toBe(false);
+  });
+});
+
+describe("formatError", () => {
+  it("should use the message of a structured error", () => {
+    expect(formatError({ kind: "other", message: "失敗" })).toBe("失敗");
+  });
+
+  it("should stringify other values", () => {
+    expect(formatError("failed")).toBe("failed");
+    expect(formatError(new Error("boom"))).toBe("Error: boom");
+  });
+});
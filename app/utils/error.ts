@@ -0,0 +1,21 @@
+/** バックエンドの構造化エラー（AppError） */
+export interface AppError {
+  kind: string;
+  message: string;
+  [key: string]: unknown;
+}
+
+/** invokeで受け取ったエラーがAppErrorか判定する */
+export function isAppError(e: unknown): e is AppError {
+  return (
+    typeof e === "object" &&
+    e !== null &&
+    typeof (e as AppError).kind === "string" &&
+    typeof (e as AppError).message === "string"
+  );
+}
+
+/** invokeで受け取ったエラーを表示用の文字列に変換する */
+export function formatError(e: unknown): string {
+  return isAppError(e) ? e.message : String(e);
+}
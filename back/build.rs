@@ -1,3 +1,27 @@
+use std::path::Path;
+use std::process::Command;
+
 fn main() {
+    // `app_version` で返すコミット（gitがない・リポジトリ外でのビルドでは設定しない）
+    if let Some(sha) = git_sha() {
+        println!("cargo:rustc-env=ORTHRUS_GIT_SHA={}", sha);
+    }
+    for path in ["../.git/HEAD", "../.git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
     tauri_build::build()
 }
+
+fn git_sha() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let sha = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!sha.is_empty()).then_some(sha)
+}
@@ -0,0 +1,109 @@
+//! ANSIエスケープシーケンスの除去
+//!
+//! ECMA-48の構文に従って読み飛ばす（色指定のSGR以外のCSIやOSCも対象）
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+/// 8ビットのCSI
+const C1_CSI: char = '\u{9b}';
+/// 8ビットのST（文字列終端）
+const C1_ST: char = '\u{9c}';
+
+/// 文字列からエスケープシーケンスを取り除く
+/// 途中で切れたシーケンスは末尾まで読み飛ばす
+pub fn strip_ansi(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => match chars.next() {
+                Some('[') => skip_csi(&mut chars),
+                // OSC / DCS / SOS / PM / APC はSTまたはBELまで
+                Some(']' | 'P' | 'X' | '^' | '_') => skip_string(&mut chars),
+                // nF: 中間バイト（0x20-0x2F）の後に終端バイト1文字
+                Some('\x20'..='\x2f') => {
+                    while chars.next_if(|c| matches!(c, '\x20'..='\x2f')).is_some() {}
+                    chars.next();
+                }
+                // Fe / Fp / Fs: ESCに続く1文字で完結
+                Some(_) | None => {}
+            },
+            C1_CSI => skip_csi(&mut chars),
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// CSIのパラメータ（0x30-0x3F）・中間（0x20-0x2F）・終端（0x40-0x7E）を読み飛ばす
+fn skip_csi(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while chars.next_if(|c| matches!(c, '\x30'..='\x3f')).is_some() {}
+    while chars.next_if(|c| matches!(c, '\x20'..='\x2f')).is_some() {}
+    // 終端バイト。範囲外の文字が来た場合は不正なシーケンスとしてそこで打ち切る
+    chars.next_if(|c| matches!(c, '\x40'..='\x7e'));
+}
+
+/// BEL・ESC \・8ビットSTのいずれかまで読み飛ばす
+fn skip_string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while let Some(c) = chars.next() {
+        match c {
+            BEL | C1_ST => return,
+            ESC => {
+                chars.next_if_eq(&'\\');
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_sgr_from_sphinx_output() {
+        assert_eq!(
+            strip_ansi("\x1b[01mbuilding [html]: \x1b[39;49;00mtargets for 3 source files"),
+            "building [html]: targets for 3 source files"
+        );
+        assert_eq!(
+            strip_ansi(
+                "\x1b[91m/docs/index.rst:12: WARNING: undefined label: 'intro'\x1b[39;49;00m"
+            ),
+            "/docs/index.rst:12: WARNING: undefined label: 'intro'"
+        );
+        assert_eq!(
+            strip_ansi("build \x1b[32msucceeded\x1b[39;49;00m."),
+            "build succeeded."
+        );
+    }
+
+    #[test]
+    fn test_strip_other_sequences() {
+        // カーソル移動・行消去（進捗表示）
+        assert_eq!(
+            strip_ansi("\x1b[2K\x1b[1Greading sources... [ 50%]"),
+            "reading sources... [ 50%]"
+        );
+        // プライベートモード・8ビットCSI
+        assert_eq!(strip_ansi("\x1b[?25lhidden\u{9b}0m"), "hidden");
+        // OSC（BEL終端・ST終端）
+        assert_eq!(
+            strip_ansi("\x1b]0;title\x07text\x1b]8;;url\x1b\\link"),
+            "textlink"
+        );
+        // 文字セット指定（nF）
+        assert_eq!(strip_ansi("\x1b(Bplain"), "plain");
+    }
+
+    #[test]
+    fn test_strip_keeps_plain_text() {
+        assert_eq!(strip_ansi("[ 50%] 日本語 index"), "[ 50%] 日本語 index");
+        // 途中で切れたシーケンス
+        assert_eq!(strip_ansi("done\x1b[3"), "done");
+        assert_eq!(strip_ansi("done\x1b"), "done");
+    }
+}
@@ -0,0 +1,494 @@
+//! ビルド出力ディレクトリの調査
+
+use serde::Serialize;
+use std::path::{Component, Path};
+use std::time::SystemTime;
+
+use crate::preview::{self, Builder};
+
+/// ビルド出力のサイズ
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BuildOutputSize {
+    pub total_bytes: u64,
+    pub file_count: u64,
+}
+
+/// 静的ファイルなどページではない出力のディレクトリ
+const NON_PAGE_DIRS: &[&str] = &["_static", "_sources"];
+
+/// ディレクトリ以下の通常ファイルを走査
+/// シンボリックリンクは循環を避けるため辿らず、通知もしない。
+/// `skip_dir` がtrueを返すディレクトリには降りない
+fn walk_files(
+    dir: &Path,
+    skip_dir: impl Fn(&Path) -> bool,
+    mut visit: impl FnMut(&Path, &std::fs::Metadata),
+) -> Result<(), String> {
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = std::fs::read_dir(&current).map_err(|e| {
+            format!(
+                "ディレクトリの読み込みに失敗: {} ({})",
+                e,
+                current.display()
+            )
+        })?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("ディレクトリの読み込みに失敗: {}", e))?;
+            let path = entry.path();
+            let metadata = std::fs::symlink_metadata(&path)
+                .map_err(|e| format!("ファイル情報の取得に失敗: {}", e))?;
+            if metadata.is_dir() {
+                if !skip_dir(&path) {
+                    pending.push(path);
+                }
+            } else if metadata.is_file() {
+                visit(&path, &metadata);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ディレクトリ以下のファイルサイズを合計（存在しなければ0）
+pub fn output_size(dir: &Path) -> Result<BuildOutputSize, String> {
+    let mut size = BuildOutputSize::default();
+    if !dir.is_dir() {
+        return Ok(size);
+    }
+
+    walk_files(
+        dir,
+        |_| false,
+        |_, metadata| {
+            size.total_bytes += metadata.len();
+            size.file_count += 1;
+        },
+    )?;
+    Ok(size)
+}
+
+/// ビルド済みHTMLページをビルドルートからの相対URL（`/` 区切り）でソートして返す
+/// `_static` / `_sources` 以下は除外。ビルドがなければ空
+pub fn list_pages(dir: &Path) -> Result<Vec<String>, String> {
+    let mut pages = Vec::new();
+    if !dir.is_dir() {
+        return Ok(pages);
+    }
+
+    walk_files(
+        dir,
+        |path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| NON_PAGE_DIRS.contains(&name))
+        },
+        |path, _| {
+            if path.extension().and_then(|e| e.to_str()) != Some("html") {
+                return;
+            }
+            if let Ok(relative) = path.strip_prefix(dir) {
+                let url = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                pages.push(url);
+            }
+        },
+    )?;
+
+    pages.sort();
+    Ok(pages)
+}
+
+/// ビルド出力の構成から推定したビルダー
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildFormat {
+    Html,
+    DirHtml,
+    /// ビルドがない、または構成から判別できない
+    Unknown,
+}
+
+/// ビルド出力のディレクトリ構成からhtml / dirhtmlのどちらで出力されたかを推定する
+/// Sphinxが必ず出力する検索・索引ページ（`search.html` / `search/index.html` など）を優先し、
+/// なければページの構成（`guide/intro.html` と `guide/intro/index.html` のどちらだけか）で判定する
+pub fn detect_format(dir: &Path) -> Result<BuildFormat, String> {
+    let pages = list_pages(dir)?;
+    if !pages.iter().any(|page| page == "index.html") {
+        return Ok(BuildFormat::Unknown);
+    }
+
+    let has = |page: &str| pages.iter().any(|p| p == page);
+    let html_markers = has("search.html") || has("genindex.html");
+    let dirhtml_markers = has("search/index.html") || has("genindex/index.html");
+    match (html_markers, dirhtml_markers) {
+        (true, false) => return Ok(BuildFormat::Html),
+        (false, true) => return Ok(BuildFormat::DirHtml),
+        (true, true) => return Ok(BuildFormat::Unknown),
+        (false, false) => {}
+    }
+
+    let nested_index = pages
+        .iter()
+        .filter(|page| page.ends_with("/index.html"))
+        .count();
+    let flat = pages
+        .iter()
+        .filter(|page| *page != "index.html" && !page.ends_with("/index.html"))
+        .count();
+    Ok(match (flat > 0, nested_index > 0) {
+        (true, false) => BuildFormat::Html,
+        (false, true) => BuildFormat::DirHtml,
+        _ => BuildFormat::Unknown,
+    })
+}
+
+/// ディレクトリ以下で最も新しい更新時刻（`skip` 以下は除く。ファイルがなければNone）
+fn newest_mtime(dir: &Path, skip: Option<&Path>) -> Result<Option<SystemTime>, String> {
+    let mut newest = None;
+    walk_files(
+        dir,
+        |path| skip.is_some_and(|skip| path == skip),
+        |_, metadata| {
+            if let Ok(modified) = metadata.modified() {
+                newest = newest.max(Some(modified));
+            }
+        },
+    )?;
+    Ok(newest)
+}
+
+/// ソースファイルに対応するビルド済みHTMLを読む（プレビューURLと同じ対応付け）
+/// まだビルドされていない場合やビルドディレクトリの外を指す場合はエラー
+pub fn read_page(
+    build_dir: &Path,
+    source_rel_path: &str,
+    builder: Builder,
+) -> Result<String, String> {
+    let mut page = preview::page_path_for_source(source_rel_path, builder);
+    // dirhtmlのURL（`guide/` や空）はディレクトリのindex.html
+    if page.is_empty() || page.ends_with('/') {
+        page.push_str("index.html");
+    }
+    let relative = Path::new(&page);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!(
+            "ビルドディレクトリ外のパスです: {}",
+            source_rel_path
+        ));
+    }
+
+    let path = build_dir.join(relative);
+    if !path.is_file() {
+        return Err(format!(
+            "ビルド済みのページがありません（未ビルド）: {}",
+            path.display()
+        ));
+    }
+    std::fs::read_to_string(&path)
+        .map_err(|e| format!("ページの読み込みに失敗: {} ({})", e, path.display()))
+}
+
+/// `changed_since` で返すパスの上限（大規模な全体ビルドでイベントが巨大にならないように）
+pub const CHANGED_FILES_LIMIT: usize = 500;
+
+/// `since` 以降に更新されたファイルをビルドルートからの相対パス（`/` 区切り）でソートして返す
+/// limitを超えた分は切り捨て、truncatedをtrueにする。ビルドがなければ空
+pub fn changed_since(
+    dir: &Path,
+    since: SystemTime,
+    limit: usize,
+) -> Result<(Vec<String>, bool), String> {
+    let mut changed = Vec::new();
+    if !dir.is_dir() {
+        return Ok((changed, false));
+    }
+
+    walk_files(
+        dir,
+        |_| false,
+        |path, metadata| {
+            if !metadata.modified().is_ok_and(|modified| modified >= since) {
+                return;
+            }
+            if let Ok(relative) = path.strip_prefix(dir) {
+                let relative = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                changed.push(relative);
+            }
+        },
+    )?;
+
+    changed.sort();
+    let truncated = changed.len() > limit;
+    changed.truncate(limit);
+    Ok((changed, truncated))
+}
+
+/// ソースがビルド出力より新しいか（ソース内のビルドディレクトリは比較から除く）
+/// ビルドディレクトリがない・空の場合は古いとみなす
+pub fn is_stale(source_dir: &Path, build_dir: &Path) -> Result<bool, String> {
+    if !build_dir.is_dir() {
+        return Ok(true);
+    }
+    let Some(built) = newest_mtime(build_dir, None)? else {
+        return Ok(true);
+    };
+    if !source_dir.is_dir() {
+        return Ok(false);
+    }
+    let source = newest_mtime(source_dir, Some(build_dir))?;
+    Ok(source.is_some_and(|source| source > built))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<html></html>").unwrap();
+        std::fs::create_dir_all(dir.path().join("_static")).unwrap();
+        std::fs::write(dir.path().join("_static/style.css"), "body{}").unwrap();
+
+        let size = output_size(dir.path()).unwrap();
+        assert_eq!(size.file_count, 2);
+        assert_eq!(size.total_bytes, 13 + 6);
+    }
+
+    #[test]
+    fn test_output_size_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let size = output_size(&dir.path().join("_build")).unwrap();
+        assert_eq!(size, BuildOutputSize::default());
+    }
+
+    #[test]
+    fn test_list_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "index.html",
+            "guide/intro.html",
+            "guide/setup.html",
+            "genindex.html",
+            "_static/basic.css",
+            "_static/doctools.html",
+            "_sources/index.rst.txt",
+            "objects.inv",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(
+            list_pages(dir.path()).unwrap(),
+            vec![
+                "genindex.html",
+                "guide/intro.html",
+                "guide/setup.html",
+                "index.html"
+            ]
+        );
+    }
+
+    fn build_tree(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_detect_format_html() {
+        let dir = build_tree(&[
+            "index.html",
+            "genindex.html",
+            "search.html",
+            "guide/index.html",
+            "guide/intro.html",
+            "_static/basic.css",
+        ]);
+        assert_eq!(detect_format(dir.path()).unwrap(), BuildFormat::Html);
+
+        // 索引ページがなくてもページの構成で判定する
+        let dir = build_tree(&["index.html", "guide/intro.html"]);
+        assert_eq!(detect_format(dir.path()).unwrap(), BuildFormat::Html);
+    }
+
+    #[test]
+    fn test_detect_format_dirhtml() {
+        let dir = build_tree(&[
+            "index.html",
+            "genindex/index.html",
+            "search/index.html",
+            "guide/index.html",
+            "guide/intro/index.html",
+            "_static/basic.css",
+        ]);
+        assert_eq!(detect_format(dir.path()).unwrap(), BuildFormat::DirHtml);
+
+        let dir = build_tree(&["index.html", "guide/intro/index.html"]);
+        assert_eq!(detect_format(dir.path()).unwrap(), BuildFormat::DirHtml);
+    }
+
+    #[test]
+    fn test_detect_format_unknown() {
+        // ルートのindex.htmlがない・トップページだけ・両方の構成が混在
+        for files in [
+            &["guide/intro.html"][..],
+            &["index.html"],
+            &["index.html", "intro.html", "guide/intro/index.html"],
+            &["index.html", "search.html", "search/index.html"],
+        ] {
+            let dir = build_tree(files);
+            assert_eq!(
+                detect_format(dir.path()).unwrap(),
+                BuildFormat::Unknown,
+                "{:?}",
+                files
+            );
+        }
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            detect_format(&dir.path().join("_build")).unwrap(),
+            BuildFormat::Unknown
+        );
+    }
+
+    #[test]
+    fn test_list_pages_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(list_pages(&dir.path().join("_build")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_dir = dir.path().join("docs");
+        // ソース内に置いたビルドディレクトリは比較から除く
+        let build_dir = source_dir.join("_build/html");
+        std::fs::create_dir_all(&build_dir).unwrap();
+        assert!(is_stale(&source_dir, &build_dir).unwrap());
+
+        let set_mtime = |path: &Path, secs: u64| {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        let source = source_dir.join("index.rst");
+        let page = build_dir.join("index.html");
+        std::fs::write(&source, "").unwrap();
+        std::fs::write(&page, "").unwrap();
+        set_mtime(&source, 1_000);
+        set_mtime(&page, 2_000);
+        assert!(!is_stale(&source_dir, &build_dir).unwrap());
+
+        // ソースを更新すると古くなる
+        set_mtime(&source, 3_000);
+        assert!(is_stale(&source_dir, &build_dir).unwrap());
+    }
+
+    #[test]
+    fn test_read_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let build_dir = dir.path().join("html");
+        std::fs::create_dir_all(build_dir.join("guide/intro")).unwrap();
+        std::fs::write(build_dir.join("index.html"), "<h1>Top</h1>").unwrap();
+        std::fs::write(build_dir.join("guide/intro.html"), "<h1>Intro</h1>").unwrap();
+        std::fs::write(build_dir.join("guide/intro/index.html"), "<h1>Dir</h1>").unwrap();
+
+        assert_eq!(
+            read_page(&build_dir, "guide/intro.rst", Builder::Html).unwrap(),
+            "<h1>Intro</h1>"
+        );
+        assert_eq!(
+            read_page(&build_dir, "index.md", Builder::Html).unwrap(),
+            "<h1>Top</h1>"
+        );
+        assert_eq!(
+            read_page(&build_dir, "guide/intro.rst", Builder::DirHtml).unwrap(),
+            "<h1>Dir</h1>"
+        );
+        assert_eq!(
+            read_page(&build_dir, "index.rst", Builder::DirHtml).unwrap(),
+            "<h1>Top</h1>"
+        );
+
+        // 未ビルド・ビルドディレクトリ外
+        let err = read_page(&build_dir, "guide/new.rst", Builder::Html).unwrap_err();
+        assert!(err.contains("未ビルド"), "{}", err);
+        assert!(read_page(&build_dir, "../secret.rst", Builder::Html).is_err());
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let build_dir = dir.path().join("html");
+        std::fs::create_dir_all(build_dir.join("_static")).unwrap();
+        let set_mtime = |path: &Path, secs: u64| {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        for name in ["index.html", "old.html", "_static/app.css"] {
+            std::fs::write(build_dir.join(name), "").unwrap();
+        }
+        set_mtime(&build_dir.join("index.html"), 2_000);
+        set_mtime(&build_dir.join("_static/app.css"), 3_000);
+        set_mtime(&build_dir.join("old.html"), 1_000);
+
+        let since = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_500);
+        let (changed, truncated) = changed_since(&build_dir, since, 10).unwrap();
+        assert_eq!(changed, ["_static/app.css", "index.html"]);
+        assert!(!truncated);
+
+        // 上限を超えた分は切り捨てる
+        let (changed, truncated) = changed_since(&build_dir, since, 1).unwrap();
+        assert_eq!(changed, ["_static/app.css"]);
+        assert!(truncated);
+
+        let (changed, _) = changed_since(&dir.path().join("missing"), since, 10).unwrap();
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn test_is_stale_missing_build_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(is_stale(dir.path(), &dir.path().join("_build")).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_size_skips_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "abc").unwrap();
+        // 自身を指すリンク（辿ると循環する）
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("loop")).unwrap();
+
+        let size = output_size(dir.path()).unwrap();
+        assert_eq!(size.file_count, 1);
+        assert_eq!(size.total_bytes, 3);
+    }
+}
@@ -6,10 +6,11 @@
 //! - iTerm2 (.itermcolors plist)
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// xterm.js ITheme互換のカラースキーム
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ColorScheme {
     #[serde(default)]
     pub background: Option<String>,
@@ -59,26 +60,326 @@ pub struct ColorScheme {
     pub bright_white: Option<String>,
 }
 
+impl ColorScheme {
+    /// フィールド名と値の可変参照の一覧
+    pub fn fields_mut(&mut self) -> [(&'static str, &mut Option<String>); 22] {
+        [
+            ("background", &mut self.background),
+            ("foreground", &mut self.foreground),
+            ("cursor", &mut self.cursor),
+            ("cursor_accent", &mut self.cursor_accent),
+            ("selection_background", &mut self.selection_background),
+            ("selection_foreground", &mut self.selection_foreground),
+            ("black", &mut self.black),
+            ("red", &mut self.red),
+            ("green", &mut self.green),
+            ("yellow", &mut self.yellow),
+            ("blue", &mut self.blue),
+            ("magenta", &mut self.magenta),
+            ("cyan", &mut self.cyan),
+            ("white", &mut self.white),
+            ("bright_black", &mut self.bright_black),
+            ("bright_red", &mut self.bright_red),
+            ("bright_green", &mut self.bright_green),
+            ("bright_yellow", &mut self.bright_yellow),
+            ("bright_blue", &mut self.bright_blue),
+            ("bright_magenta", &mut self.bright_magenta),
+            ("bright_cyan", &mut self.bright_cyan),
+            ("bright_white", &mut self.bright_white),
+        ]
+    }
+
+    /// 色の表記を正規化（小文字化、短縮形 `#fff` の展開、`0x` 接頭辞の変換）
+    /// テーマの出典によって `#FFFFFF` / `#ffffff` が混在し比較がぶれるのを防ぐ
+    pub fn normalize(&mut self) {
+        for (_, value) in self.fields_mut() {
+            if let Some(color) = value.as_mut() {
+                *color = normalize_hex(color);
+            }
+        }
+    }
+}
+
+impl ColorScheme {
+    /// 個別の色をキー指定で上書き（ベーステーマの一部だけ変えたい場合）
+    /// 未知のキーや16進として解釈できない値があれば何も変更せずにエラーを返す
+    pub fn apply_overrides(&mut self, overrides: &HashMap<String, String>) -> Result<(), String> {
+        let mut validated = Vec::with_capacity(overrides.len());
+        for (key, value) in overrides {
+            if !self.fields_mut().iter().any(|(name, _)| name == key) {
+                return Err(format!("未知のカラーキー: {}", key));
+            }
+            let color = normalize_hex(value);
+            if !is_normalized_hex(&color) {
+                return Err(format!("16進カラーではありません: {} = {}", key, value));
+            }
+            validated.push((key.as_str(), color));
+        }
+
+        for (key, color) in validated {
+            if let Some((_, field)) = self.fields_mut().into_iter().find(|(name, _)| *name == key) {
+                *field = Some(color);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 16進カラーを `#rrggbb`（アルファ付きは `#rrggbbaa`）に正規化する（解釈できなければNone）
+pub fn parse_hex_color(value: &str) -> Option<String> {
+    Some(normalize_hex(value)).filter(|color| is_normalized_hex(color))
+}
+
+/// `normalize_hex` の結果が正規化済みの16進表記か
+fn is_normalized_hex(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|digits| {
+        matches!(digits.len(), 6 | 8) && digits.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// 16進カラー表記を `#rrggbb`（アルファ付きは `#rrggbbaa`）に正規化
+/// 16進として解釈できない値（`rgb(...)` など）はそのまま返す
+fn normalize_hex(color: &str) -> String {
+    let trimmed = color.trim();
+    let digits = trimmed
+        .strip_prefix('#')
+        .or_else(|| trimmed.strip_prefix("0x"))
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return color.to_string();
+    }
+
+    let digits = digits.to_ascii_lowercase();
+    match digits.len() {
+        // #rgb / #rgba -> #rrggbb / #rrggbbaa
+        3 | 4 => digits.chars().fold(String::from("#"), |mut acc, c| {
+            acc.push(c);
+            acc.push(c);
+            acc
+        }),
+        6 | 8 => format!("#{}", digits),
+        // 桁数が足りない場合はゼロ埋め
+        n if n < 6 => format!("#{:0>6}", digits),
+        _ => color.to_string(),
+    }
+}
+
+/// テーマファイルのフォーマット
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeFormat {
+    /// Alacritty (TOML)
+    Alacritty,
+    /// Windows Terminal (JSON)
+    WindowsTerminal,
+    /// iTerm2 (.itermcolors plist)
+    Iterm2,
+}
+
+impl ThemeFormat {
+    /// 対応している全フォーマット（判定はこの順で行う）
+    pub const ALL: &'static [ThemeFormat] = &[
+        ThemeFormat::Alacritty,
+        ThemeFormat::WindowsTerminal,
+        ThemeFormat::Iterm2,
+    ];
+
+    /// 表示名
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeFormat::Alacritty => "Alacritty (TOML)",
+            ThemeFormat::WindowsTerminal => "Windows Terminal (JSON)",
+            ThemeFormat::Iterm2 => "iTerm2 (.itermcolors plist)",
+        }
+    }
+
+    /// このフォーマットとして扱う拡張子（小文字、ドットなし）
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ThemeFormat::Alacritty => &["toml"],
+            ThemeFormat::WindowsTerminal => &["json"],
+            ThemeFormat::Iterm2 => &["itermcolors"],
+        }
+    }
+
+    /// 判定方法と読み取る内容の説明（インポート画面用）
+    pub fn detection(self) -> &'static str {
+        match self {
+            ThemeFormat::Alacritty => {
+                "拡張子 .toml。[colors.primary] / [colors.normal] / [colors.bright] などを読む"
+            }
+            ThemeFormat::WindowsTerminal => {
+                "拡張子 .json。単体のスキーム、または settings.json の schemes からテーマ名で選択"
+            }
+            ThemeFormat::Iterm2 => "拡張子 .itermcolors。Ansi 0〜15 Color などのRGB成分を読む",
+        }
+    }
+
+    /// 拡張子からフォーマットを判定
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+}
+
+/// 対応フォーマットの説明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThemeFormatInfo {
+    pub format: ThemeFormat,
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub detection: &'static str,
+}
+
+/// 読み込める全テーマフォーマットの説明（`load_theme_file` の判定と同じ定義から作る）
+pub fn supported_theme_formats() -> Vec<ThemeFormatInfo> {
+    ThemeFormat::ALL
+        .iter()
+        .map(|&format| ThemeFormatInfo {
+            format,
+            name: format.name(),
+            extensions: format.extensions(),
+            detection: format.detection(),
+        })
+        .collect()
+}
+
+/// 対応する拡張子の一覧（エラーメッセージ用、例: ".toml, .json"）
+fn supported_extensions_label() -> String {
+    ThemeFormat::ALL
+        .iter()
+        .flat_map(|format| format.extensions())
+        .map(|extension| format!(".{}", extension))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// テーマファイルを読み込み、フォーマットを拡張子から自動検出
-pub fn load_theme_file(path: &Path) -> Result<ColorScheme, String> {
+///
+/// `scheme_name` は複数のスキームを含むファイル（Windows Terminalの
+/// `settings.json` など）から使用するスキームを選択する
+pub fn load_theme_file(path: &Path, scheme_name: Option<&str>) -> Result<ColorScheme, String> {
+    let format = ThemeFormat::from_path(path).ok_or_else(|| {
+        format!(
+            "未対応のテーマファイル形式: {} (対応: {})",
+            path.display(),
+            supported_extensions_label()
+        )
+    })?;
+
     let content =
         std::fs::read_to_string(path).map_err(|e| format!("テーマファイル読み込み失敗: {}", e))?;
 
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    match extension.as_str() {
-        "toml" => parse_alacritty_toml(&content),
-        "json" => parse_windows_terminal_json(&content),
-        "itermcolors" => parse_iterm2_plist(&content),
-        _ => Err(format!(
-            "未対応のテーマファイル形式: .{} (対応: .toml, .json, .itermcolors)",
-            extension
-        )),
+    let mut scheme = match format {
+        ThemeFormat::Alacritty => parse_alacritty_toml(&content),
+        ThemeFormat::WindowsTerminal => parse_windows_terminal_json(&content, scheme_name),
+        ThemeFormat::Iterm2 => parse_iterm2_plist(&content),
+    }?;
+
+    scheme.normalize();
+    Ok(scheme)
+}
+
+/// 必須とみなす色（カーソル・選択色は端末側のデフォルトで補える）
+const REQUIRED_COLOR_KEYS: &[&str] = &[
+    "background",
+    "foreground",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+/// テーマファイルの検証結果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThemeValidation {
+    pub ok: bool,
+    pub format: Option<ThemeFormat>,
+    pub problems: Vec<String>,
+}
+
+/// テーマファイルを適用せずに検証（フォーマット判定・パース・16進値の確認）
+pub fn validate_theme_file(path: &Path, scheme_name: Option<&str>) -> ThemeValidation {
+    let format = ThemeFormat::from_path(path);
+    let mut problems = Vec::new();
+
+    if format.is_none() {
+        problems.push(format!("未対応のテーマファイル形式: {}", path.display()));
+    } else {
+        match load_theme_file(path, scheme_name) {
+            Ok(mut scheme) => {
+                for (key, value) in scheme.fields_mut() {
+                    match value {
+                        Some(color) if !is_normalized_hex(color) => {
+                            problems.push(format!("16進カラーではありません: {} = {}", key, color));
+                        }
+                        None if REQUIRED_COLOR_KEYS.contains(&key) => {
+                            problems.push(format!("キーがありません: {}", key));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => problems.push(e),
+        }
     }
+
+    ThemeValidation {
+        ok: problems.is_empty(),
+        format,
+        problems,
+    }
+}
+
+/// ディレクトリ内のテーマファイル
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThemeFileEntry {
+    pub path: String,
+    pub format: ThemeFormat,
+}
+
+/// ディレクトリ直下からカラースキームとして読み込めるテーマファイルを列挙（パス順）
+/// 読み込めないファイルや色が1つも定義されていないファイルは除外する
+pub fn list_theme_files(dir: &Path) -> Result<Vec<ThemeFileEntry>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("ディレクトリの読み込みに失敗: {}: {}", dir.display(), e))?;
+
+    let mut themes: Vec<ThemeFileEntry> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let format = ThemeFormat::from_path(&path)?;
+            let scheme = load_theme_file(&path, None).ok()?;
+            if scheme == ColorScheme::default() {
+                return None;
+            }
+            Some(ThemeFileEntry {
+                path: path.to_string_lossy().to_string(),
+                format,
+            })
+        })
+        .collect();
+
+    themes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(themes)
 }
 
 /// Alacritty TOML形式をパース
@@ -164,7 +465,14 @@ fn parse_alacritty_toml(content: &str) -> Result<ColorScheme, String> {
 }
 
 /// Windows Terminal JSON形式をパース
-fn parse_windows_terminal_json(content: &str) -> Result<ColorScheme, String> {
+///
+/// 単体のスキームオブジェクトと、`schemes` 配列を含む `settings.json` の両方に対応。
+/// `settings.json` の場合は `scheme_name` に一致する `name` のスキームを選択し、
+/// 未指定なら先頭のスキームを使用する
+fn parse_windows_terminal_json(
+    content: &str,
+    scheme_name: Option<&str>,
+) -> Result<ColorScheme, String> {
     #[derive(Deserialize)]
     #[serde(rename_all = "camelCase")]
     struct WinTermTheme {
@@ -190,7 +498,16 @@ fn parse_windows_terminal_json(content: &str) -> Result<ColorScheme, String> {
         bright_white: Option<String>,
     }
 
-    let theme: WinTermTheme = serde_json::from_str(content)
+    let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("Windows Terminal JSON パース失敗: {}", e))?;
+
+    // settings.json形式の場合はschemes配列からスキームを選択
+    let scheme_value = match value.get("schemes").and_then(|s| s.as_array()) {
+        Some(schemes) => select_windows_terminal_scheme(schemes, scheme_name)?.clone(),
+        None => value,
+    };
+
+    let theme: WinTermTheme = serde_json::from_value(scheme_value)
         .map_err(|e| format!("Windows Terminal JSON パース失敗: {}", e))?;
 
     Ok(ColorScheme {
@@ -219,6 +536,33 @@ fn parse_windows_terminal_json(content: &str) -> Result<ColorScheme, String> {
     })
 }
 
+/// settings.jsonのschemes配列から名前でスキームを選択
+fn select_windows_terminal_scheme<'a>(
+    schemes: &'a [serde_json::Value],
+    scheme_name: Option<&str>,
+) -> Result<&'a serde_json::Value, String> {
+    let Some(name) = scheme_name else {
+        return schemes
+            .first()
+            .ok_or_else(|| "Windows Terminal JSON にスキームが含まれていません".to_string());
+    };
+
+    schemes
+        .iter()
+        .find(|s| s.get("name").and_then(|n| n.as_str()) == Some(name))
+        .ok_or_else(|| {
+            let available: Vec<&str> = schemes
+                .iter()
+                .filter_map(|s| s.get("name").and_then(|n| n.as_str()))
+                .collect();
+            format!(
+                "スキーム '{}' が見つかりません (利用可能: {})",
+                name,
+                available.join(", ")
+            )
+        })
+}
+
 /// iTerm2 .itermcolors plist形式をパース
 fn parse_iterm2_plist(content: &str) -> Result<ColorScheme, String> {
     use std::collections::HashMap;
@@ -399,13 +743,60 @@ white = "#ffffff"
 }
 "##;
 
-        let scheme = parse_windows_terminal_json(json).unwrap();
+        let scheme = parse_windows_terminal_json(json, None).unwrap();
         assert_eq!(scheme.background, Some("#1E1E1E".to_string()));
         assert_eq!(scheme.cursor, Some("#FFFFFF".to_string()));
         assert_eq!(scheme.magenta, Some("#CC00CC".to_string()));
         assert_eq!(scheme.bright_magenta, Some("#FF55FF".to_string()));
     }
 
+    #[test]
+    fn test_parse_windows_terminal_settings_schemes() {
+        let json = r##"
+{
+    "profiles": { "defaults": {} },
+    "schemes": [
+        {
+            "name": "Campbell",
+            "background": "#0C0C0C",
+            "foreground": "#CCCCCC",
+            "purple": "#881798"
+        },
+        {
+            "name": "One Half Dark",
+            "background": "#282C34",
+            "foreground": "#DCDFE4",
+            "selectionBackground": "#FFFFFF",
+            "purple": "#C678DD",
+            "brightPurple": "#C678DD"
+        }
+    ]
+}
+"##;
+
+        // 名前で選択
+        let scheme = parse_windows_terminal_json(json, Some("One Half Dark")).unwrap();
+        assert_eq!(scheme.background, Some("#282C34".to_string()));
+        assert_eq!(scheme.selection_background, Some("#FFFFFF".to_string()));
+        assert_eq!(scheme.magenta, Some("#C678DD".to_string()));
+
+        // 未指定なら先頭のスキーム
+        let scheme = parse_windows_terminal_json(json, None).unwrap();
+        assert_eq!(scheme.background, Some("#0C0C0C".to_string()));
+        assert_eq!(scheme.magenta, Some("#881798".to_string()));
+
+        // 存在しない名前はエラー
+        let err = parse_windows_terminal_json(json, Some("Solarized")).unwrap_err();
+        assert!(err.contains("Campbell"));
+        assert!(err.contains("One Half Dark"));
+    }
+
+    #[test]
+    fn test_parse_windows_terminal_settings_empty_schemes() {
+        let json = r#"{ "schemes": [] }"#;
+        assert!(parse_windows_terminal_json(json, None).is_err());
+    }
+
     #[test]
     fn test_parse_iterm2_plist() {
         let plist = r#"
@@ -450,6 +841,208 @@ white = "#ffffff"
         assert_eq!(scheme.black, Some("#000000".to_string()));
     }
 
+    #[test]
+    fn test_supported_theme_formats() {
+        let formats = supported_theme_formats();
+        let listed: Vec<ThemeFormat> = formats.iter().map(|info| info.format).collect();
+        assert_eq!(
+            listed,
+            [
+                ThemeFormat::Alacritty,
+                ThemeFormat::WindowsTerminal,
+                ThemeFormat::Iterm2
+            ]
+        );
+        // 一覧の拡張子はload_theme_fileの判定と一致する
+        for info in &formats {
+            assert!(!info.detection.is_empty());
+            for extension in info.extensions {
+                let path = format!("theme.{}", extension.to_uppercase());
+                assert_eq!(ThemeFormat::from_path(Path::new(&path)), Some(info.format));
+            }
+        }
+        assert_eq!(ThemeFormat::from_path(Path::new("theme.yaml")), None);
+        assert_eq!(supported_extensions_label(), ".toml, .json, .itermcolors");
+    }
+
+    #[test]
+    fn test_list_theme_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("dark.toml"),
+            "[colors.primary]\nbackground = \"#1e1e1e\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.json"), "{ not json").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "background = #000000").unwrap();
+
+        let themes = list_theme_files(dir.path()).unwrap();
+        assert_eq!(themes.len(), 1);
+        assert!(themes[0].path.ends_with("dark.toml"));
+        assert_eq!(themes[0].format, ThemeFormat::Alacritty);
+    }
+
+    #[test]
+    fn test_list_theme_files_missing_dir() {
+        assert!(list_theme_files(Path::new("/nonexistent/themes")).is_err());
+    }
+
+    /// 必須キーをすべて含むAlacrittyテーマ
+    fn complete_alacritty_theme(background: &str) -> String {
+        let ansi = "black = \"#000000\"\nred = \"#cc0000\"\ngreen = \"#00cc00\"\n\
+                    yellow = \"#cccc00\"\nblue = \"#0000cc\"\nmagenta = \"#cc00cc\"\n\
+                    cyan = \"#00cccc\"\nwhite = \"#cccccc\"\n";
+        format!(
+            "[colors.primary]\nbackground = \"{}\"\nforeground = \"#d4d4d4\"\n\
+             [colors.normal]\n{}[colors.bright]\n{}",
+            background, ansi, ansi
+        )
+    }
+
+    #[test]
+    fn test_validate_theme_file_valid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("theme.toml");
+        std::fs::write(&path, complete_alacritty_theme("#1e1e1e")).unwrap();
+
+        let result = validate_theme_file(&path, None);
+        assert!(result.ok, "{:?}", result.problems);
+        assert_eq!(result.format, Some(ThemeFormat::Alacritty));
+        assert!(result.problems.is_empty());
+    }
+
+    #[test]
+    fn test_validate_theme_file_problems() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // 16進でない値
+        let bad_hex = dir.path().join("bad_hex.toml");
+        std::fs::write(&bad_hex, complete_alacritty_theme("dark")).unwrap();
+        let result = validate_theme_file(&bad_hex, None);
+        assert!(!result.ok);
+        assert_eq!(result.problems.len(), 1);
+        assert!(result.problems[0].contains("background"));
+
+        // キー不足
+        let missing = dir.path().join("missing.json");
+        std::fs::write(&missing, r##"{ "background": "#000000" }"##).unwrap();
+        let result = validate_theme_file(&missing, None);
+        assert!(!result.ok);
+        assert_eq!(result.format, Some(ThemeFormat::WindowsTerminal));
+        assert!(result.problems.iter().any(|p| p.contains("foreground")));
+
+        // パースできない
+        let broken = dir.path().join("broken.toml");
+        std::fs::write(&broken, "[colors.primary\nbackground =").unwrap();
+        let result = validate_theme_file(&broken, None);
+        assert!(!result.ok);
+        assert_eq!(result.format, Some(ThemeFormat::Alacritty));
+        assert_eq!(result.problems.len(), 1);
+        assert!(result.problems[0].contains("パース失敗"));
+
+        // 未対応の拡張子
+        let result = validate_theme_file(Path::new("theme.yaml"), None);
+        assert!(!result.ok);
+        assert_eq!(result.format, None);
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut scheme = ColorScheme {
+            background: Some("#000000".to_string()),
+            foreground: Some("#ffffff".to_string()),
+            ..Default::default()
+        };
+        let overrides = HashMap::from([("red".to_string(), "#F00".to_string())]);
+        scheme.apply_overrides(&overrides).unwrap();
+        assert_eq!(scheme.red, Some("#ff0000".to_string()));
+        assert_eq!(scheme.background, Some("#000000".to_string()));
+    }
+
+    #[test]
+    fn test_apply_overrides_rejects_invalid() {
+        let mut scheme = ColorScheme::default();
+        let unknown = HashMap::from([("purple".to_string(), "#ff00ff".to_string())]);
+        assert!(scheme.apply_overrides(&unknown).is_err());
+
+        let not_hex = HashMap::from([
+            ("red".to_string(), "#ff0000".to_string()),
+            ("blue".to_string(), "rgb(0, 0, 255)".to_string()),
+        ]);
+        assert!(scheme.apply_overrides(&not_hex).is_err());
+        // 不正な値があれば一切変更しない
+        assert_eq!(scheme, ColorScheme::default());
+    }
+
+    #[test]
+    fn test_normalize_hex() {
+        assert_eq!(normalize_hex("#FFFFFF"), "#ffffff");
+        assert_eq!(normalize_hex("#fff"), "#ffffff");
+        assert_eq!(normalize_hex("#AbC"), "#aabbcc");
+        assert_eq!(normalize_hex("0x1E1E1E"), "#1e1e1e");
+        assert_eq!(normalize_hex("#1e1e1eCC"), "#1e1e1ecc");
+        assert_eq!(normalize_hex("#ff"), "#0000ff");
+        assert_eq!(normalize_hex(" #D4D4D4 "), "#d4d4d4");
+        // 16進でない値はそのまま
+        assert_eq!(normalize_hex("rgb(0, 0, 0)"), "rgb(0, 0, 0)");
+        assert_eq!(normalize_hex("#gggggg"), "#gggggg");
+    }
+
+    #[test]
+    fn test_normalize_schemes_from_different_sources() {
+        let toml = r##"
+[colors.primary]
+background = "0x1E1E1E"
+foreground = "#d4d4d4"
+
+[colors.normal]
+black = "#000"
+magenta = "#CC00CC"
+"##;
+        let json = r##"
+{
+    "background": "#1E1E1E",
+    "foreground": "#D4D4D4",
+    "black": "#000000",
+    "purple": "#cc00cc"
+}
+"##;
+
+        let mut from_toml = parse_alacritty_toml(toml).unwrap();
+        let mut from_json = parse_windows_terminal_json(json, None).unwrap();
+        assert_ne!(from_toml, from_json);
+
+        from_toml.normalize();
+        from_json.normalize();
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_toml.background, Some("#1e1e1e".to_string()));
+        assert_eq!(from_toml.black, Some("#000000".to_string()));
+    }
+
+    #[test]
+    fn test_color_scheme_round_trip() {
+        let mut scheme = parse_windows_terminal_json(
+            r##"{ "background": "#1E1E1E", "cursorColor": "#FFF", "brightPurple": "#FF55FF" }"##,
+            None,
+        )
+        .unwrap();
+        scheme.normalize();
+
+        // JSON / TOML どちらで往復しても同じ値になる
+        let json = serde_json::to_string(&scheme).unwrap();
+        let from_json: ColorScheme = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, scheme);
+
+        let toml_str = toml::to_string(&scheme).unwrap();
+        let from_toml: ColorScheme = toml::from_str(&toml_str).unwrap();
+        assert_eq!(from_toml, scheme);
+
+        // 正規化は冪等
+        let mut again = scheme.clone();
+        again.normalize();
+        assert_eq!(again, scheme);
+    }
+
     #[test]
     fn test_rgb_float_to_hex() {
         fn rgb_float_to_hex(r: f64, g: f64, b: f64) -> String {
@@ -1,6 +1,9 @@
 use crate::color_scheme::{load_theme_file, ColorScheme};
+use crate::error::AppError;
+use crate::logging::{self, LogLevel};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// プロジェクト設定全体
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -22,11 +25,43 @@ pub struct SphinxConfig {
     pub source_dir: String,
     #[serde(default = "default_build_dir")]
     pub build_dir: String,
+    /// conf.pyのディレクトリ（None = source_dir）。sphinx-buildの `-c` に渡す
+    #[serde(default)]
+    pub config_dir: Option<String>,
     #[serde(default)]
     pub server: ServerConfig,
     /// sphinx-autobuild への追加引数
     #[serde(default)]
     pub extra_args: Vec<String>,
+    /// 各ビルドの前に実行するコマンド（`--pre-build`、指定順に実行）
+    #[serde(default)]
+    pub pre_build: Vec<String>,
+    /// `sphinx_log` の行から色などのエスケープシーケンスを除去する（元の行は `raw` に残す）
+    #[serde(default)]
+    pub strip_ansi: bool,
+    /// ブラウザを開くまでの待ち時間（秒、`--delay`）。server.open_browser有効時のみ意味がある
+    #[serde(default)]
+    pub delay_secs: Option<u64>,
+    /// 変更を無視するパスの正規表現（`--re-ignore`）
+    #[serde(default)]
+    pub re_ignore: Vec<String>,
+    /// sphinx-autobuildに設定するロケール（`LC_ALL` / `LANG`）
+    /// ビルド完了などのログ判定は英語のメッセージを前提とするため既定は "C"。
+    /// 空文字列なら環境のロケールを引き継ぐ（ログは翻訳されるが判定が効かなくなる）
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// 起動時の初回ビルドを省略し、変更があるまで待つ（`--no-initial`）
+    #[serde(default)]
+    pub no_initial: bool,
+    /// ビルドごとに更新された出力ファイルを `sphinx_output_changed` で通知する
+    #[serde(default)]
+    pub notify_output_changes: bool,
+    /// `sphinx_log` の1行の上限（バイト、None = 8KiB）。超えた行は切り詰めて通知する
+    #[serde(default)]
+    pub max_log_line_bytes: Option<usize>,
+    /// 起動前にビルドディレクトリへ書き込めるか確認し、書き込めなければ起動しない
+    #[serde(default)]
+    pub check_build_dir: bool,
 }
 
 /// sphinx-autobuildサーバー設定
@@ -34,6 +69,31 @@ pub struct SphinxConfig {
 pub struct ServerConfig {
     #[serde(default)]
     pub port: u16, // 0 = 自動割り当て
+    /// 指定ポートが使用中の場合に空きポートへ切り替えるか（false = エラー）
+    #[serde(default)]
+    pub fallback_to_random: bool,
+    /// sphinx-autobuild自身にブラウザを開かせるか
+    /// アプリ内プレビューと二重に表示されるため通常はfalseのまま
+    #[serde(default)]
+    pub open_browser: bool,
+    /// ヘルスチェックの間隔（秒）。応答しない状態が続くと `sphinx_unhealthy` を通知
+    /// None = 無効
+    #[serde(default)]
+    pub health_check_interval_secs: Option<u64>,
+    /// リバースプロキシ配下でプレビューを埋め込む場合のパスプレフィックス
+    /// sphinx自体はルートで配信し、プレビューURLにのみ付与する
+    #[serde(default)]
+    pub base_url_path: Option<String>,
+    /// 再起動待ちの初期値（ミリ秒）。試行ごとに倍になる（None = 1000）
+    #[serde(default)]
+    pub restart_backoff_ms: Option<u64>,
+    /// 再起動待ちの上限（ミリ秒）（None = 30000）
+    #[serde(default)]
+    pub restart_backoff_max_ms: Option<u64>,
+    /// 再起動待ちに加える揺らぎの割合（0.0〜1.0、None = 0.2）
+    /// 複数プロジェクトの再起動が同時に集中しないようにする
+    #[serde(default)]
+    pub restart_jitter: Option<f64>,
 }
 
 /// Python環境設定
@@ -48,6 +108,38 @@ pub struct PythonConfig {
 pub struct EditorConfig {
     #[serde(default = "default_editor")]
     pub command: String,
+    /// エディタで開く拡張子（それ以外の画像などは既定のアプリで開く）
+    #[serde(default = "default_text_extensions")]
+    pub text_extensions: Vec<String>,
+}
+
+/// ファイルを開く先
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenTarget {
+    /// 設定のエディタ
+    Editor,
+    /// OSの既定のアプリ
+    Default,
+}
+
+impl EditorConfig {
+    /// 拡張子からエディタと既定のアプリのどちらで開くかを決める
+    /// 拡張子のないファイル（Makefileなど）はテキストとみなす
+    pub fn open_target(&self, path: &Path) -> OpenTarget {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return OpenTarget::Editor;
+        };
+        let is_text = self
+            .text_extensions
+            .iter()
+            .any(|text| text.trim_start_matches('.').eq_ignore_ascii_case(extension));
+        if is_text {
+            OpenTarget::Editor
+        } else {
+            OpenTarget::Default
+        }
+    }
 }
 
 /// ターミナル設定
@@ -56,6 +148,12 @@ pub struct TerminalConfig {
     /// シェルパス (None = $SHELL から自動検出)
     #[serde(default)]
     pub shell: Option<String>,
+    /// シェルの引数（指定するとログインシェルの `-l` の代わりに使う。`~` は展開しない）
+    #[serde(default)]
+    pub shell_args: Vec<String>,
+    /// shellが未指定の場合に順に試すシェル（最初に実行できたものを使う。どれもなければ$SHELL）
+    #[serde(default)]
+    pub shell_fallbacks: Vec<String>,
     /// フォントファミリー
     #[serde(default)]
     pub font_family: Option<String>,
@@ -65,9 +163,25 @@ pub struct TerminalConfig {
     /// テーマファイルパス（Alacritty/WindowsTerminal/iTerm2形式）
     #[serde(default)]
     pub theme_file: Option<String>,
+    /// テーマファイル内のスキーム名（Windows Terminalのsettings.json用）
+    #[serde(default)]
+    pub theme_name: Option<String>,
     /// インラインカラースキーム（theme_fileより優先）
     #[serde(default)]
     pub color_scheme: Option<ColorScheme>,
+    /// 解決したテーマに重ねる個別の色（`background`, `red` などのキーと16進値）
+    #[serde(default)]
+    pub overrides: Option<HashMap<String, String>>,
+    /// 入出力のない状態がこの秒数続いたセッションを自動終了（None = 無効）
+    #[serde(default)]
+    pub idle_kill_secs: Option<u64>,
+    /// 貼り付けサイズの上限（バイト、None = 1MiB）。超えた貼り付けは書き込まない
+    #[serde(default)]
+    pub max_paste_bytes: Option<usize>,
+    /// PTYのslaveを最初の出力を読むまで閉じない（起動直後の出力の取りこぼし対策）
+    /// 何も出力せずに終了したシェルの終了を検出できなくなるため、既定はfalse
+    #[serde(default)]
+    pub defer_slave_drop: bool,
 }
 
 // デフォルト値関数
@@ -79,6 +193,10 @@ fn default_build_dir() -> String {
     "_build/html".to_string()
 }
 
+fn default_locale() -> String {
+    "C".to_string()
+}
+
 fn default_interpreter() -> String {
     "python".to_string()
 }
@@ -87,13 +205,33 @@ fn default_editor() -> String {
     "nvim".to_string()
 }
 
+fn default_text_extensions() -> Vec<String> {
+    [
+        "rst", "md", "txt", "py", "toml", "cfg", "ini", "json", "yaml", "yml", "css", "js", "html",
+        "csv", "inc",
+    ]
+    .iter()
+    .map(|e| e.to_string())
+    .collect()
+}
+
 impl Default for SphinxConfig {
     fn default() -> Self {
         Self {
             source_dir: default_source_dir(),
             build_dir: default_build_dir(),
+            config_dir: None,
             server: ServerConfig::default(),
             extra_args: Vec::new(),
+            pre_build: Vec::new(),
+            strip_ansi: false,
+            delay_secs: None,
+            re_ignore: Vec::new(),
+            locale: default_locale(),
+            no_initial: false,
+            notify_output_changes: false,
+            max_log_line_bytes: None,
+            check_build_dir: false,
         }
     }
 }
@@ -106,48 +244,513 @@ impl Default for PythonConfig {
     }
 }
 
+/// プロジェクト内のvenvを探す順序
+const VENV_PYTHON_CANDIDATES: &[&str] = &[
+    ".venv/bin/python",
+    "venv/bin/python",
+    ".venv/Scripts/python.exe",
+];
+
+/// PATHから探すインタプリタ名
+const PATH_PYTHON_CANDIDATES: &[&str] = &["python3", "python"];
+
+/// Pythonインタプリタを推測する（設定画面の自動検出用）
+/// プロジェクト内のvenv → PATHの python3 / python の順に、最初に見つかったものを返す
+pub fn detect_python(project_path: &Path) -> Option<String> {
+    detect_python_in(project_path, std::env::var_os("PATH").as_deref())
+}
+
+fn detect_python_in(project_path: &Path, path_var: Option<&std::ffi::OsStr>) -> Option<String> {
+    let venv = VENV_PYTHON_CANDIDATES
+        .iter()
+        .map(|candidate| project_path.join(candidate))
+        .find(|path| path.is_file());
+    if let Some(path) = venv {
+        return Some(path.to_string_lossy().to_string());
+    }
+
+    let dirs: Vec<PathBuf> = path_var
+        .map(|paths| std::env::split_paths(paths).collect())
+        .unwrap_or_default();
+    PATH_PYTHON_CANDIDATES.iter().find_map(|name| {
+        dirs.iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .map(|path| path.to_string_lossy().to_string())
+    })
+}
+
 impl Default for EditorConfig {
     fn default() -> Self {
         Self {
             command: default_editor(),
+            text_extensions: default_text_extensions(),
+        }
+    }
+}
+
+/// ベースのカラースキームに個別の色を重ねる（不正な指定は警告して無視）
+fn layer_color_overrides(
+    color_scheme: &mut Option<ColorScheme>,
+    overrides: Option<&HashMap<String, String>>,
+) {
+    let Some(overrides) = overrides else {
+        return;
+    };
+    let mut scheme = color_scheme.clone().unwrap_or_default();
+    match scheme.apply_overrides(overrides) {
+        Ok(()) => *color_scheme = Some(scheme),
+        Err(e) => {
+            if logging::enabled(LogLevel::Error) {
+                eprintln!("カラー上書きエラー: {}", e);
+            }
         }
     }
 }
 
+/// カラースキームの出所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeSource {
+    /// インラインのcolor_scheme
+    Inline,
+    /// theme_fileから読み込んだ
+    ThemeFile,
+    /// 指定なし（フロントエンドのデフォルトテーマを使用）
+    None,
+}
+
+/// theme_fileの解決結果（設定ミスの診断用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThemeResolution {
+    /// 読み込もうとした絶対パス（theme_file未指定ならNone）
+    pub resolved_path: Option<String>,
+    pub loaded: bool,
+    pub error: Option<String>,
+}
+
+/// 解決済みのターミナルテーマ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedTheme {
+    pub color_scheme: Option<ColorScheme>,
+    pub source: ThemeSource,
+}
+
 impl TerminalConfig {
-    /// theme_fileからカラースキームを解決
-    /// color_schemeが設定済みの場合はそのまま、
-    /// theme_fileが設定されている場合はファイルを読み込んでcolor_schemeに変換
-    pub fn resolve_color_scheme(&mut self, base_path: Option<&std::path::Path>) {
-        // color_schemeが既に設定されている場合はそのまま
-        if self.color_scheme.is_some() {
-            return;
+    /// theme_fileからカラースキームを解決し、どこから得たかを返す
+    /// color_schemeが設定済みの場合は正規化のみ、
+    /// theme_fileが設定されている場合はファイルを読み込んでcolor_schemeに変換し、
+    /// 最後にoverridesを重ねる
+    pub fn resolve_color_scheme(&mut self, base_path: Option<&Path>) -> ThemeSource {
+        let source = self.resolve_base_color_scheme(base_path);
+        layer_color_overrides(&mut self.color_scheme, self.overrides.as_ref());
+        source
+    }
+
+    /// overridesを適用する前のベースのカラースキームを解決
+    fn resolve_base_color_scheme(&mut self, base_path: Option<&Path>) -> ThemeSource {
+        // color_schemeが既に設定されている場合は正規化のみ
+        if let Some(ref mut scheme) = self.color_scheme {
+            scheme.normalize();
+            return ThemeSource::Inline;
         }
 
         // theme_fileが設定されている場合はファイルを読み込む
-        if let Some(ref theme_file) = self.theme_file {
-            let theme_path = if let Some(base) = base_path {
-                base.join(theme_file)
-            } else {
-                PathBuf::from(theme_file)
-            };
-
-            match load_theme_file(&theme_path) {
+        if let Some((_, result)) = self.load_theme_file(base_path) {
+            match result {
                 Ok(scheme) => {
                     self.color_scheme = Some(scheme);
+                    return ThemeSource::ThemeFile;
                 }
                 Err(e) => {
-                    eprintln!("テーマファイル読み込みエラー: {}", e);
+                    if logging::enabled(LogLevel::Error) {
+                        eprintln!("テーマファイル読み込みエラー: {}", e);
+                    }
                 }
             }
         }
+
+        ThemeSource::None
+    }
+
+    /// theme_fileを解決したパスと読み込み結果（theme_file未指定ならNone）
+    fn load_theme_file(
+        &self,
+        base_path: Option<&Path>,
+    ) -> Option<(PathBuf, Result<ColorScheme, String>)> {
+        let theme_file = self.theme_file.as_ref()?;
+        let theme_path = match base_path {
+            Some(base) => base.join(theme_file),
+            None => PathBuf::from(theme_file),
+        };
+        let result = load_theme_file(&theme_path, self.theme_name.as_deref());
+        Some((theme_path, result))
+    }
+
+    /// theme_fileをどのパスとして読み込もうとしたか、その結果を返す
+    pub fn theme_resolution(&self, base_path: Option<&Path>) -> ThemeResolution {
+        match self.load_theme_file(base_path) {
+            Some((path, result)) => ThemeResolution {
+                resolved_path: Some(path.to_string_lossy().to_string()),
+                loaded: result.is_ok(),
+                error: result.err(),
+            },
+            None => ThemeResolution {
+                resolved_path: None,
+                loaded: false,
+                error: None,
+            },
+        }
+    }
+
+    /// 24bitカラーのテーマを使う設定か（色はすべて16進指定のため）
+    pub fn uses_truecolor(&self) -> bool {
+        self.color_scheme.is_some()
+            || self.theme_file.is_some()
+            || self.overrides.as_ref().is_some_and(|o| !o.is_empty())
+    }
+
+    /// 優先順位（インライン > theme_file > なし）に従って最終的なテーマを求める
+    pub fn resolved_theme(&self, base_path: Option<&Path>) -> ResolvedTheme {
+        let mut terminal = self.clone();
+        let source = terminal.resolve_color_scheme(base_path);
+        ResolvedTheme {
+            color_scheme: terminal.color_scheme,
+            source,
+        }
+    }
+}
+
+/// 設定ディレクトリの書き込み可否（設定の保存ボタンを出すかの判断用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigDirStatus {
+    pub path: String,
+    pub writable: bool,
+}
+
+/// ディレクトリを作成し、一時ファイルの書き込み・削除ができるか試す
+pub(crate) fn probe_dir_writable(dir: &Path) -> bool {
+    if std::fs::create_dir_all(dir).is_err() {
+        return false;
+    }
+    let probe = dir.join(format!(".orthrus-write-test-{}", std::process::id()));
+    if std::fs::write(&probe, b"").is_err() {
+        return false;
+    }
+    std::fs::remove_file(&probe).is_ok()
+}
+
+/// 設定の変更点（`config_diff` の要素）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigChange {
+    /// `sphinx.server.port` のようなドット区切りのパス
+    pub path: String,
+    /// 変更前の値（未設定ならNone）
+    pub old: Option<String>,
+    /// 変更後の値（未設定ならNone）
+    pub new: Option<String>,
+}
+
+/// 2つの設定の差分を、値が異なる項目ごとにパス順で列挙する（保存前の確認表示用）
+/// 配列は要素単位ではなく全体を1つの値として比較する
+pub fn config_diff(old: &Config, new: &Config) -> Vec<ConfigChange> {
+    let old = serde_json::to_value(old).unwrap_or_default();
+    let new = serde_json::to_value(new).unwrap_or_default();
+    let mut changes = Vec::new();
+    diff_values(String::new(), &old, &new, &mut changes);
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+fn diff_values(
+    path: String,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    changes: &mut Vec<ConfigChange>,
+) {
+    use serde_json::Value;
+
+    if old == new {
+        return;
+    }
+    if let (Value::Object(old_map), Value::Object(new_map)) = (old, new) {
+        let keys = old_map
+            .keys()
+            .chain(new_map.keys().filter(|key| !old_map.contains_key(*key)));
+        for key in keys {
+            let child_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            diff_values(
+                child_path,
+                old_map.get(key).unwrap_or(&Value::Null),
+                new_map.get(key).unwrap_or(&Value::Null),
+                changes,
+            );
+        }
+        return;
+    }
+
+    // テーブルが片側だけ未設定の場合も、設定された側の各項目を差分として出す
+    if let Value::Object(map) = old {
+        if new.is_null() {
+            for (key, value) in map {
+                diff_values(format!("{}.{}", path, key), value, &Value::Null, changes);
+            }
+            return;
+        }
+    }
+    if let Value::Object(map) = new {
+        if old.is_null() {
+            for (key, value) in map {
+                diff_values(format!("{}.{}", path, key), &Value::Null, value, changes);
+            }
+            return;
+        }
+    }
+
+    changes.push(ConfigChange {
+        path,
+        old: display_value(old),
+        new: display_value(new),
+    });
+}
+
+/// 差分表示用の文字列（文字列はクォートなし、それ以外はJSON表記）
+fn display_value(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// 設定文字列のフォーマット
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+/// プロジェクトごとの設定ファイル名
+const PROJECT_CONFIG_FILE: &str = ".orthrus.toml";
+
+/// 設定ファイルのパスを上書きする環境変数（埋め込み先やテスト用）
+const CONFIG_PATH_ENV: &str = "ORTHRUS_CONFIG";
+
+/// バイト位置を1始まりの行・列（列は文字単位）に変換
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let mut end = offset.min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let before = &content[..end];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// TOMLのパースエラーを位置付きのエラーにする
+fn toml_parse_error(e: &toml::de::Error, content: &str, path: Option<&Path>) -> AppError {
+    let position = e.span().map(|span| line_column(content, span.start));
+    AppError::ConfigParse {
+        path: path.map(|path| path.to_string_lossy().to_string()),
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+        message: e.message().trim().to_string(),
+    }
+}
+
+/// JSONのパースエラーを位置付きのエラーにする
+fn json_parse_error(e: &serde_json::Error, path: Option<&Path>) -> AppError {
+    // 位置が分からない場合（入出力エラーなど）は0になる
+    let line = Some(e.line()).filter(|&line| line > 0);
+    let message = e.to_string();
+    // 位置は別フィールドで返すので、末尾の " at line X column Y" は除く
+    let message = match message.rfind(" at line ") {
+        Some(i) if line.is_some() => message[..i].to_string(),
+        _ => message,
+    };
+    AppError::ConfigParse {
+        path: path.map(|path| path.to_string_lossy().to_string()),
+        line,
+        column: line.map(|_| e.column().max(1)),
+        message,
+    }
+}
+
+/// 上書き値があれば置き換える
+fn override_value<T: Clone>(target: &mut T, value: &Option<T>) {
+    if let Some(v) = value {
+        *target = v.clone();
+    }
+}
+
+/// 相対パスをbaseを基準にした絶対パスに置き換える
+fn absolutize(path: &mut Option<String>, base: &Path) {
+    if let Some(value) = path.as_mut() {
+        if Path::new(value.as_str()).is_relative() {
+            *value = base.join(value.as_str()).to_string_lossy().to_string();
+        }
+    }
+}
+
+/// 上書き値があればSomeで置き換える
+fn override_option<T: Clone>(target: &mut Option<T>, value: &Option<T>) {
+    if value.is_some() {
+        *target = value.clone();
     }
 }
 
 impl Config {
-    /// XDG_CONFIG_HOME/orthrus/config.toml から設定を読み込む
+    /// 部分上書きを適用（指定のないフィールドは元の値を維持）
+    pub fn apply_override(&mut self, config_override: &ConfigOverride) {
+        if let Some(ref sphinx) = config_override.sphinx {
+            override_value(&mut self.sphinx.source_dir, &sphinx.source_dir);
+            override_value(&mut self.sphinx.build_dir, &sphinx.build_dir);
+            override_option(&mut self.sphinx.config_dir, &sphinx.config_dir);
+            override_value(&mut self.sphinx.extra_args, &sphinx.extra_args);
+            override_value(&mut self.sphinx.pre_build, &sphinx.pre_build);
+            override_value(&mut self.sphinx.strip_ansi, &sphinx.strip_ansi);
+            override_option(&mut self.sphinx.delay_secs, &sphinx.delay_secs);
+            override_value(&mut self.sphinx.re_ignore, &sphinx.re_ignore);
+            override_value(&mut self.sphinx.locale, &sphinx.locale);
+            override_value(&mut self.sphinx.no_initial, &sphinx.no_initial);
+            override_value(
+                &mut self.sphinx.notify_output_changes,
+                &sphinx.notify_output_changes,
+            );
+            override_option(
+                &mut self.sphinx.max_log_line_bytes,
+                &sphinx.max_log_line_bytes,
+            );
+            override_value(&mut self.sphinx.check_build_dir, &sphinx.check_build_dir);
+            if let Some(ref server) = sphinx.server {
+                override_value(&mut self.sphinx.server.port, &server.port);
+                override_value(
+                    &mut self.sphinx.server.fallback_to_random,
+                    &server.fallback_to_random,
+                );
+                override_value(&mut self.sphinx.server.open_browser, &server.open_browser);
+                override_option(
+                    &mut self.sphinx.server.health_check_interval_secs,
+                    &server.health_check_interval_secs,
+                );
+                override_option(&mut self.sphinx.server.base_url_path, &server.base_url_path);
+                override_option(
+                    &mut self.sphinx.server.restart_backoff_ms,
+                    &server.restart_backoff_ms,
+                );
+                override_option(
+                    &mut self.sphinx.server.restart_backoff_max_ms,
+                    &server.restart_backoff_max_ms,
+                );
+                override_option(
+                    &mut self.sphinx.server.restart_jitter,
+                    &server.restart_jitter,
+                );
+            }
+        }
+        if let Some(ref python) = config_override.python {
+            override_value(&mut self.python.interpreter, &python.interpreter);
+        }
+        if let Some(ref editor) = config_override.editor {
+            override_value(&mut self.editor.command, &editor.command);
+            override_value(&mut self.editor.text_extensions, &editor.text_extensions);
+        }
+        if let Some(ref terminal) = config_override.terminal {
+            override_option(&mut self.terminal.shell, &terminal.shell);
+            override_value(&mut self.terminal.shell_args, &terminal.shell_args);
+            override_value(
+                &mut self.terminal.shell_fallbacks,
+                &terminal.shell_fallbacks,
+            );
+            override_option(&mut self.terminal.font_family, &terminal.font_family);
+            override_option(&mut self.terminal.font_size, &terminal.font_size);
+            override_option(&mut self.terminal.theme_file, &terminal.theme_file);
+            override_option(&mut self.terminal.theme_name, &terminal.theme_name);
+            if terminal.clear_color_scheme {
+                self.terminal.color_scheme = None;
+            }
+            override_option(&mut self.terminal.color_scheme, &terminal.color_scheme);
+            override_option(&mut self.terminal.overrides, &terminal.overrides);
+            override_option(&mut self.terminal.idle_kill_secs, &terminal.idle_kill_secs);
+            override_option(
+                &mut self.terminal.max_paste_bytes,
+                &terminal.max_paste_bytes,
+            );
+            override_value(
+                &mut self.terminal.defer_slave_drop,
+                &terminal.defer_slave_drop,
+            );
+        }
+    }
+
+    /// グローバル設定にプロジェクトの .orthrus.toml を上書きとして適用して読み込む
+    /// theme_fileの相対パスは、グローバル設定のものは設定ディレクトリ、
+    /// .orthrus.toml のものはプロジェクトディレクトリを基準に解決する
+    pub fn load_for_project(project_path: &Path) -> Result<Self, AppError> {
+        let mut config = Self::load_for_project_unresolved(project_path)?;
+        config.terminal.resolve_color_scheme(Some(project_path));
+        Ok(config)
+    }
+
+    /// `load_for_project` のカラースキームを解決する前の状態（テーマの診断用）
+    /// theme_fileは絶対パスに置き換え済み
+    pub fn load_for_project_unresolved(project_path: &Path) -> Result<Self, AppError> {
+        let mut config = Self::load()?;
+        config.apply_project_config(&Self::config_dir(), project_path)?;
+        Ok(config)
+    }
+
+    /// .orthrus.toml を上書きとして適用する（ファイルがなければtheme_fileの解決のみ）
+    fn apply_project_config(
+        &mut self,
+        config_dir: &Path,
+        project_path: &Path,
+    ) -> Result<(), AppError> {
+        absolutize(&mut self.terminal.theme_file, config_dir);
+
+        let project_config_path = project_path.join(PROJECT_CONFIG_FILE);
+        if project_config_path.exists() {
+            let content = std::fs::read_to_string(&project_config_path)
+                .map_err(|e| format!("プロジェクト設定の読み込みに失敗: {}", e))?;
+            let mut project_override: ConfigOverride = toml::from_str(&content)
+                .map_err(|e| toml_parse_error(&e, &content, Some(&project_config_path)))?;
+            if let Some(ref mut terminal) = project_override.terminal {
+                absolutize(&mut terminal.theme_file, project_path);
+            }
+            self.apply_override(&project_override);
+        }
+
+        Ok(())
+    }
+
+    /// グローバル設定ディレクトリ（theme_fileの相対パスの基準）
+    pub fn config_dir() -> PathBuf {
+        Self::config_path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// 設定ディレクトリに書き込めるか（なければ作成してから確認する）
+    pub fn config_dir_status() -> ConfigDirStatus {
+        let dir = Self::config_dir();
+        ConfigDirStatus {
+            writable: probe_dir_writable(&dir),
+            path: dir.to_string_lossy().to_string(),
+        }
+    }
+
+    /// 設定ファイルから設定を読み込む（パスは `config_path` を参照）
     /// 設定ファイルが存在しない場合はデフォルト値を返す
-    pub fn load() -> Result<Self, String> {
+    pub fn load() -> Result<Self, AppError> {
         let config_path = Self::config_path();
 
         if !config_path.exists() {
@@ -157,12 +760,35 @@ impl Config {
         let content = std::fs::read_to_string(&config_path)
             .map_err(|e| format!("設定ファイルの読み込みに失敗: {}", e))?;
 
-        toml::from_str(&content).map_err(|e| format!("設定ファイルのパースに失敗: {}", e))
+        toml::from_str(&content).map_err(|e| toml_parse_error(&e, &content, Some(&config_path)))
+    }
+
+    /// TOML文字列から読み込む（省略したフィールドはデフォルト値）
+    pub fn from_toml_str(content: &str) -> Result<Self, AppError> {
+        toml::from_str(content).map_err(|e| toml_parse_error(&e, content, None))
+    }
+
+    /// JSON文字列から読み込む（省略したフィールドはデフォルト値）
+    pub fn from_json_str(content: &str) -> Result<Self, AppError> {
+        serde_json::from_str(content).map_err(|e| json_parse_error(&e, None))
+    }
+
+    /// 指定フォーマットの文字列から読み込む
+    pub fn from_str_with_format(content: &str, format: ConfigFormat) -> Result<Self, AppError> {
+        match format {
+            ConfigFormat::Toml => Self::from_toml_str(content),
+            ConfigFormat::Json => Self::from_json_str(content),
+        }
     }
 
     /// 設定ファイルのパスを取得
-    /// XDG_CONFIG_HOME/orthrus/config.toml または ~/.config/orthrus/config.toml
-    fn config_path() -> PathBuf {
+    /// `ORTHRUS_CONFIG` が設定されていればそのパス、
+    /// なければ XDG_CONFIG_HOME/orthrus/config.toml または ~/.config/orthrus/config.toml
+    pub fn config_path() -> PathBuf {
+        if let Some(path) = std::env::var_os(CONFIG_PATH_ENV).filter(|path| !path.is_empty()) {
+            return PathBuf::from(path);
+        }
+
         let config_dir = std::env::var("XDG_CONFIG_HOME")
             .map(PathBuf::from)
             .unwrap_or_else(|_| dirs::home_dir().unwrap_or_default().join(".config"));
@@ -180,6 +806,9 @@ pub struct DevConfig {
     /// sphinx-autobuildを自動起動するか
     #[serde(default = "default_auto_start_sphinx")]
     pub auto_start_sphinx: bool,
+    /// 最初のビルドが完了したらプレビューをブラウザで開くか
+    #[serde(default)]
+    pub auto_open_browser: bool,
     /// グローバル設定の上書き（部分的に指定可能）
     #[serde(default)]
     pub config: Option<ConfigOverride>,
@@ -205,15 +834,49 @@ pub struct SphinxConfigOverride {
     #[serde(default)]
     pub build_dir: Option<String>,
     #[serde(default)]
+    pub config_dir: Option<String>,
+    #[serde(default)]
     pub server: Option<ServerConfigOverride>,
     #[serde(default)]
     pub extra_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub pre_build: Option<Vec<String>>,
+    #[serde(default)]
+    pub strip_ansi: Option<bool>,
+    #[serde(default)]
+    pub delay_secs: Option<u64>,
+    #[serde(default)]
+    pub re_ignore: Option<Vec<String>>,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub no_initial: Option<bool>,
+    #[serde(default)]
+    pub notify_output_changes: Option<bool>,
+    #[serde(default)]
+    pub max_log_line_bytes: Option<usize>,
+    #[serde(default)]
+    pub check_build_dir: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ServerConfigOverride {
     #[serde(default)]
     pub port: Option<u16>,
+    #[serde(default)]
+    pub fallback_to_random: Option<bool>,
+    #[serde(default)]
+    pub open_browser: Option<bool>,
+    #[serde(default)]
+    pub health_check_interval_secs: Option<u64>,
+    #[serde(default)]
+    pub base_url_path: Option<String>,
+    #[serde(default)]
+    pub restart_backoff_ms: Option<u64>,
+    #[serde(default)]
+    pub restart_backoff_max_ms: Option<u64>,
+    #[serde(default)]
+    pub restart_jitter: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -226,6 +889,8 @@ pub struct PythonConfigOverride {
 pub struct EditorConfigOverride {
     #[serde(default)]
     pub command: Option<String>,
+    #[serde(default)]
+    pub text_extensions: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -233,22 +898,47 @@ pub struct TerminalConfigOverride {
     #[serde(default)]
     pub shell: Option<String>,
     #[serde(default)]
+    pub shell_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub shell_fallbacks: Option<Vec<String>>,
+    #[serde(default)]
     pub font_family: Option<String>,
     #[serde(default)]
     pub font_size: Option<u16>,
     #[serde(default)]
     pub theme_file: Option<String>,
     #[serde(default)]
+    pub theme_name: Option<String>,
+    #[serde(default)]
     pub color_scheme: Option<ColorScheme>,
+    /// trueならベース設定のインラインcolor_schemeを取り消し、theme_fileにフォールバックさせる
+    /// （`color_scheme` のNoneは「上書きしない」の意味なので、取り消しはこのフラグで表す）
+    /// 同じ上書きで `color_scheme` も指定した場合はそちらが使われる
+    #[serde(default)]
+    pub clear_color_scheme: bool,
+    #[serde(default)]
+    pub overrides: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub idle_kill_secs: Option<u64>,
+    #[serde(default)]
+    pub max_paste_bytes: Option<usize>,
+    #[serde(default)]
+    pub defer_slave_drop: Option<bool>,
 }
 
 impl TerminalConfigOverride {
-    /// theme_fileからカラースキームを解決
-    /// color_schemeが設定済みの場合はそのまま、
-    /// theme_fileが設定されている場合はファイルを読み込んでcolor_schemeに変換
+    /// theme_fileからカラースキームを解決し、overridesを重ねる
     pub fn resolve_color_scheme(&mut self) {
-        // color_schemeが既に設定されている場合はそのまま
-        if self.color_scheme.is_some() {
+        self.resolve_base_color_scheme();
+        layer_color_overrides(&mut self.color_scheme, self.overrides.as_ref());
+    }
+
+    /// color_schemeが設定済みの場合は正規化のみ、
+    /// theme_fileが設定されている場合はファイルを読み込んでcolor_schemeに変換
+    fn resolve_base_color_scheme(&mut self) {
+        // color_schemeが既に設定されている場合は正規化のみ
+        if let Some(ref mut scheme) = self.color_scheme {
+            scheme.normalize();
             return;
         }
 
@@ -257,12 +947,14 @@ impl TerminalConfigOverride {
             // DevConfigのtheme_fileは絶対パスを想定
             let theme_path = PathBuf::from(theme_file);
 
-            match load_theme_file(&theme_path) {
+            match load_theme_file(&theme_path, self.theme_name.as_deref()) {
                 Ok(scheme) => {
                     self.color_scheme = Some(scheme);
                 }
                 Err(e) => {
-                    eprintln!("テーマファイル読み込みエラー: {}", e);
+                    if logging::enabled(LogLevel::Error) {
+                        eprintln!("テーマファイル読み込みエラー: {}", e);
+                    }
                 }
             }
         }
@@ -278,11 +970,15 @@ impl DevConfig {
     /// カレントディレクトリと親ディレクトリを順に探索
     pub fn load() -> Option<Self> {
         let current_dir = std::env::current_dir().ok()?;
+        Self::load_from(&current_dir)
+    }
 
+    /// dirとその親ディレクトリから.orthrus.dev.jsonを読み込む（呼ぶたびにファイルを読み直す）
+    pub fn load_from(dir: &Path) -> Option<Self> {
         // カレントディレクトリと親ディレクトリを順に探索
         // （Tauri devモードではback/から実行されるため）
-        let mut candidates = vec![current_dir.join(".orthrus.dev.json")];
-        if let Some(parent) = current_dir.parent() {
+        let mut candidates = vec![dir.join(".orthrus.dev.json")];
+        if let Some(parent) = dir.parent() {
             candidates.push(parent.join(".orthrus.dev.json"));
         }
 
@@ -307,6 +1003,7 @@ mod tests {
         assert_eq!(config.sphinx.source_dir, "docs");
         assert_eq!(config.sphinx.build_dir, "_build/html");
         assert_eq!(config.sphinx.server.port, 0);
+        assert!(!config.sphinx.server.fallback_to_random);
         assert_eq!(config.python.interpreter, "python");
         assert_eq!(config.editor.command, "nvim");
         assert!(config.terminal.shell.is_none());
@@ -365,6 +1062,50 @@ mod tests {
         std::env::remove_var("XDG_CONFIG_HOME");
     }
 
+    #[test]
+    fn test_config_path_env_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.toml");
+        std::env::set_var(CONFIG_PATH_ENV, &path);
+        assert_eq!(Config::config_path(), path);
+        // theme_fileの基準や書き込み確認もこのディレクトリになる
+        assert_eq!(Config::config_dir(), dir.path());
+        std::env::remove_var(CONFIG_PATH_ENV);
+        assert_ne!(Config::config_path(), path);
+    }
+
+    #[test]
+    fn test_probe_dir_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        // 存在しなければ作成する
+        let config_dir = dir.path().join("xdg").join("orthrus");
+        assert!(probe_dir_writable(&config_dir));
+        assert!(config_dir.is_dir());
+        // 確認用のファイルは残さない
+        assert_eq!(std::fs::read_dir(&config_dir).unwrap().count(), 0);
+
+        // 親がファイルなので作成できない
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(!probe_dir_writable(&file.join("orthrus")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_probe_dir_writable_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+        // rootは権限に関係なく書き込めるため確認できない
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let read_only = dir.path().join("orthrus");
+        std::fs::create_dir(&read_only).unwrap();
+        std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o555)).unwrap();
+        assert!(!probe_dir_writable(&read_only));
+        std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
     #[test]
     fn test_dev_config_parse_camel_case() {
         // ユーザーが使用するキャメルケースのJSONをパースできるか確認
@@ -438,4 +1179,557 @@ mod tests {
         assert_eq!(terminal.font_family, Some("Fira Code".to_string()));
         assert_eq!(terminal.font_size, Some(18));
     }
+
+    #[test]
+    fn test_dev_config_reload_picks_up_changes() {
+        let root = tempfile::tempdir().unwrap();
+        let back = root.path().join("back");
+        std::fs::create_dir(&back).unwrap();
+        assert!(DevConfig::load_from(&back).is_none());
+
+        // 親ディレクトリのファイルも探す
+        let path = root.path().join(".orthrus.dev.json");
+        std::fs::write(&path, r#"{"project_path": "/tmp/a"}"#).unwrap();
+        let first = DevConfig::load_from(&back).unwrap();
+        assert_eq!(first.project_path.as_deref(), Some("/tmp/a"));
+
+        std::fs::write(
+            &path,
+            r#"{"project_path": "/tmp/b", "auto_start_sphinx": false}"#,
+        )
+        .unwrap();
+        let second = DevConfig::load_from(&back).unwrap();
+        assert_eq!(second.project_path.as_deref(), Some("/tmp/b"));
+        assert!(first.auto_start_sphinx);
+        assert!(!second.auto_start_sphinx);
+
+        // 壊れたJSONは読み込めない
+        std::fs::write(&path, "{").unwrap();
+        assert!(DevConfig::load_from(&back).is_none());
+    }
+
+    #[test]
+    fn test_resolve_color_scheme_normalizes_inline_scheme() {
+        let toml_str = r##"
+            [terminal.color_scheme]
+            background = "#1E1E1E"
+            cursor = "#FFF"
+        "##;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        config.terminal.resolve_color_scheme(None);
+        let scheme = config.terminal.color_scheme.unwrap();
+        assert_eq!(scheme.background, Some("#1e1e1e".to_string()));
+        assert_eq!(scheme.cursor, Some("#ffffff".to_string()));
+    }
+
+    #[test]
+    fn test_resolved_theme_inline_takes_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("theme.json"),
+            r##"{ "background": "#000000" }"##,
+        )
+        .unwrap();
+
+        let terminal = TerminalConfig {
+            theme_file: Some("theme.json".to_string()),
+            color_scheme: Some(ColorScheme {
+                background: Some("#FFFFFF".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let resolved = terminal.resolved_theme(Some(dir.path()));
+        assert_eq!(resolved.source, ThemeSource::Inline);
+        assert_eq!(
+            resolved.color_scheme.unwrap().background,
+            Some("#ffffff".to_string())
+        );
+    }
+
+    #[test]
+    fn test_open_target_by_extension() {
+        let editor = EditorConfig::default();
+        assert_eq!(
+            editor.open_target(Path::new("docs/index.rst")),
+            OpenTarget::Editor
+        );
+        assert_eq!(
+            editor.open_target(Path::new("docs/conf.PY")),
+            OpenTarget::Editor
+        );
+        assert_eq!(
+            editor.open_target(Path::new("Makefile")),
+            OpenTarget::Editor
+        );
+        assert_eq!(
+            editor.open_target(Path::new("_static/logo.png")),
+            OpenTarget::Default
+        );
+
+        let editor = EditorConfig {
+            text_extensions: vec![".svg".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            editor.open_target(Path::new("diagram.svg")),
+            OpenTarget::Editor
+        );
+        assert_eq!(
+            editor.open_target(Path::new("index.rst")),
+            OpenTarget::Default
+        );
+    }
+
+    #[test]
+    fn test_project_theme_file_resolves_against_project_dir() {
+        let global_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            global_dir.path().join("theme.json"),
+            r##"{ "background": "#000000" }"##,
+        )
+        .unwrap();
+        std::fs::create_dir(project_dir.path().join("themes")).unwrap();
+        std::fs::write(
+            project_dir.path().join("themes/project.json"),
+            r##"{ "background": "#111111" }"##,
+        )
+        .unwrap();
+        let global = Config {
+            terminal: TerminalConfig {
+                theme_file: Some("theme.json".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // .orthrus.toml なし: グローバルのtheme_fileは設定ディレクトリ基準
+        let mut config = global.clone();
+        config
+            .apply_project_config(global_dir.path(), project_dir.path())
+            .unwrap();
+        config
+            .terminal
+            .resolve_color_scheme(Some(project_dir.path()));
+        assert_eq!(
+            config.terminal.color_scheme.unwrap().background,
+            Some("#000000".to_string())
+        );
+
+        // .orthrus.toml の相対パスはプロジェクト基準
+        std::fs::write(
+            project_dir.path().join(PROJECT_CONFIG_FILE),
+            "[terminal]\ntheme_file = \"themes/project.json\"\n",
+        )
+        .unwrap();
+        let mut config = global.clone();
+        config
+            .apply_project_config(global_dir.path(), project_dir.path())
+            .unwrap();
+        assert_eq!(
+            config.terminal.theme_file,
+            Some(
+                project_dir
+                    .path()
+                    .join("themes/project.json")
+                    .to_string_lossy()
+                    .to_string()
+            )
+        );
+        config
+            .terminal
+            .resolve_color_scheme(Some(project_dir.path()));
+        assert_eq!(
+            config.terminal.color_scheme.unwrap().background,
+            Some("#111111".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolved_theme_from_theme_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("theme.json"),
+            r##"{ "background": "#1E1E1E" }"##,
+        )
+        .unwrap();
+
+        let terminal = TerminalConfig {
+            theme_file: Some("theme.json".to_string()),
+            ..Default::default()
+        };
+        let resolved = terminal.resolved_theme(Some(dir.path()));
+        assert_eq!(resolved.source, ThemeSource::ThemeFile);
+        assert_eq!(
+            resolved.color_scheme.unwrap().background,
+            Some("#1e1e1e".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolved_theme_with_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("theme.json"),
+            r##"{ "background": "#1E1E1E", "foreground": "#D4D4D4" }"##,
+        )
+        .unwrap();
+
+        let terminal = TerminalConfig {
+            theme_file: Some("theme.json".to_string()),
+            overrides: Some(HashMap::from([(
+                "background".to_string(),
+                "#000".to_string(),
+            )])),
+            ..Default::default()
+        };
+        let resolved = terminal.resolved_theme(Some(dir.path()));
+        assert_eq!(resolved.source, ThemeSource::ThemeFile);
+        let scheme = resolved.color_scheme.unwrap();
+        assert_eq!(scheme.background, Some("#000000".to_string()));
+        assert_eq!(scheme.foreground, Some("#d4d4d4".to_string()));
+    }
+
+    #[test]
+    fn test_resolved_theme_none() {
+        let resolved = TerminalConfig::default().resolved_theme(None);
+        assert_eq!(resolved.source, ThemeSource::None);
+        assert!(resolved.color_scheme.is_none());
+
+        // 読み込めないtheme_fileも指定なし扱い
+        let terminal = TerminalConfig {
+            theme_file: Some("/nonexistent/theme.toml".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(terminal.resolved_theme(None).source, ThemeSource::None);
+    }
+
+    #[test]
+    fn test_theme_resolution_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let terminal = TerminalConfig {
+            theme_file: Some("themes/missing.toml".to_string()),
+            ..Default::default()
+        };
+
+        let resolution = terminal.theme_resolution(Some(dir.path()));
+        assert_eq!(
+            resolution.resolved_path,
+            Some(
+                dir.path()
+                    .join("themes/missing.toml")
+                    .to_string_lossy()
+                    .to_string()
+            )
+        );
+        assert!(!resolution.loaded);
+        assert!(resolution.error.unwrap().contains("読み込み失敗"));
+    }
+
+    #[test]
+    fn test_theme_resolution_without_theme_file() {
+        let resolution = TerminalConfig::default().theme_resolution(None);
+        assert_eq!(resolution.resolved_path, None);
+        assert!(!resolution.loaded);
+        assert_eq!(resolution.error, None);
+    }
+
+    #[test]
+    fn test_detect_python_prefers_venv() {
+        let project = tempfile::tempdir().unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        std::fs::write(bin_dir.path().join("python3"), "").unwrap();
+
+        // venvがなければPATHから
+        assert_eq!(
+            detect_python_in(project.path(), Some(bin_dir.path().as_os_str())),
+            Some(bin_dir.path().join("python3").to_string_lossy().to_string())
+        );
+
+        let venv_bin = project.path().join("venv/bin");
+        std::fs::create_dir_all(&venv_bin).unwrap();
+        std::fs::write(venv_bin.join("python"), "").unwrap();
+        assert_eq!(
+            detect_python_in(project.path(), Some(bin_dir.path().as_os_str())),
+            Some(venv_bin.join("python").to_string_lossy().to_string())
+        );
+
+        // .venv は venv より優先
+        let dot_venv_bin = project.path().join(".venv/bin");
+        std::fs::create_dir_all(&dot_venv_bin).unwrap();
+        std::fs::write(dot_venv_bin.join("python"), "").unwrap();
+        assert_eq!(
+            detect_python_in(project.path(), None),
+            Some(dot_venv_bin.join("python").to_string_lossy().to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_python_not_found() {
+        let project = tempfile::tempdir().unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            detect_python_in(project.path(), Some(bin_dir.path().as_os_str())),
+            None
+        );
+        assert_eq!(detect_python_in(project.path(), None), None);
+
+        // PATH上では python3 を python より優先
+        std::fs::write(bin_dir.path().join("python"), "").unwrap();
+        std::fs::write(bin_dir.path().join("python3"), "").unwrap();
+        assert_eq!(
+            detect_python_in(project.path(), Some(bin_dir.path().as_os_str())),
+            Some(bin_dir.path().join("python3").to_string_lossy().to_string())
+        );
+    }
+
+    #[test]
+    fn test_config_diff() {
+        let base = Config::default();
+        assert!(config_diff(&base, &base).is_empty());
+
+        let mut changed = base.clone();
+        changed.sphinx.server.port = 8080;
+        changed.editor.command = "code".to_string();
+        changed.sphinx.extra_args = vec!["-W".to_string()];
+        changed.terminal.font_size = Some(16);
+
+        assert_eq!(
+            config_diff(&base, &changed),
+            vec![
+                ConfigChange {
+                    path: "editor.command".to_string(),
+                    old: Some("nvim".to_string()),
+                    new: Some("code".to_string()),
+                },
+                ConfigChange {
+                    path: "sphinx.extra_args".to_string(),
+                    old: Some("[]".to_string()),
+                    new: Some(r#"["-W"]"#.to_string()),
+                },
+                ConfigChange {
+                    path: "sphinx.server.port".to_string(),
+                    old: Some("0".to_string()),
+                    new: Some("8080".to_string()),
+                },
+                ConfigChange {
+                    path: "terminal.font_size".to_string(),
+                    old: None,
+                    new: Some("16".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_diff_nested_optional_table() {
+        let base = Config::default();
+        let mut changed = base.clone();
+        changed.terminal.overrides = Some(HashMap::from([(
+            "background".to_string(),
+            "#000000".to_string(),
+        )]));
+
+        assert_eq!(
+            config_diff(&base, &changed),
+            vec![ConfigChange {
+                path: "terminal.overrides.background".to_string(),
+                old: None,
+                new: Some("#000000".to_string()),
+            }]
+        );
+        // 逆方向
+        assert_eq!(
+            config_diff(&changed, &base)[0].old,
+            Some("#000000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_toml_str() {
+        let config = Config::from_toml_str(
+            r#"
+            [sphinx.server]
+            port = 8080
+
+            [editor]
+            command = "vim"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.sphinx.server.port, 8080);
+        assert_eq!(config.editor.command, "vim");
+        assert_eq!(config.sphinx.source_dir, "docs");
+
+        assert!(Config::from_toml_str("[sphinx\nport = ").is_err());
+        // 型が合わない
+        assert!(Config::from_toml_str("[sphinx.server]\nport = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_from_json_str() {
+        let config = Config::from_json_str(
+            r#"{ "sphinx": { "build_dir": "out" }, "python": { "interpreter": ".venv/bin/python" } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.sphinx.build_dir, "out");
+        assert_eq!(config.python.interpreter, ".venv/bin/python");
+
+        assert!(Config::from_json_str("{ not json").is_err());
+        assert!(Config::from_json_str(r#"{ "sphinx": { "server": { "port": -1 } } }"#).is_err());
+    }
+
+    #[test]
+    fn test_toml_parse_error_reports_line() {
+        let content = "[terminal]\nfont_size = 14\n\n[sphinx]\nsource_dir = docs\n";
+        match Config::from_toml_str(content) {
+            Err(AppError::ConfigParse {
+                path: None,
+                line: Some(5),
+                column: Some(column),
+                message,
+            }) => {
+                assert!(column > 1, "{}", column);
+                assert!(!message.is_empty());
+            }
+            other => panic!("{:?}", other),
+        }
+
+        // 型の誤りも値の位置を返す
+        match Config::from_toml_str("[terminal]\nfont_size = \"big\"\n") {
+            Err(AppError::ConfigParse { line, column, .. }) => {
+                assert_eq!((line, column), (Some(2), Some(13)));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_parse_error_reports_line() {
+        let content = "{\n  \"terminal\": {\n    \"font_size\": 14,\n  }\n}";
+        match Config::from_json_str(content) {
+            Err(AppError::ConfigParse {
+                line: Some(4),
+                column: Some(_),
+                message,
+                ..
+            }) => assert!(!message.contains(" at line "), "{}", message),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_project_config_parse_error_has_path() {
+        let global_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+        let path = project_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(&path, "[sphinx]\nbuild_dir = \"_build\"\nport = = 1\n").unwrap();
+
+        let err = Config::default()
+            .apply_project_config(global_dir.path(), project_dir.path())
+            .unwrap_err();
+        match &err {
+            AppError::ConfigParse {
+                path: Some(reported),
+                line: Some(3),
+                ..
+            } => assert_eq!(reported, &path.to_string_lossy()),
+            other => panic!("{:?}", other),
+        }
+        assert!(err.to_string().contains(":3:"), "{}", err);
+    }
+
+    #[test]
+    fn test_line_column() {
+        assert_eq!(line_column("abc", 0), (1, 1));
+        assert_eq!(line_column("a\nbc", 3), (2, 2));
+        // 文字の途中を指しても文字単位で数える
+        assert_eq!(line_column("x = \"あい\"", 7), (1, 6));
+        assert_eq!(line_column("a", 100), (1, 2));
+    }
+
+    #[test]
+    fn test_from_str_with_format() {
+        assert!(Config::from_str_with_format("{}", ConfigFormat::Json).is_ok());
+        assert!(Config::from_str_with_format("{}", ConfigFormat::Toml).is_err());
+    }
+
+    #[test]
+    fn test_apply_override() {
+        let mut config = Config::default();
+        let config_override: ConfigOverride = toml::from_str(
+            r#"
+            [sphinx]
+            build_dir = "build"
+
+            [sphinx.server]
+            port = 9000
+            restart_backoff_ms = 500
+            restart_jitter = 0.1
+
+            [terminal]
+            shell = "/bin/bash"
+        "#,
+        )
+        .unwrap();
+
+        config.apply_override(&config_override);
+        assert_eq!(config.sphinx.build_dir, "build");
+        assert_eq!(config.sphinx.server.port, 9000);
+        assert_eq!(config.sphinx.server.restart_backoff_ms, Some(500));
+        assert_eq!(config.sphinx.server.restart_backoff_max_ms, None);
+        assert_eq!(config.sphinx.server.restart_jitter, Some(0.1));
+        assert_eq!(config.terminal.shell, Some("/bin/bash".to_string()));
+        // 指定のないフィールドはそのまま
+        assert_eq!(config.sphinx.source_dir, "docs");
+        assert_eq!(config.editor.command, "nvim");
+    }
+
+    #[test]
+    fn test_apply_override_clear_color_scheme() {
+        let mut config: Config = toml::from_str(
+            r##"
+            [terminal]
+            theme_file = "themes/gruvbox.toml"
+
+            [terminal.color_scheme]
+            background = "#1e1e1e"
+        "##,
+        )
+        .unwrap();
+        assert!(config.terminal.color_scheme.is_some());
+
+        // color_schemeを省略しただけでは取り消されない
+        let keep: ConfigOverride = toml::from_str("[terminal]\nfont_size = 16").unwrap();
+        config.apply_override(&keep);
+        assert!(config.terminal.color_scheme.is_some());
+
+        let clear: ConfigOverride =
+            toml::from_str("[terminal]\nclear_color_scheme = true").unwrap();
+        let mut cleared = config.clone();
+        cleared.apply_override(&clear);
+        assert!(cleared.terminal.color_scheme.is_none());
+        assert_eq!(
+            cleared.terminal.theme_file,
+            Some("themes/gruvbox.toml".to_string())
+        );
+
+        // 同時に指定したcolor_schemeは有効
+        let replace: ConfigOverride = toml::from_str(
+            r##"
+            [terminal]
+            clear_color_scheme = true
+
+            [terminal.color_scheme]
+            background = "#000000"
+        "##,
+        )
+        .unwrap();
+        config.apply_override(&replace);
+        assert_eq!(
+            config.terminal.color_scheme.unwrap().background,
+            Some("#000000".to_string())
+        );
+    }
 }
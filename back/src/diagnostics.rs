@@ -0,0 +1,191 @@
+//! Sphinxのログ行から警告・エラーを構造化して取り出す
+//!
+//! `path/to/file.rst:12: WARNING: message` 形式と、
+//! docutilsの `path/to/file.rst:12: (ERROR/3) message` 形式に対応する
+
+use serde::{Deserialize, Serialize};
+
+/// 1回のビルドで保持する診断の上限（大量の警告でメモリを使い切らないように）
+const DIAGNOSTICS_LIMIT: usize = 1000;
+
+/// 診断の重大度（比較は info < warning < error）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Sphinx / docutilsのレベル名から変換
+    fn from_level(level: &str) -> Option<Self> {
+        match level {
+            "INFO" => Some(Severity::Info),
+            "WARNING" => Some(Severity::Warning),
+            "ERROR" | "SEVERE" | "CRITICAL" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// ログ行から取り出した診断
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SphinxDiagnostic {
+    pub severity: Severity,
+    /// 対象のファイル（位置のない警告ではNone）
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+/// ログ行（エスケープシーケンス除去済み）を診断として解釈する
+pub fn parse_diagnostic(log_line: &str) -> Option<SphinxDiagnostic> {
+    let (location, severity, message) = split_level(log_line)?;
+    let (file, line) = parse_location(location);
+    Some(SphinxDiagnostic {
+        severity,
+        file,
+        line,
+        message: message.trim().to_string(),
+    })
+}
+
+/// `<位置>: <LEVEL>: <本文>` / `<位置>: (<LEVEL>/<n>) <本文>` / `<LEVEL>: <本文>` に分ける
+fn split_level(log_line: &str) -> Option<(&str, Severity, &str)> {
+    let log_line = log_line.trim_end();
+    // 位置なし（`WARNING: ...`）
+    if let Some((level, message)) = log_line.split_once(": ") {
+        if let Some(severity) = Severity::from_level(level) {
+            return Some(("", severity, message));
+        }
+    }
+
+    for (index, _) in log_line.match_indices(": ") {
+        let (location, rest) = (&log_line[..index], &log_line[index + 2..]);
+        if let Some((level, message)) = rest.split_once(": ") {
+            if let Some(severity) = Severity::from_level(level) {
+                return Some((location, severity, message));
+            }
+        }
+        if let Some(rest) = rest.strip_prefix('(') {
+            if let Some((level_part, message)) = rest.split_once(") ") {
+                let level = level_part.split('/').next().unwrap_or_default();
+                if let Some(severity) = Severity::from_level(level) {
+                    return Some((location, severity, message));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// `file.rst:12` を (ファイル, 行) に分ける。行番号がなければファイルのみ
+fn parse_location(location: &str) -> (Option<String>, Option<u32>) {
+    if location.is_empty() {
+        return (None, None);
+    }
+    match location.rsplit_once(':') {
+        Some((file, line)) => match line.parse() {
+            Ok(line) => (Some(file.to_string()), Some(line)),
+            Err(_) => (Some(location.to_string()), None),
+        },
+        None => (Some(location.to_string()), None),
+    }
+}
+
+/// 直近のビルドの診断（ビルド開始でリセット）
+#[derive(Debug, Default)]
+pub struct DiagnosticsBuffer {
+    entries: Vec<SphinxDiagnostic>,
+}
+
+impl DiagnosticsBuffer {
+    pub fn on_build_started(&mut self) {
+        self.entries.clear();
+    }
+
+    /// 診断として解釈できる行なら記録する
+    pub fn record(&mut self, log_line: &str) {
+        if self.entries.len() >= DIAGNOSTICS_LIMIT {
+            return;
+        }
+        if let Some(diagnostic) = parse_diagnostic(log_line) {
+            self.entries.push(diagnostic);
+        }
+    }
+
+    /// 指定した重大度以上の診断（出力順）
+    pub fn at_least(&self, min_severity: Severity) -> Vec<SphinxDiagnostic> {
+        self.entries
+            .iter()
+            .filter(|diagnostic| diagnostic.severity >= min_severity)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diagnostic() {
+        assert_eq!(
+            parse_diagnostic("/docs/index.rst:12: WARNING: undefined label: 'intro'"),
+            Some(SphinxDiagnostic {
+                severity: Severity::Warning,
+                file: Some("/docs/index.rst".to_string()),
+                line: Some(12),
+                message: "undefined label: 'intro'".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_diagnostic("/docs/api.rst:3: (ERROR/3) Unknown directive type \"foo\"."),
+            Some(SphinxDiagnostic {
+                severity: Severity::Error,
+                file: Some("/docs/api.rst".to_string()),
+                line: Some(3),
+                message: "Unknown directive type \"foo\".".to_string(),
+            })
+        );
+        // 行番号なし・位置なし
+        let diagnostic =
+            parse_diagnostic("/docs/old.rst: WARNING: document isn't included in any toctree")
+                .unwrap();
+        assert_eq!(diagnostic.file, Some("/docs/old.rst".to_string()));
+        assert_eq!(diagnostic.line, None);
+        let diagnostic =
+            parse_diagnostic("WARNING: html_static_path entry '_static' does not exist").unwrap();
+        assert_eq!(diagnostic.file, None);
+
+        assert_eq!(parse_diagnostic("build succeeded, 2 warnings."), None);
+        assert_eq!(parse_diagnostic("reading sources... [ 50%] index"), None);
+    }
+
+    #[test]
+    fn test_buffer_filters_by_severity_and_resets() {
+        let mut buffer = DiagnosticsBuffer::default();
+        buffer.record("/docs/a.rst:1: (INFO/1) Duplicate implicit target name: \"a\".");
+        buffer.record("/docs/b.rst:2: WARNING: undefined label: 'b'");
+        buffer.record("reading sources... [100%] c");
+        buffer.record("/docs/c.rst:3: ERROR: Unknown directive type \"foo\".");
+        buffer.record("/docs/d.rst:4: (SEVERE/4) Title level inconsistent");
+
+        assert_eq!(buffer.at_least(Severity::Info).len(), 4);
+        let warnings: Vec<Option<u32>> = buffer
+            .at_least(Severity::Warning)
+            .iter()
+            .map(|d| d.line)
+            .collect();
+        assert_eq!(warnings, [Some(2), Some(3), Some(4)]);
+        assert!(buffer
+            .at_least(Severity::Error)
+            .iter()
+            .all(|d| d.severity == Severity::Error));
+        assert_eq!(buffer.at_least(Severity::Error).len(), 2);
+
+        buffer.on_build_started();
+        assert!(buffer.at_least(Severity::Info).is_empty());
+    }
+}
@@ -0,0 +1,374 @@
+//! フロントエンドに返す構造化エラー
+//!
+//! IPCでは `{ kind, message, ...詳細フィールド }` 形式のオブジェクトとしてシリアライズされる
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// コマンドが返すエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// 指定ポートが使用中
+    /// session_idは同じポートを使っている管理中の別セッション（外部のプロセスならNone）
+    PortInUse {
+        port: u16,
+        session_id: Option<String>,
+    },
+    /// 指定ポートをbindする権限がない（特権ポートなど）
+    PortPermissionDenied { port: u16 },
+    /// PTYの読み手が詰まっており書き込めない
+    WriteBackpressure { session_id: String },
+    /// 貼り付けが上限サイズを超えている
+    PasteTooLarge {
+        session_id: String,
+        size: usize,
+        limit: usize,
+    },
+    /// シェル（実行ファイル）が見つからない
+    ShellNotFound { path: String },
+    /// シェル（実行ファイル）を実行する権限がない
+    PermissionDenied { path: String },
+    /// その他の理由でシェルを起動できなかった
+    SpawnFailed { path: String, message: String },
+    /// プロジェクトのディレクトリが存在しない
+    /// suggestionsは同じ親ディレクトリにあるSphinxプロジェクトらしきディレクトリ
+    ProjectNotFound {
+        path: String,
+        suggestions: Vec<String>,
+    },
+    /// プロジェクトのパスがディレクトリではない
+    ProjectNotDirectory { path: String },
+    /// URL・ファイルを外部のアプリで開けなかった
+    /// hintは原因の見当（既定のブラウザがない・サンドボックスなど）。フロントエンドはURLのコピーなどで代替する
+    OpenFailed {
+        target: String,
+        message: String,
+        hint: Option<String>,
+    },
+    /// 設定のパースに失敗（行・列は1始まり。位置が分からない場合はNone）
+    /// pathは読み込んだファイル（文字列から読み込んだ場合はNone）
+    ConfigParse {
+        path: Option<String>,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    /// 分類されていないエラー
+    Other(String),
+}
+
+impl AppError {
+    /// フロントエンドで分岐に使うエラー種別
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::PortInUse { .. } => "portInUse",
+            AppError::PortPermissionDenied { .. } => "portPermissionDenied",
+            AppError::WriteBackpressure { .. } => "writeBackpressure",
+            AppError::PasteTooLarge { .. } => "pasteTooLarge",
+            AppError::ShellNotFound { .. } => "shellNotFound",
+            AppError::PermissionDenied { .. } => "permissionDenied",
+            AppError::SpawnFailed { .. } => "spawnFailed",
+            AppError::ProjectNotFound { .. } => "projectNotFound",
+            AppError::ProjectNotDirectory { .. } => "projectNotDirectory",
+            AppError::OpenFailed { .. } => "openFailed",
+            AppError::ConfigParse { .. } => "configParse",
+            AppError::Other(_) => "other",
+        }
+    }
+}
+
+/// 開く処理の失敗メッセージから推測する原因
+const OPEN_FAILURE_HINTS: &[(&[&str], &str)] = &[
+    (
+        &[
+            "xdg-open",
+            "no such file",
+            "not found",
+            "no application",
+            "no default",
+        ],
+        "既定のブラウザ（アプリ）が設定されていない可能性があります",
+    ),
+    (
+        &["permission denied", "not allowed", "sandbox", "denied"],
+        "サンドボックスなどで外部のアプリを起動できない環境の可能性があります",
+    ),
+];
+
+impl AppError {
+    /// opener（tauri-plugin-opener）の失敗を原因の見当付きのエラーにする
+    pub fn open_failed(target: &str, message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let hint = OPEN_FAILURE_HINTS
+            .iter()
+            .find(|(patterns, _)| patterns.iter().any(|pattern| lower.contains(pattern)))
+            .map(|(_, hint)| hint.to_string());
+        AppError::OpenFailed {
+            target: target.to_string(),
+            message: message.to_string(),
+            hint,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::PortInUse { port, session_id } => {
+                write!(f, "ポート {} は使用中です", port)?;
+                if let Some(session_id) = session_id {
+                    write!(f, "（セッション: {}）", session_id)?;
+                }
+                Ok(())
+            }
+            AppError::PortPermissionDenied { port } => {
+                write!(f, "ポート {} を使用する権限がありません", port)
+            }
+            AppError::WriteBackpressure { session_id } => {
+                write!(f, "PTYへの書き込みが詰まっています: {}", session_id)
+            }
+            AppError::PasteTooLarge { size, limit, .. } => write!(
+                f,
+                "貼り付けが大きすぎます: {} バイト（上限 {} バイト）",
+                size, limit
+            ),
+            AppError::ShellNotFound { path } => write!(f, "シェルが見つかりません: {}", path),
+            AppError::PermissionDenied { path } => {
+                write!(f, "シェルを実行する権限がありません: {}", path)
+            }
+            AppError::SpawnFailed { path, message } => {
+                write!(f, "シェルの起動に失敗: {} ({})", path, message)
+            }
+            AppError::ProjectNotFound { path, suggestions } => {
+                write!(f, "プロジェクトのディレクトリが見つかりません: {}", path)?;
+                if !suggestions.is_empty() {
+                    write!(f, "（候補: {}）", suggestions.join(", "))?;
+                }
+                Ok(())
+            }
+            AppError::ProjectNotDirectory { path } => {
+                write!(
+                    f,
+                    "プロジェクトのパスがディレクトリではありません: {}",
+                    path
+                )
+            }
+            AppError::OpenFailed {
+                target,
+                message,
+                hint,
+            } => {
+                write!(f, "開けませんでした: {} ({})", target, message)?;
+                if let Some(hint) = hint {
+                    write!(f, " - {}", hint)?;
+                }
+                Ok(())
+            }
+            AppError::ConfigParse {
+                path,
+                line,
+                column,
+                message,
+            } => {
+                write!(f, "設定のパースに失敗: ")?;
+                if let Some(path) = path {
+                    write!(f, "{}:", path)?;
+                }
+                if let Some(line) = line {
+                    write!(f, "{}:{}: ", line, column.unwrap_or(1))?;
+                } else if path.is_some() {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}", message)
+            }
+            AppError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            AppError::PortInUse { port, session_id } => {
+                map.serialize_entry("port", port)?;
+                map.serialize_entry("sessionId", session_id)?
+            }
+            AppError::PortPermissionDenied { port } => map.serialize_entry("port", port)?,
+            AppError::WriteBackpressure { session_id } => {
+                map.serialize_entry("sessionId", session_id)?
+            }
+            AppError::PasteTooLarge {
+                session_id,
+                size,
+                limit,
+            } => {
+                map.serialize_entry("sessionId", session_id)?;
+                map.serialize_entry("size", size)?;
+                map.serialize_entry("limit", limit)?
+            }
+            AppError::ShellNotFound { path }
+            | AppError::PermissionDenied { path }
+            | AppError::SpawnFailed { path, .. }
+            | AppError::ProjectNotDirectory { path } => map.serialize_entry("path", path)?,
+            AppError::ProjectNotFound { path, suggestions } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("suggestions", suggestions)?
+            }
+            AppError::OpenFailed { target, hint, .. } => {
+                map.serialize_entry("target", target)?;
+                map.serialize_entry("hint", hint)?
+            }
+            AppError::ConfigParse {
+                path, line, column, ..
+            } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("line", line)?;
+                map.serialize_entry("column", column)?
+            }
+            AppError::Other(_) => {}
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_port_in_use() {
+        let value = serde_json::to_value(AppError::PortInUse {
+            port: 8000,
+            session_id: None,
+        })
+        .unwrap();
+        assert_eq!(value["kind"], "portInUse");
+        assert_eq!(value["port"], 8000);
+        assert_eq!(value["sessionId"], serde_json::Value::Null);
+        assert!(value["message"].as_str().unwrap().contains("8000"));
+
+        let value = serde_json::to_value(AppError::PortInUse {
+            port: 8000,
+            session_id: Some("docs".to_string()),
+        })
+        .unwrap();
+        assert_eq!(value["sessionId"], "docs");
+        assert!(value["message"].as_str().unwrap().contains("docs"));
+    }
+
+    #[test]
+    fn test_serialize_config_parse() {
+        let error = AppError::ConfigParse {
+            path: Some("/home/a/.orthrus.toml".to_string()),
+            line: Some(3),
+            column: Some(7),
+            message: "invalid string".to_string(),
+        };
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(value["kind"], "configParse");
+        assert_eq!(value["line"], 3);
+        assert_eq!(value["column"], 7);
+        assert_eq!(value["path"], "/home/a/.orthrus.toml");
+        assert_eq!(
+            value["message"],
+            "設定のパースに失敗: /home/a/.orthrus.toml:3:7: invalid string"
+        );
+
+        let error = AppError::ConfigParse {
+            path: None,
+            line: None,
+            column: None,
+            message: "invalid".to_string(),
+        };
+        assert_eq!(error.to_string(), "設定のパースに失敗: invalid");
+    }
+
+    #[test]
+    fn test_serialize_shell_not_found() {
+        let value = serde_json::to_value(AppError::ShellNotFound {
+            path: "/bin/nosuch".to_string(),
+        })
+        .unwrap();
+        assert_eq!(value["kind"], "shellNotFound");
+        assert_eq!(value["path"], "/bin/nosuch");
+    }
+
+    #[test]
+    fn test_serialize_paste_too_large() {
+        let value = serde_json::to_value(AppError::PasteTooLarge {
+            session_id: "s1".to_string(),
+            size: 2048,
+            limit: 1024,
+        })
+        .unwrap();
+        assert_eq!(value["kind"], "pasteTooLarge");
+        assert_eq!(value["sessionId"], "s1");
+        assert_eq!(value["size"], 2048);
+        assert_eq!(value["limit"], 1024);
+    }
+
+    #[test]
+    fn test_serialize_project_not_found() {
+        let value = serde_json::to_value(AppError::ProjectNotFound {
+            path: "/work/dcos".to_string(),
+            suggestions: vec!["/work/docs".to_string()],
+        })
+        .unwrap();
+        assert_eq!(value["kind"], "projectNotFound");
+        assert_eq!(value["path"], "/work/dcos");
+        assert_eq!(value["suggestions"], serde_json::json!(["/work/docs"]));
+        assert!(value["message"].as_str().unwrap().contains("/work/docs"));
+    }
+
+    #[test]
+    fn test_open_failed_hints() {
+        let hint_of = |message: &str| match AppError::open_failed("http://127.0.0.1:8000", message)
+        {
+            AppError::OpenFailed { hint, .. } => hint,
+            _ => unreachable!(),
+        };
+        assert!(
+            hint_of("Failed to run xdg-open: No such file or directory (os error 2)")
+                .unwrap()
+                .contains("既定のブラウザ")
+        );
+        assert!(hint_of("Permission denied (os error 13)")
+            .unwrap()
+            .contains("サンドボックス"));
+        assert_eq!(hint_of("unexpected failure"), None);
+
+        let value = serde_json::to_value(AppError::open_failed(
+            "http://127.0.0.1:8000",
+            "No default browser",
+        ))
+        .unwrap();
+        assert_eq!(value["kind"], "openFailed");
+        assert_eq!(value["target"], "http://127.0.0.1:8000");
+        assert!(value["hint"].is_string());
+    }
+
+    #[test]
+    fn test_serialize_other() {
+        let value = serde_json::to_value(AppError::from("失敗".to_string())).unwrap();
+        assert_eq!(value["kind"], "other");
+        assert_eq!(value["message"], "失敗");
+    }
+}
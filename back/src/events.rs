@@ -0,0 +1,326 @@
+//! バックエンドが発行するイベント名とそのカタログ
+//!
+//! emit呼び出しは必ずここの定数を使い、新しいイベントを追加したら `CATALOG` にも載せる
+
+use serde::Serialize;
+
+use crate::color_scheme::ColorScheme;
+
+pub const PTY_DATA: &str = "pty_data";
+pub const PTY_EXIT: &str = "pty_exit";
+pub const PTY_RESIZED: &str = "pty_resized";
+pub const PTY_RESTARTED: &str = "pty_restarted";
+pub const PTY_STARTUP_SLOW: &str = "pty_startup_slow";
+pub const PTY_IDLE_KILLED: &str = "pty_idle_killed";
+pub const PTY_WRITE_BACKPRESSURE: &str = "pty_write_backpressure";
+pub const PTY_PASTE_REJECTED: &str = "pty_paste_rejected";
+pub const PTY_CWD: &str = "pty_cwd";
+pub const PTY_PROMPT_MARKER: &str = "pty_prompt_marker";
+pub const PTY_THEME_CHANGED: &str = "pty_theme_changed";
+pub const TERMINAL_COLOR_WARNING: &str = "terminal_color_warning";
+pub const SPHINX_LOG: &str = "sphinx_log";
+pub const SPHINX_LOG_TRUNCATED: &str = "sphinx_log_truncated";
+pub const SPHINX_STARTED: &str = "sphinx_started";
+pub const SPHINX_SERVING: &str = "sphinx_serving";
+pub const SPHINX_BUILT: &str = "sphinx_built";
+pub const SPHINX_ERROR: &str = "sphinx_error";
+pub const SPHINX_UNHEALTHY: &str = "sphinx_unhealthy";
+pub const SPHINX_BUILD_FINISHED: &str = "sphinx_build_finished";
+pub const SPHINX_BUILD_CANCELLED: &str = "sphinx_build_cancelled";
+pub const SPHINX_BUILD_CYCLE: &str = "sphinx_build_cycle";
+pub const SPHINX_OUTPUT_CHANGED: &str = "sphinx_output_changed";
+
+/// セッションIDのみのペイロード（文字列としてシリアライズされる）
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionId<'a>(pub &'a str);
+
+/// `pty_data`: `[sessionId, data, seq]`
+/// seqはセッションごとの連番（後から追加したため末尾に置く）
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyData<'a>(pub &'a str, pub &'a str, pub u64);
+
+/// `pty_exit`: `[sessionId, exitCode, error]`
+/// errorは読み取りエラーで終了したか（後から追加したため末尾に置く）
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyExit<'a>(pub &'a str, pub i32, pub bool);
+
+/// `pty_cwd`: `[sessionId, path]`
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyCwd<'a>(pub &'a str, pub &'a str);
+
+/// `pty_prompt_marker`: `[sessionId, marker, exitCode]`
+/// markerはOSC 133の種別（A/B/C/D）。exitCodeはDのみ
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyPromptMarker<'a>(pub &'a str, pub &'a str, pub Option<i32>);
+
+/// `pty_theme_changed`: `[sessionId, scheme]`
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyThemeChanged<'a>(pub &'a str, pub &'a ColorScheme);
+
+/// `sphinx_started`: `[sessionId, port]`
+#[derive(Debug, Clone, Serialize)]
+pub struct SphinxStarted<'a>(pub &'a str, pub u16);
+
+/// `sphinx_serving`: `[sessionId, url]`
+#[derive(Debug, Clone, Serialize)]
+pub struct SphinxServing<'a>(pub &'a str, pub &'a str);
+
+/// `sphinx_error`: `[sessionId, line]`
+#[derive(Debug, Clone, Serialize)]
+pub struct SphinxError<'a>(pub &'a str, pub &'a str);
+
+/// `sphinx_log_truncated`: `[sessionId, seq, originalLength]`
+/// seqは切り詰めた `sphinx_log` の通し番号、originalLengthは元の行のバイト数
+#[derive(Debug, Clone, Serialize)]
+pub struct SphinxLogTruncated<'a>(pub &'a str, pub u64, pub usize);
+
+/// `sphinx_build_finished`: `[sessionId, exitCode]`（シグナルで終了した場合はnull）
+#[derive(Debug, Clone, Serialize)]
+pub struct SphinxBuildFinished<'a>(pub &'a str, pub Option<i32>);
+
+/// `pty_paste_rejected`: 上限を超えたため書き込まなかった貼り付け
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyPasteRejected<'a> {
+    pub session_id: &'a str,
+    pub size: usize,
+    pub limit: usize,
+}
+
+/// `terminal_color_warning`: 24bitカラーを期待できないTERM / COLORTERM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TerminalColorWarning {
+    pub term: String,
+    pub colorterm: Option<String>,
+}
+
+/// `sphinx_build_cycle`: sphinx-autobuildの1回分のビルドのまとめ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SphinxBuildCycle {
+    pub session_id: String,
+    /// ビルド開始時刻（UNIXエポックからのミリ秒）
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    pub warnings: usize,
+    pub errors: usize,
+}
+
+/// `sphinx_output_changed`: ビルド中に更新された出力ファイル
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SphinxOutputChanged {
+    pub session_id: String,
+    /// ビルドディレクトリからの相対パス（`/` 区切り、パス順）
+    pub paths: Vec<String>,
+    /// 上限を超えたため一部を省略した
+    pub truncated: bool,
+}
+
+/// イベントのメタデータ
+#[derive(Debug, Clone, Serialize)]
+pub struct EventInfo {
+    pub name: &'static str,
+    /// ペイロードの形（TypeScriptの型表記）
+    pub payload: &'static str,
+    pub description: &'static str,
+}
+
+/// 発行される全イベントの一覧
+pub const CATALOG: &[EventInfo] = &[
+    EventInfo {
+        name: PTY_DATA,
+        payload: "[sessionId: string, data: string, seq: number]",
+        description: "PTYからの出力（seqはセッションごとに0から1ずつ増える）",
+    },
+    EventInfo {
+        name: PTY_EXIT,
+        payload: "[sessionId: string, exitCode: number, error: boolean]",
+        description: "PTYの出力の終了（正常に閉じたら0、読み取りエラーなら1でerrorがtrue。エラー時はセッションも破棄される）",
+    },
+    EventInfo {
+        name: PTY_RESIZED,
+        payload: "{ session_id: string, cols: number, rows: number }",
+        description: "PTYのサイズ変更が適用された",
+    },
+    EventInfo {
+        name: PTY_RESTARTED,
+        payload: "sessionId: string",
+        description: "restart_all_terminals でセッションが作り直された",
+    },
+    EventInfo {
+        name: PTY_STARTUP_SLOW,
+        payload: "sessionId: string",
+        description: "起動後、一定時間内に最初の出力がなかった",
+    },
+    EventInfo {
+        name: PTY_IDLE_KILLED,
+        payload: "sessionId: string",
+        description: "無操作のまま idle_kill_secs を超えたため終了した",
+    },
+    EventInfo {
+        name: PTY_WRITE_BACKPRESSURE,
+        payload: "sessionId: string",
+        description: "PTYへの書き込みが詰まっている",
+    },
+    EventInfo {
+        name: PTY_PASTE_REJECTED,
+        payload: "{ session_id: string, size: number, limit: number }",
+        description: "貼り付けが上限サイズを超えたため書き込まなかった",
+    },
+    EventInfo {
+        name: PTY_CWD,
+        payload: "[sessionId: string, path: string]",
+        description: "シェルがOSC 7で通知した作業ディレクトリが変わった",
+    },
+    EventInfo {
+        name: PTY_PROMPT_MARKER,
+        payload: "[sessionId: string, marker: \"A\" | \"B\" | \"C\" | \"D\", exitCode: number | null]",
+        description: "シェル統合のプロンプトの区切り（OSC 133）を受け取った",
+    },
+    EventInfo {
+        name: PTY_THEME_CHANGED,
+        payload: "[sessionId: string, scheme: ColorScheme]",
+        description: "set_all_terminals_color_scheme でセッションのカラースキームが変わった",
+    },
+    EventInfo {
+        name: TERMINAL_COLOR_WARNING,
+        payload: "{ term: string, colorterm: string | null }",
+        description: "テーマが24bitカラーを使うが、TERM / COLORTERM が対応を示していない",
+    },
+    EventInfo {
+        name: SPHINX_LOG,
+        payload: "{ session_id: string, seq: number, stream: \"stdout\" | \"stderr\", line: string, raw?: string }",
+        description: "sphinx-autobuildのログ1行",
+    },
+    EventInfo {
+        name: SPHINX_LOG_TRUNCATED,
+        payload: "[sessionId: string, seq: number, originalLength: number]",
+        description: "sphinx_log の行が max_log_line_bytes を超えたため切り詰めた",
+    },
+    EventInfo {
+        name: SPHINX_STARTED,
+        payload: "[sessionId: string, port: number]",
+        description: "プレビューサーバーが応答し始めた",
+    },
+    EventInfo {
+        name: SPHINX_SERVING,
+        payload: "[sessionId: string, url: string]",
+        description: "プレビューのURL（sphinx_started と同時に発行）",
+    },
+    EventInfo {
+        name: SPHINX_BUILT,
+        payload: "sessionId: string",
+        description: "ビルドが成功した",
+    },
+    EventInfo {
+        name: SPHINX_ERROR,
+        payload: "[sessionId: string, line: string]",
+        description: "stderrにエラー行が出力された",
+    },
+    EventInfo {
+        name: SPHINX_UNHEALTHY,
+        payload: "sessionId: string",
+        description: "プロセスは生きているがサーバーが応答しない",
+    },
+    EventInfo {
+        name: SPHINX_BUILD_FINISHED,
+        payload: "[sessionId: string, exitCode: number | null]",
+        description: "build_sphinx_once のビルドが終了した",
+    },
+    EventInfo {
+        name: SPHINX_BUILD_CANCELLED,
+        payload: "sessionId: string",
+        description: "build_sphinx_once のビルドがキャンセルされた",
+    },
+    EventInfo {
+        name: SPHINX_BUILD_CYCLE,
+        payload: "{ session_id: string, started_at: number, duration_ms: number, succeeded: boolean, warnings: number, errors: number }",
+        description: "sphinx-autobuildのビルドが1回終わった（開始時刻・所要時間・警告とエラーの数）",
+    },
+    EventInfo {
+        name: SPHINX_OUTPUT_CHANGED,
+        payload: "{ session_id: string, paths: string[], truncated: boolean }",
+        description: "ビルド中に更新された出力ファイル（notify_output_changes有効時、ビルドごとに1回）",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 全イベントの定数と、フロントエンドが購読している名前
+    /// 新しいイベントを追加したらここにも載せる
+    const EVENTS: &[(&str, &str)] = &[
+        (PTY_DATA, "pty_data"),
+        (PTY_EXIT, "pty_exit"),
+        (PTY_RESIZED, "pty_resized"),
+        (PTY_RESTARTED, "pty_restarted"),
+        (PTY_STARTUP_SLOW, "pty_startup_slow"),
+        (PTY_IDLE_KILLED, "pty_idle_killed"),
+        (PTY_WRITE_BACKPRESSURE, "pty_write_backpressure"),
+        (PTY_PASTE_REJECTED, "pty_paste_rejected"),
+        (PTY_CWD, "pty_cwd"),
+        (PTY_PROMPT_MARKER, "pty_prompt_marker"),
+        (PTY_THEME_CHANGED, "pty_theme_changed"),
+        (TERMINAL_COLOR_WARNING, "terminal_color_warning"),
+        (SPHINX_LOG, "sphinx_log"),
+        (SPHINX_LOG_TRUNCATED, "sphinx_log_truncated"),
+        (SPHINX_STARTED, "sphinx_started"),
+        (SPHINX_SERVING, "sphinx_serving"),
+        (SPHINX_BUILT, "sphinx_built"),
+        (SPHINX_ERROR, "sphinx_error"),
+        (SPHINX_UNHEALTHY, "sphinx_unhealthy"),
+        (SPHINX_BUILD_FINISHED, "sphinx_build_finished"),
+        (SPHINX_BUILD_CANCELLED, "sphinx_build_cancelled"),
+        (SPHINX_BUILD_CYCLE, "sphinx_build_cycle"),
+        (SPHINX_OUTPUT_CHANGED, "sphinx_output_changed"),
+    ];
+
+    #[test]
+    fn test_event_names_are_stable() {
+        // フロントエンドが購読している名前なので変えてはいけない
+        for (constant, name) in EVENTS {
+            assert_eq!(constant, name);
+        }
+    }
+
+    #[test]
+    fn test_payloads_keep_tuple_shapes() {
+        let json = |value: serde_json::Value| value.to_string();
+        assert_eq!(json(serde_json::json!(SessionId("s1"))), r#""s1""#);
+        assert_eq!(
+            json(serde_json::json!(PtyData("s1", "ls\r\n", 3))),
+            r#"["s1","ls\r\n",3]"#
+        );
+        assert_eq!(
+            json(serde_json::json!(PtyExit("s1", 1, true))),
+            r#"["s1",1,true]"#
+        );
+        assert_eq!(
+            json(serde_json::json!(SphinxStarted("s1", 8000))),
+            r#"["s1",8000]"#
+        );
+        assert_eq!(
+            json(serde_json::json!(SphinxBuildFinished("s1", None))),
+            r#"["s1",null]"#
+        );
+    }
+
+    #[test]
+    fn test_every_event_is_in_catalog() {
+        for (constant, _) in EVENTS {
+            assert_eq!(
+                CATALOG.iter().filter(|info| info.name == *constant).count(),
+                1,
+                "{} がカタログにない、または重複している",
+                constant
+            );
+        }
+        for info in CATALOG {
+            assert!(
+                EVENTS.iter().any(|(constant, _)| *constant == info.name),
+                "{} が一覧にない",
+                info.name
+            );
+        }
+    }
+}
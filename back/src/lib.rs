@@ -1,50 +1,366 @@
+mod ansi;
+mod build_output;
 mod color_scheme;
 mod config;
+mod diagnostics;
+mod error;
+mod events;
+mod logging;
+mod port_cache;
+mod preview;
+mod project;
+mod recent_projects;
+mod report;
+mod session_id;
 mod sphinx;
 mod terminal;
 
-use config::{Config, DevConfig};
-use sphinx::{create_sphinx_manager, SharedSphinxManager};
-use tauri::State;
+use build_output::{BuildFormat, BuildOutputSize};
+use color_scheme::{ColorScheme, ThemeFileEntry, ThemeFormatInfo, ThemeValidation};
+use config::{
+    Config, ConfigChange, ConfigDirStatus, ConfigFormat, DevConfig, OpenTarget, ResolvedTheme,
+    ServerConfig, SphinxConfig, ThemeResolution,
+};
+use diagnostics::{Severity, SphinxDiagnostic};
+use error::AppError;
+use logging::LogLevel;
+use sphinx::{
+    create_sphinx_manager, BuildDirStatus, DetachedSphinx, RestartBackoff, SharedSphinxManager,
+    SingleFileBuild, SphinxManager, SphinxSessionInfo, SphinxStartResult,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{Emitter, State};
 use tauri_plugin_opener::OpenerExt;
-use terminal::{create_terminal_manager, SharedTerminalManager};
+use terminal::{
+    create_terminal_manager, start_idle_reaper, CommandCapture, RestartAllResult,
+    SharedTerminalManager, SpawnOptions, TerminalInfo, TerminalListEntry, TerminalMetrics,
+};
 
-/// PTYセッションを生成
+/// 埋め込み先のRustコードからPTYを直接扱う場合に使う（出力は `spawn_with_sink` で受け取る）
+pub use terminal::TerminalManager;
+
+/// PTYセッションを生成（optionsの各項目は省略可。cols / rowsのみ必須）
+/// startup_timeout_ms内に出力がなければ `pty_startup_slow` を通知する（診断用、セッションは維持）
+/// keep_aliveがtrueのセッションはアイドル時の自動終了の対象外
+/// keepalive_secsを指定すると、その間隔でSSHの無通信切断を防ぐためのNULを書き込む
+/// shell_argsを指定するとログインシェルの `-l` の代わりに使う（ログインシェルにする場合は引数に含める）
+/// shellが未指定ならshell_fallbacksを順に試し、最初に実行できたものを使う
+/// shell_integrationがtrueならbash / zshにOSC 133を出力させ、`pty_prompt_marker` を通知する
+/// safe_modeがtrueならrcファイルを読まずに起動する（bash / zsh / fish、壊れたrcの修復用）
+/// defer_slave_dropがtrueならPTYのslaveを最初の出力を読むまで閉じない（既定は起動直後に閉じる）
+/// accentはUIの色分け用の16進カラー（`get_terminal_info` / `list_terminals` で返す）
+/// envはシェルに追加する環境変数（`effective_spawn_env` で確認できる）
 #[tauri::command]
 fn spawn_terminal(
     session_id: String,
-    cwd: Option<String>,
-    shell: Option<String>,
-    cols: u16,
-    rows: u16,
+    options: SpawnOptions,
     manager: State<'_, SharedTerminalManager>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let mut inner = manager.lock().map_err(|e| e.to_string())?;
-    inner.spawn(session_id, cwd, shell, cols, rows, app_handle)
+    inner.spawn(session_id, options, app_handle)
 }
 
 /// PTYにデータを書き込む
+/// 読み手が詰まっている場合は `pty_write_backpressure` を通知してすぐに返る
 #[tauri::command]
 fn pty_write(
     session_id: String,
     data: String,
     manager: State<'_, SharedTerminalManager>,
-) -> Result<(), String> {
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    let result = inner.write(&session_id, data.as_bytes());
+    if let Err(AppError::WriteBackpressure { ref session_id }) = result {
+        let _ = app_handle.emit(
+            events::PTY_WRITE_BACKPRESSURE,
+            events::SessionId(session_id),
+        );
+    }
+    result
+}
+
+/// 貼り付けをPTYに書き込む
+/// max_paste_bytes（省略時は設定の `terminal.max_paste_bytes`、それもなければ1MiB）を
+/// 超える場合は書き込まず `pty_paste_rejected` を通知する
+#[tauri::command]
+fn pty_write_paste(
+    session_id: String,
+    data: String,
+    max_paste_bytes: Option<usize>,
+    manager: State<'_, SharedTerminalManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    // 設定の読み込み（ディスクI/O）はロックの外で行う
+    let configured = match max_paste_bytes {
+        Some(_) => None,
+        None => Config::load()
+            .ok()
+            .and_then(|config| config.terminal.max_paste_bytes),
+    };
+    let limit = terminal::resolve_paste_limit(max_paste_bytes, configured);
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    let result = inner.write_paste(&session_id, data.as_bytes(), limit);
+    match result {
+        Err(AppError::PasteTooLarge {
+            ref session_id,
+            size,
+            limit,
+        }) => {
+            let _ = app_handle.emit(
+                events::PTY_PASTE_REJECTED,
+                events::PtyPasteRejected {
+                    session_id,
+                    size,
+                    limit,
+                },
+            );
+        }
+        Err(AppError::WriteBackpressure { ref session_id }) => {
+            let _ = app_handle.emit(
+                events::PTY_WRITE_BACKPRESSURE,
+                events::SessionId(session_id),
+            );
+        }
+        _ => {}
+    }
+    result
+}
+
+/// フォアグラウンドのプロセスに割り込み（Ctrl-C）を送る
+#[tauri::command]
+fn pty_interrupt(
+    session_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<(), AppError> {
     let mut inner = manager.lock().map_err(|e| e.to_string())?;
-    inner.write(&session_id, data.as_bytes())
+    inner.interrupt(&session_id)
 }
 
-/// PTYのサイズを変更
+/// 固まったプログラムを強制終了する（シェルと履歴は残す）
+#[tauri::command]
+fn pty_kill_foreground(
+    session_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<(), AppError> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.kill_foreground(&session_id)
+}
+
+/// ペインのフォーカス変化をPTYへ伝える（フォーカスレポート対応のTUI向け）
+#[tauri::command]
+fn pty_focus(
+    session_id: String,
+    focused: bool,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<(), AppError> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.focus(&session_id, focused)
+}
+
+/// PTYのサイズを変更し、適用したサイズを `pty_resized` で通知
+/// 現在と同じサイズの場合は何もしない
 #[tauri::command]
 fn pty_resize(
     session_id: String,
     cols: u16,
     rows: u16,
     manager: State<'_, SharedTerminalManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    if let Some(resized) = inner.resize(&session_id, cols, rows)? {
+        let _ = app_handle.emit(events::PTY_RESIZED, resized);
+    }
+    Ok(())
+}
+
+/// スクロールバックの内容を取得
+#[tauri::command]
+fn get_terminal_scrollback(
+    session_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<String, String> {
+    let inner = manager.lock().map_err(|e| e.to_string())?;
+    let bytes = inner.scrollback(&session_id)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// スクロールバックをgzipで圧縮して取得（フロントエンドで展開する）
+/// 大きな履歴をJSONの文字列として送らずに済むよう、生のバイト列で返す
+#[tauri::command]
+fn get_terminal_scrollback_gz(
+    session_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<tauri::ipc::Response, String> {
+    let inner = manager.lock().map_err(|e| e.to_string())?;
+    let compressed = inner.scrollback_gz(&session_id)?;
+    Ok(tauri::ipc::Response::new(compressed))
+}
+
+/// スクロールバックを破棄
+/// `clear_view` がtrueの場合、xterm.js側の履歴も消えるよう `ESC[3J` を出力として送る
+#[tauri::command]
+fn clear_terminal_scrollback(
+    session_id: String,
+    clear_view: Option<bool>,
+    manager: State<'_, SharedTerminalManager>,
+    app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
     let mut inner = manager.lock().map_err(|e| e.to_string())?;
-    inner.resize(&session_id, cols, rows)
+    inner.clear_scrollback(&session_id)?;
+    if clear_view.unwrap_or(false) {
+        if let Some(seq) = inner.next_output_seq(&session_id) {
+            let _ = app_handle.emit(
+                events::PTY_DATA,
+                events::PtyData(&session_id, "\x1b[3J", seq),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// PTYセッションの情報（PID・サイズ・タイトルなど）をまとめて取得
+#[tauri::command]
+fn get_terminal_info(
+    session_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<TerminalInfo, String> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.info(&session_id)
+}
+
+/// 新しいセッションID（UUID v4）。labelを指定すると `label-<uuid>` にする
+/// フロントエンドとバックエンドで同じ方式にし、再読み込み後の衝突を避ける
+#[tauri::command]
+fn new_session_id(label: Option<String>) -> String {
+    session_id::new_session_id(label.as_deref())
+}
+
+/// 全PTYセッションの情報（セッションID順）
+#[tauri::command]
+fn list_terminals(
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<Vec<TerminalListEntry>, String> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    Ok(inner.list())
+}
+
+/// 起動するシェルに渡る環境変数（親プロセスの環境 < TERM / COLORTERM / SHELL < env）
+/// envは `spawn_terminal` の `options.env` と同じもの。
+/// シェルは設定と環境から決まる既定のもの。値は伏せないため秘密が含まれることがある
+#[tauri::command]
+fn effective_spawn_env(
+    env: Option<HashMap<String, String>>,
+) -> std::collections::BTreeMap<String, String> {
+    let shell = match Config::load() {
+        Ok(config) => terminal::detect_shell(
+            config.terminal.shell.as_deref(),
+            &config.terminal.shell_fallbacks,
+        ),
+        Err(_) => terminal::detect_shell(None, &[]),
+    };
+    let process_env = std::env::vars_os().map(|(key, value)| {
+        (
+            key.to_string_lossy().to_string(),
+            value.to_string_lossy().to_string(),
+        )
+    });
+    terminal::effective_spawn_env(process_env, &shell, &env.unwrap_or_default())
+}
+
+/// PTYセッションの出力量（累計の読み取りバイト数・通知数と直近の読み取り速度）
+#[tauri::command]
+fn get_terminal_metrics(
+    session_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<TerminalMetrics, String> {
+    let inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.metrics(&session_id)
+}
+
+/// 全PTYセッションを同じ設定で新しいプロセスに置き換える（シェル設定の変更後など）
+/// 再起動したセッションIDと、起動し直せなかったセッションとその理由を返す
+#[tauri::command]
+fn restart_all_terminals(
+    manager: State<'_, SharedTerminalManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<RestartAllResult, AppError> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    Ok(inner.restart_all(app_handle))
+}
+
+/// 全PTYセッションにカラースキームを適用する（設定でテーマを変えたとき）
+/// 色の表記を正規化して各セッションに記録し、セッションごとに `pty_theme_changed` を通知する
+#[tauri::command]
+fn set_all_terminals_color_scheme(
+    mut scheme: ColorScheme,
+    manager: State<'_, SharedTerminalManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, AppError> {
+    scheme.normalize();
+    let session_ids = {
+        let mut inner = manager.lock().map_err(|e| e.to_string())?;
+        inner.set_all_color_scheme(&scheme)
+    };
+    for session_id in &session_ids {
+        let _ = app_handle.emit(
+            events::PTY_THEME_CHANGED,
+            events::PtyThemeChanged(session_id, &scheme),
+        );
+    }
+    Ok(session_ids)
+}
+
+/// コマンドをPTYで1回だけ実行し、終了コードと出力をまとめて返す
+/// minimal_env（省略時true）なら親プロセスの環境はPATH / HOME / ロケールなどだけを引き継ぐ
+/// 終了まで待つため非同期コマンドとして実行する
+#[tauri::command(async)]
+fn spawn_command_capture(
+    program: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    minimal_env: Option<bool>,
+) -> Result<CommandCapture, AppError> {
+    terminal::capture_command(
+        &program,
+        &args.unwrap_or_default(),
+        cwd.as_deref(),
+        &env.unwrap_or_default(),
+        minimal_env.unwrap_or(true),
+    )
+}
+
+/// 入力を止め、残りの出力をdrain_msまで読み取って通知してからセッションを終了
+/// 待機中はマネージャーのロックを保持しない
+#[tauri::command(async)]
+fn close_terminal(
+    session_id: String,
+    drain_ms: Option<u64>,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<(), String> {
+    let exited = {
+        let mut inner = manager.lock().map_err(|e| e.to_string())?;
+        inner.begin_close(&session_id)?
+    };
+    terminal::wait_for_drain(&exited, Duration::from_millis(drain_ms.unwrap_or(0)));
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.kill(&session_id)
+}
+
+/// PTYセッションを終了させずにIDを付け替える（タブの並べ替え用）
+/// new_idが既に使われている場合はエラー
+#[tauri::command]
+fn rename_terminal(
+    old_id: String,
+    new_id: String,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<(), String> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.rename(&old_id, &new_id)
 }
 
 /// PTYセッションを終了
@@ -57,21 +373,200 @@ fn kill_terminal(
     inner.kill(&session_id)
 }
 
+/// 不具合報告用に現在の状態（OS・設定・シェル・各セッション）をまとめる
+/// redact_homeがtrueの場合、パスに含まれるホームディレクトリを `~` に置き換える
+/// include_log_tailがtrueの場合のみ、各ターミナルの出力の末尾（エスケープシーケンスは除く）を含める
+#[tauri::command]
+fn diagnostic_dump(
+    redact_home: Option<bool>,
+    include_log_tail: Option<bool>,
+    terminals: State<'_, SharedTerminalManager>,
+    sphinx: State<'_, SharedSphinxManager>,
+) -> Result<serde_json::Value, String> {
+    // 設定ファイルの読み込み中にマネージャのロックを保持しない
+    let config = Config::load();
+    let dump = {
+        let mut terminals = terminals.lock().map_err(|e| e.to_string())?;
+        let sphinx = sphinx.lock().map_err(|e| e.to_string())?;
+        report::collect(
+            config,
+            &mut terminals,
+            &sphinx,
+            include_log_tail.unwrap_or(false),
+        )
+    };
+    let mut value = serde_json::to_value(dump).map_err(|e| e.to_string())?;
+    if redact_home.unwrap_or(false) {
+        if let Some(home) = dirs::home_dir() {
+            report::redact_home(&mut value, &home);
+        }
+    }
+    Ok(value)
+}
+
+/// アプリのバージョンとビルド情報（About・診断パネル用）
+#[tauri::command]
+fn app_version() -> report::AppVersion {
+    report::app_version()
+}
+
+/// プロジェクトのパスを正規化した絶対パスにする（存在しない・ディレクトリでなければエラー）
+#[tauri::command]
+fn canonicalize_project(path: String) -> Result<String, AppError> {
+    let canonical = project::canonicalize_project(std::path::Path::new(&path))?;
+    Ok(canonical.to_string_lossy().to_string())
+}
+
+/// 開いたプロジェクトを最近のプロジェクトの先頭に記録
+#[tauri::command]
+fn record_recent_project(path: String) -> Result<(), String> {
+    let mut recent = recent_projects::RecentProjects::open_default()
+        .ok_or("データディレクトリが見つかりません")?;
+    recent.record(std::path::Path::new(&path))
+}
+
+/// 最近開いたプロジェクト（新しい順、存在しないものは除く。limit省略時10件）
+#[tauri::command]
+fn list_recent_projects(limit: Option<usize>) -> Vec<String> {
+    recent_projects::RecentProjects::open_default()
+        .map(|recent| recent.list(limit.unwrap_or(10)))
+        .unwrap_or_default()
+}
+
 /// グローバル設定を読み込む
 #[tauri::command]
-fn load_config() -> Result<Config, String> {
+fn load_config() -> Result<Config, AppError> {
     let mut config = Config::load()?;
     // テーマファイルがある場合は解決（設定ファイルの場所を基準に）
-    let config_dir = dirs::config_dir()
-        .unwrap_or_default()
-        .join("orthrus");
-    config.terminal.resolve_color_scheme(Some(&config_dir));
+    config
+        .terminal
+        .resolve_color_scheme(Some(&Config::config_dir()));
     Ok(config)
 }
 
+/// グローバル設定にプロジェクトの .orthrus.toml を重ねて読み込む
+/// テーマファイルの相対パスは定義されたファイルの場所を基準に解決される
+#[tauri::command]
+fn load_project_config(project_path: String) -> Result<Config, AppError> {
+    Config::load_for_project(std::path::Path::new(&project_path))
+}
+
+/// TERM / COLORTERM（term省略時はセッションに設定する値）で24bitカラーが使えるか判定
+/// 設定のテーマが24bitカラーを使うのに対応していなければ `terminal_color_warning` を通知する
+#[tauri::command]
+fn check_truecolor_support(
+    term: Option<String>,
+    colorterm: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<bool, String> {
+    let (term, colorterm) = match term {
+        Some(term) => (term, colorterm),
+        None => (
+            terminal::SESSION_TERM.to_string(),
+            Some(terminal::SESSION_COLORTERM.to_string()),
+        ),
+    };
+    let requested = Config::load()?.terminal.uses_truecolor();
+    if let Some(warning) = terminal::truecolor_warning(requested, &term, colorterm.as_deref()) {
+        let _ = app_handle.emit(events::TERMINAL_COLOR_WARNING, warning);
+    }
+    Ok(terminal::check_truecolor_support(
+        &term,
+        colorterm.as_deref(),
+    ))
+}
+
+/// 現在の設定ファイルのパス（`ORTHRUS_CONFIG` で上書きされていればそのパス）
+#[tauri::command]
+fn config_path() -> String {
+    Config::config_path().to_string_lossy().to_string()
+}
+
+/// 設定ディレクトリに書き込めるか（なければ作成）と、その絶対パス
+#[tauri::command]
+fn config_writable() -> ConfigDirStatus {
+    Config::config_dir_status()
+}
+
+/// 2つの設定の差分（保存前に「何が変わるか」を表示する用）
+#[tauri::command]
+fn config_diff(old: Config, new: Config) -> Vec<ConfigChange> {
+    config::config_diff(&old, &new)
+}
+
+/// プロジェクトのPythonインタプリタを推測（設定画面の自動検出ボタン用）
+#[tauri::command]
+fn detect_python(project_path: String) -> Option<String> {
+    config::detect_python(std::path::Path::new(&project_path))
+}
+
+/// 設定文字列をパースして検証（保存前の設定画面での確認用）
+/// formatは "toml"（デフォルト）または "json"
+#[tauri::command]
+fn parse_config(content: String, format: Option<ConfigFormat>) -> Result<Config, AppError> {
+    Config::from_str_with_format(&content, format.unwrap_or(ConfigFormat::Toml))
+}
+
+/// ターミナルの最終的なカラースキームと、その出所を取得
+/// project_pathを指定するとプロジェクトの .orthrus.toml も考慮する
+#[tauri::command]
+fn resolved_terminal_theme(project_path: Option<String>) -> Result<ResolvedTheme, String> {
+    let config = match project_path {
+        Some(path) => Config::load_for_project_unresolved(std::path::Path::new(&path))?,
+        None => Config::load()?,
+    };
+    Ok(config.terminal.resolved_theme(Some(&Config::config_dir())))
+}
+
+/// バックエンドが発行するイベントの一覧（名前・ペイロードの形・説明）
+#[tauri::command]
+fn event_catalog() -> Vec<events::EventInfo> {
+    events::CATALOG.to_vec()
+}
+
+/// theme_fileを読み込もうとした絶対パスと、その成否・エラーを取得
+#[tauri::command]
+fn theme_resolution_info(project_path: Option<String>) -> Result<ThemeResolution, String> {
+    let config = match project_path {
+        Some(path) => Config::load_for_project_unresolved(std::path::Path::new(&path))?,
+        None => Config::load()?,
+    };
+    Ok(config
+        .terminal
+        .theme_resolution(Some(&Config::config_dir())))
+}
+
+/// ディレクトリ内のテーマファイルを列挙（テーマ選択用）
+#[tauri::command]
+fn list_theme_files(dir: String) -> Result<Vec<ThemeFileEntry>, String> {
+    color_scheme::list_theme_files(std::path::Path::new(&dir))
+}
+
+/// 読み込めるテーマファイルの形式と判定方法（インポート画面の説明用）
+#[tauri::command]
+fn supported_theme_formats() -> Vec<ThemeFormatInfo> {
+    color_scheme::supported_theme_formats()
+}
+
+/// テーマファイルを適用せずに検証し、問題点を返す
+#[tauri::command]
+fn validate_theme_file(path: String, theme_name: Option<String>) -> ThemeValidation {
+    color_scheme::validate_theme_file(std::path::Path::new(&path), theme_name.as_deref())
+}
+
 /// ローカル開発用設定を読み込む
 #[tauri::command]
 fn load_dev_config() -> Option<DevConfig> {
+    read_dev_config()
+}
+
+/// ローカル開発用設定を読み直す（.orthrus.dev.jsonの変更を再起動せずに反映する）
+#[tauri::command]
+fn reload_dev_config() -> Option<DevConfig> {
+    read_dev_config()
+}
+
+fn read_dev_config() -> Option<DevConfig> {
     let mut config = DevConfig::load()?;
     // テーマファイルがある場合は解決
     if let Some(ref mut terminal) = config.config.as_mut().and_then(|c| c.terminal.as_mut()) {
@@ -81,6 +576,9 @@ fn load_dev_config() -> Option<DevConfig> {
 }
 
 /// sphinx-autobuildを起動
+/// `options` は設定ファイル由来の型付きオプション。
+/// source_dir / build_dir / port は呼び出し側の引数が優先される。
+/// extra_args は `options.extra_args` の後ろに追加される
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
 fn start_sphinx(
@@ -91,11 +589,11 @@ fn start_sphinx(
     python_path: String,
     port: u16,
     extra_args: Vec<String>,
+    options: Option<SphinxConfig>,
     manager: State<'_, SharedSphinxManager>,
     app_handle: tauri::AppHandle,
-) -> Result<u16, String> {
-    let mut inner = manager.lock().map_err(|e| e.to_string())?;
-    inner.start(
+) -> Result<u16, AppError> {
+    start_sphinx_v2(
         session_id,
         project_path,
         source_dir,
@@ -103,8 +601,91 @@ fn start_sphinx(
         python_path,
         port,
         extra_args,
+        options,
+        manager,
         app_handle,
     )
+    .map(|started| started.port)
+}
+
+/// バックエンドのログレベルを切り替え、以前のレベルを返す
+/// 不具合の再現時にUIから詳細なログを有効にするため
+#[tauri::command]
+fn set_log_level(level: String) -> Result<LogLevel, String> {
+    let level = level.parse()?;
+    Ok(logging::filter().set(level))
+}
+
+/// ターミナルのシェルが終了するまで待ち、終了コードを返す（自動化スクリプト用）
+/// timeout_ms（省略時60秒）を過ぎたらエラー。終了後にセッションを閉じても終了コードを返す
+#[tauri::command]
+async fn wait_terminal_exit(
+    session_id: String,
+    timeout_ms: Option<u64>,
+    manager: State<'_, SharedTerminalManager>,
+) -> Result<u32, String> {
+    let rx = {
+        let inner = manager.lock().map_err(|e| e.to_string())?;
+        inner.wait_for_exit(&session_id)?
+    };
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(60_000));
+    terminal::await_exit(&session_id, rx, timeout).await
+}
+
+/// sphinx-autobuildを再起動するまでの待ち時間（ミリ秒）
+/// attemptは0始まりの試行回数。serverを省略した場合はグローバル設定の `[sphinx.server]` で計算する
+/// （自動再起動はないため、再起動を繰り返すフロントエンド用）
+#[tauri::command]
+fn sphinx_restart_delay(attempt: u32, server: Option<ServerConfig>) -> u64 {
+    let server = server.unwrap_or_else(|| Config::load().unwrap_or_default().sphinx.server);
+    let backoff = RestartBackoff::from_config(&server);
+    backoff.delay(attempt).as_millis() as u64
+}
+
+/// sphinx-autobuildを起動し、ホスト・ポート・プレビューURLを返す
+/// 引数は `start_sphinx` と同じ（`start_sphinx` はポートのみを返す互換用）
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn start_sphinx_v2(
+    session_id: String,
+    project_path: String,
+    source_dir: String,
+    build_dir: String,
+    python_path: String,
+    port: u16,
+    extra_args: Vec<String>,
+    options: Option<SphinxConfig>,
+    manager: State<'_, SharedSphinxManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<SphinxStartResult, AppError> {
+    let mut options = options.unwrap_or_default();
+    options.source_dir = source_dir;
+    options.build_dir = build_dir;
+    options.server.port = port;
+
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.start(
+        session_id.clone(),
+        project_path,
+        python_path,
+        &options,
+        extra_args,
+        app_handle,
+    )?;
+    Ok(inner.start_result(&session_id)?)
+}
+
+/// 設定・追加引数を変えてsphinx-autobuildを再設定（変更がなければ再起動しない）
+/// options省略時は起動時の設定を使う
+#[tauri::command(async)]
+fn reconfigure_sphinx(
+    session_id: String,
+    extra_args: Vec<String>,
+    options: Option<SphinxConfig>,
+    manager: State<'_, SharedSphinxManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<u16, AppError> {
+    sphinx::reconfigure(&manager, session_id, options, extra_args, app_handle)
 }
 
 /// sphinx-autobuildを停止
@@ -114,6 +695,146 @@ fn stop_sphinx(session_id: String, manager: State<'_, SharedSphinxManager>) -> R
     inner.stop(&session_id)
 }
 
+/// sphinx-autobuildを穏やかに停止し、ポートが再びbindできるようになるまで待つ
+/// timeout_ms（省略時5秒）以内に空けばtrue。
+/// セッションがない場合とattachしたセッション（登録を解除するだけ）は待たずにtrue
+#[tauri::command(async)]
+fn stop_sphinx_and_wait(
+    session_id: String,
+    timeout_ms: Option<u64>,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<bool, String> {
+    // 停止・待機の間に他のコマンドを止めないよう、管理から外したらロックを外す
+    let stopping = {
+        let mut inner = manager.lock().map_err(|e| e.to_string())?;
+        inner.take_for_stop(&session_id)
+    };
+    let Some(stopping) = stopping.filter(|stopping| !stopping.is_attached()) else {
+        return Ok(true);
+    };
+    let port = stopping.terminate()?;
+    Ok(sphinx::wait_for_port_free(
+        port,
+        Duration::from_millis(timeout_ms.unwrap_or(5000)),
+    ))
+}
+
+/// 次のビルド完了（`sphinx_built`）まで待つ（起動直後の読み込み表示を消す用）
+/// timeout_ms（省略時60秒）を過ぎるか、プロセスが終了・停止したらエラー
+#[tauri::command]
+async fn await_first_build(
+    session_id: String,
+    timeout_ms: Option<u64>,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<(), String> {
+    let rx = {
+        let inner = manager.lock().map_err(|e| e.to_string())?;
+        inner.wait_for_build(&session_id)?
+    };
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(60_000));
+    sphinx::await_build(&session_id, rx, timeout).await
+}
+
+/// 次のビルドが完了したらプレビューをブラウザで1回開く（dev configの auto_open_browser 用）
+/// timeout_ms（省略時60秒）を過ぎるか、ビルドせずに終了・停止した場合は開かずにエラー
+#[tauri::command]
+async fn open_preview_after_first_build(
+    session_id: String,
+    timeout_ms: Option<u64>,
+    manager: State<'_, SharedSphinxManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let (rx, url) = {
+        let inner = manager.lock().map_err(|e| e.to_string())?;
+        (
+            inner.wait_for_build(&session_id)?,
+            inner.start_result(&session_id)?.url,
+        )
+    };
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(60_000));
+    sphinx::open_after_build(&session_id, rx, timeout, || {
+        app_handle
+            .opener()
+            .open_url(&url, None::<&str>)
+            .map_err(|e| AppError::open_failed(&url, &e.to_string()).to_string())
+    })
+    .await
+}
+
+/// sphinx-buildで1回だけビルド（builder未指定ならhtml）
+#[tauri::command]
+fn build_sphinx_once(
+    session_id: String,
+    project_path: String,
+    python_path: String,
+    options: Option<SphinxConfig>,
+    builder: Option<String>,
+    manager: State<'_, SharedSphinxManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+    let builder = builder.unwrap_or_else(|| "html".to_string());
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.build_once(
+        session_id,
+        project_path,
+        python_path,
+        &options,
+        &builder,
+        app_handle,
+    )
+}
+
+/// sphinx-buildで指定した1ファイルだけビルドし、出力先・終了コード・標準エラー出力を返す
+/// builder未指定ならhtml（単一ファイルに対応しないビルダーはエラー）
+/// config_dir / locale / extra_argsは `options` から `start_sphinx` と同じように使う
+/// （source_dir / build_dirは呼び出し側の引数が優先される）
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+fn build_single_file(
+    project_path: String,
+    source_dir: String,
+    build_dir: String,
+    python_path: String,
+    source_rel_path: String,
+    builder: Option<String>,
+    options: Option<SphinxConfig>,
+) -> Result<SingleFileBuild, String> {
+    let mut options = options.unwrap_or_default();
+    options.source_dir = source_dir;
+    options.build_dir = build_dir;
+    sphinx::build_single_file(
+        &project_path,
+        &python_path,
+        &options,
+        builder.as_deref().unwrap_or("html"),
+        &source_rel_path,
+    )
+}
+
+/// 実行中の単発ビルドをキャンセル
+#[tauri::command]
+fn cancel_sphinx_build(
+    session_id: String,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<bool, String> {
+    // 停止の猶予の間に他のコマンドを止めないよう、ロックの外でキャンセルする
+    let build = {
+        let mut inner = manager.lock().map_err(|e| e.to_string())?;
+        inner.running_build(&session_id)
+    };
+    match build {
+        Some(build) => build.cancel(),
+        None => Ok(false),
+    }
+}
+
+/// ポートが空いているかを確認（使用中ならfalse、権限不足はエラー）
+#[tauri::command]
+fn is_port_free(port: u16) -> Result<bool, AppError> {
+    SphinxManager::check_port(port)
+}
+
 /// sphinxのポートを取得
 #[tauri::command]
 fn get_sphinx_port(
@@ -124,36 +845,261 @@ fn get_sphinx_port(
     Ok(inner.get_port(&session_id))
 }
 
+/// 直近のビルドの警告・エラーを、指定した重大度（info / warning / error）以上に絞って取得
+#[tauri::command]
+fn get_sphinx_diagnostics(
+    session_id: String,
+    min_severity: Option<Severity>,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<Vec<SphinxDiagnostic>, String> {
+    let inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.diagnostics(&session_id, min_severity.unwrap_or(Severity::Info))
+}
+
+/// 外部で起動済みのsphinx-autobuildをセッションとして登録（停止時もkillしない）
+#[tauri::command]
+fn attach_sphinx(
+    session_id: String,
+    host: String,
+    port: u16,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<(), String> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.attach(session_id, host, port)
+}
+
+/// sphinxセッションを管理から外す（`attach_sphinx` の逆。プロセスはkillしない）
+/// 呼び出し側で追跡できるようPIDとポートを返す
+#[tauri::command]
+fn detach_sphinx(
+    session_id: String,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<DetachedSphinx, String> {
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.detach(&session_id)
+}
+
+/// sphinxセッションの一覧（起動したものとattachしたものを `owned` で区別）
+#[tauri::command]
+fn list_sphinx_sessions(
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<Vec<SphinxSessionInfo>, String> {
+    let inner = manager.lock().map_err(|e| e.to_string())?;
+    Ok(inner.list_sessions())
+}
+
+/// ソースファイルのプレビューURLを取得（ブラウザは開かない）
+#[tauri::command]
+fn preview_url_for(
+    session_id: String,
+    source_rel_path: String,
+    manager: State<'_, SharedSphinxManager>,
+) -> Result<String, String> {
+    let inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.preview_url_for(&session_id, &source_rel_path)
+}
+
+/// ファイルダイアログなどで得た絶対パスを、ソースディレクトリからの相対パスに変換
+/// （`preview_url_for` に渡す形式。ソースディレクトリ外ならエラー）
+#[tauri::command]
+fn relativize_source(
+    project_path: String,
+    source_dir: String,
+    abs_path: String,
+) -> Result<String, String> {
+    let source_dir = sphinx::resolve_project_dir(std::path::Path::new(&project_path), &source_dir);
+    preview::relativize_source(&source_dir, std::path::Path::new(&abs_path))
+}
+
+/// ビルドディレクトリに書き込めるか（なければ作成する）。起動前の確認用
+/// 権限の問題でsphinxの分かりにくいエラーになる前に、解決したパスとともに知らせる
+#[tauri::command]
+fn build_dir_writable(project_path: String, build_dir: String) -> BuildDirStatus {
+    sphinx::build_dir_status(std::path::Path::new(&project_path), &build_dir)
+}
+
+/// ビルド出力ディレクトリの合計サイズとファイル数を取得
+#[tauri::command]
+fn build_output_size(project_path: String, build_dir: String) -> Result<BuildOutputSize, String> {
+    let dir = sphinx::resolve_project_dir(std::path::Path::new(&project_path), &build_dir);
+    build_output::output_size(&dir)
+}
+
+/// ソースがビルド出力より新しいか（「ドキュメントが古い可能性」の表示用）
+/// ビルドディレクトリがなければtrue
+#[tauri::command]
+fn build_is_stale(
+    project_path: String,
+    source_dir: String,
+    build_dir: String,
+) -> Result<bool, String> {
+    let project_path = std::path::Path::new(&project_path);
+    build_output::is_stale(
+        &sphinx::resolve_project_dir(project_path, &source_dir),
+        &sphinx::resolve_project_dir(project_path, &build_dir),
+    )
+}
+
+/// ビルド済みHTMLページの一覧を取得
+#[tauri::command]
+fn list_built_pages(project_path: String, build_dir: String) -> Result<Vec<String>, String> {
+    let dir = sphinx::resolve_project_dir(std::path::Path::new(&project_path), &build_dir);
+    build_output::list_pages(&dir)
+}
+
+/// ビルド出力の構成からhtml / dirhtmlのどちらでビルドされたかを推定する（プレビューURLの対応付け用）
+/// ビルドがない、または判別できなければ `unknown`
+#[tauri::command]
+fn detect_build_format(project_path: String, build_dir: String) -> Result<BuildFormat, String> {
+    let dir = sphinx::resolve_project_dir(std::path::Path::new(&project_path), &build_dir);
+    build_output::detect_format(&dir)
+}
+
+/// ソースファイルに対応するビルド済みHTMLを読む（iframeを使わないプレビュー用）
+/// builder省略時はhtml。まだビルドされていなければエラー
+#[tauri::command]
+fn read_built_page(
+    project_path: String,
+    build_dir: String,
+    source_rel_path: String,
+    builder: Option<preview::Builder>,
+) -> Result<String, String> {
+    let dir = sphinx::resolve_project_dir(std::path::Path::new(&project_path), &build_dir);
+    build_output::read_page(&dir, &source_rel_path, builder.unwrap_or_default())
+}
+
 /// ブラウザでURLを開く
+/// 開けない場合は原因の見当を付けた `openFailed` を返す（フロントエンドでURLをコピーして代替する）
 #[tauri::command]
-fn open_in_browser(url: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+fn open_in_browser(url: String, app_handle: tauri::AppHandle) -> Result<(), AppError> {
     app_handle
         .opener()
         .open_url(&url, None::<&str>)
-        .map_err(|e| e.to_string())
+        .map_err(|e| AppError::open_failed(&url, &e.to_string()))
+}
+
+/// ファイルをOSの既定のアプリで開く（画像など、エディタで開かないもの）
+#[tauri::command]
+fn open_path_default(path: String, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    app_handle
+        .opener()
+        .open_path(&path, None::<&str>)
+        .map_err(|e| AppError::open_failed(&path, &e.to_string()))
+}
+
+/// ファイルをエディタと既定のアプリのどちらで開くか（editor.text_extensionsで判定）
+/// project_pathを指定するとプロジェクトの .orthrus.toml も考慮する
+#[tauri::command]
+fn open_target_for(path: String, project_path: Option<String>) -> Result<OpenTarget, String> {
+    let config = match project_path {
+        Some(project_path) => {
+            Config::load_for_project_unresolved(std::path::Path::new(&project_path))?
+        }
+        None => Config::load()?,
+    };
+    Ok(config.editor.open_target(std::path::Path::new(&path)))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logging::init_from_env();
     let terminal_manager = create_terminal_manager();
     let sphinx_manager = create_sphinx_manager();
+    let reaper_terminal_manager = terminal_manager.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(terminal_manager)
         .manage(sphinx_manager)
+        .setup(move |app| {
+            // アイドルが続くターミナルを自動終了
+            let idle_kill_secs = Config::load()
+                .ok()
+                .and_then(|config| config.terminal.idle_kill_secs)
+                .filter(|&secs| secs > 0);
+            if let Some(secs) = idle_kill_secs {
+                start_idle_reaper(
+                    reaper_terminal_manager,
+                    Duration::from_secs(secs),
+                    app.handle().clone(),
+                );
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             spawn_terminal,
             pty_write,
+            pty_write_paste,
+            pty_interrupt,
+            pty_kill_foreground,
+            pty_focus,
             pty_resize,
+            get_terminal_scrollback,
+            get_terminal_scrollback_gz,
+            clear_terminal_scrollback,
+            get_terminal_info,
+            list_terminals,
+            new_session_id,
+            effective_spawn_env,
+            get_terminal_metrics,
+            restart_all_terminals,
+            set_all_terminals_color_scheme,
+            spawn_command_capture,
+            close_terminal,
             kill_terminal,
+            diagnostic_dump,
+            app_version,
+            canonicalize_project,
+            record_recent_project,
+            list_recent_projects,
+            rename_terminal,
             load_config,
+            load_project_config,
+            check_truecolor_support,
             load_dev_config,
+            reload_dev_config,
+            parse_config,
+            config_diff,
+            config_writable,
+            config_path,
+            detect_python,
+            event_catalog,
+            resolved_terminal_theme,
+            theme_resolution_info,
+            list_theme_files,
+            validate_theme_file,
+            supported_theme_formats,
             start_sphinx,
+            start_sphinx_v2,
+            sphinx_restart_delay,
+            set_log_level,
+            wait_terminal_exit,
+            reconfigure_sphinx,
             stop_sphinx,
+            stop_sphinx_and_wait,
+            await_first_build,
+            open_preview_after_first_build,
+            build_sphinx_once,
+            build_single_file,
+            cancel_sphinx_build,
+            is_port_free,
             get_sphinx_port,
+            get_sphinx_diagnostics,
+            attach_sphinx,
+            detach_sphinx,
+            list_sphinx_sessions,
+            preview_url_for,
+            relativize_source,
+            build_output_size,
+            build_is_stale,
+            list_built_pages,
+            build_dir_writable,
+            detect_build_format,
+            read_built_page,
             open_in_browser,
+            open_path_default,
+            open_target_for,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,146 @@
+//! バックエンドのログの出力レベル
+//! 起動時に環境変数 `ORTHRUS_LOG` から読み、`set_log_level` で実行中に変更できる
+
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 起動時のレベルを指定する環境変数
+pub const ENV_VAR: &str = "ORTHRUS_LOG";
+
+/// 指定がない場合のレベル
+const DEFAULT_LEVEL: LogLevel = LogLevel::Warn;
+
+/// ログレベル（後ろほど詳細）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        Self::ALL
+            .get(value as usize)
+            .copied()
+            .unwrap_or(DEFAULT_LEVEL)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|level| level.as_str()).collect();
+                format!("不正なログレベル: {} ({})", s, names.join(", "))
+            })
+    }
+}
+
+/// 実行中に切り替えられるレベルの保持先
+#[derive(Debug)]
+pub struct LogFilter(AtomicU8);
+
+impl LogFilter {
+    pub const fn new(level: LogLevel) -> Self {
+        Self(AtomicU8::new(level as u8))
+    }
+
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_u8(self.0.load(Ordering::Relaxed))
+    }
+
+    /// レベルを切り替え、以前のレベルを返す
+    pub fn set(&self, level: LogLevel) -> LogLevel {
+        LogLevel::from_u8(self.0.swap(level as u8, Ordering::Relaxed))
+    }
+
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.level()
+    }
+}
+
+static FILTER: LogFilter = LogFilter::new(DEFAULT_LEVEL);
+
+/// アプリ全体で共有するレベル
+pub fn filter() -> &'static LogFilter {
+    &FILTER
+}
+
+/// 指定レベルのログを出力するか
+pub fn enabled(level: LogLevel) -> bool {
+    FILTER.enabled(level)
+}
+
+/// `ORTHRUS_LOG` からレベルを設定する（不正な値は警告して既定のまま）
+pub fn init_from_env() {
+    let Ok(value) = std::env::var(ENV_VAR) else {
+        return;
+    };
+    match value.parse() {
+        Ok(level) => {
+            FILTER.set(level);
+        }
+        Err(e) => eprintln!("{}: {}", ENV_VAR, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!("debug".parse::<LogLevel>(), Ok(LogLevel::Debug));
+        assert_eq!(" WARN ".parse::<LogLevel>(), Ok(LogLevel::Warn));
+        let err = "verbose".parse::<LogLevel>().unwrap_err();
+        assert!(err.contains("verbose"));
+        assert!(err.contains("trace"));
+    }
+
+    #[test]
+    fn test_filter_toggles_levels() {
+        let filter = LogFilter::new(LogLevel::Warn);
+        assert!(filter.enabled(LogLevel::Error));
+        assert!(!filter.enabled(LogLevel::Debug));
+
+        assert_eq!(filter.set(LogLevel::Debug), LogLevel::Warn);
+        assert!(filter.enabled(LogLevel::Debug));
+        assert!(!filter.enabled(LogLevel::Trace));
+
+        assert_eq!(filter.set(LogLevel::Off), LogLevel::Debug);
+        assert!(!filter.enabled(LogLevel::Error));
+        assert!(!filter.enabled(LogLevel::Off));
+        assert_eq!(filter.level(), LogLevel::Off);
+    }
+}
@@ -0,0 +1,93 @@
+//! プロジェクトごとに最後に使ったプレビューポートのキャッシュ
+//!
+//! 再起動してもポートが変わらないようにし、ブックマークやプロキシ設定を安定させる
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// キャッシュファイル名
+const PORT_CACHE_FILE: &str = "port-cache.json";
+
+/// ポートキャッシュ（キーはプロジェクトの正規化済みパス）
+pub struct PortCache {
+    path: PathBuf,
+    ports: HashMap<String, u16>,
+}
+
+impl PortCache {
+    /// キャッシュファイルを読み込む（存在しない・壊れている場合は空）
+    pub fn open(path: PathBuf) -> Self {
+        let ports = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, ports }
+    }
+
+    /// 既定の場所（~/.cache/orthrus/port-cache.json）のキャッシュを開く
+    pub fn open_default() -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("orthrus");
+        Some(Self::open(dir.join(PORT_CACHE_FILE)))
+    }
+
+    /// 前回使用したポート
+    pub fn get(&self, project_path: &Path) -> Option<u16> {
+        self.ports.get(&cache_key(project_path)).copied()
+    }
+
+    /// 使用したポートを記録して保存
+    pub fn set(&mut self, project_path: &Path, port: u16) -> Result<(), String> {
+        self.ports.insert(cache_key(project_path), port);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("キャッシュディレクトリの作成に失敗: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(&self.ports)
+            .map_err(|e| format!("ポートキャッシュのシリアライズに失敗: {}", e))?;
+        std::fs::write(&self.path, content)
+            .map_err(|e| format!("ポートキャッシュの書き込みに失敗: {}", e))
+    }
+}
+
+/// シンボリックリンクや `..` の違いで別プロジェクト扱いにならないよう正規化
+fn cache_key(project_path: &Path) -> String {
+    project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache").join(PORT_CACHE_FILE);
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let mut cache = PortCache::open(cache_path.clone());
+        assert_eq!(cache.get(&project), None);
+        cache.set(&project, 8123).unwrap();
+
+        // 別の表記でも同じプロジェクトとして扱う
+        let reopened = PortCache::open(cache_path);
+        assert_eq!(
+            reopened.get(&project.join("..").join("project")),
+            Some(8123)
+        );
+    }
+
+    #[test]
+    fn test_port_cache_ignores_broken_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(PORT_CACHE_FILE);
+        std::fs::write(&cache_path, "not json").unwrap();
+
+        let cache = PortCache::open(cache_path);
+        assert_eq!(cache.get(dir.path()), None);
+    }
+}
@@ -0,0 +1,228 @@
+//! ソースファイルとビルド出力（プレビューURL）の対応付け
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+/// Sphinxのビルダー（出力のディレクトリ構成が異なる）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Builder {
+    /// `guide/intro.rst` -> `guide/intro.html`
+    #[default]
+    Html,
+    /// `guide/intro.rst` -> `guide/intro/index.html`（URLは `guide/intro/`）
+    DirHtml,
+}
+
+/// ソースとして扱う拡張子
+const SOURCE_EXTENSIONS: &[&str] = &["rst", "md", "txt"];
+
+impl Builder {
+    /// sphinx-autobuildの引数から `-b` / `--builder` を読み取る（未指定ならhtml）
+    pub fn from_args(args: &[String]) -> Self {
+        let mut name = None;
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "-b" || arg == "--builder" {
+                name = iter.next().map(String::as_str);
+            } else if let Some(value) = arg.strip_prefix("--builder=") {
+                name = Some(value);
+            } else if let Some(value) = arg.strip_prefix("-b").filter(|v| !v.is_empty()) {
+                name = Some(value);
+            }
+        }
+
+        match name {
+            Some("dirhtml") => Builder::DirHtml,
+            _ => Builder::Html,
+        }
+    }
+}
+
+/// ソースディレクトリからの相対パスを、ビルド出力ルートからのURLパスに変換
+/// 区切り文字は `/` に正規化する
+pub fn page_path_for_source(source_rel_path: &str, builder: Builder) -> String {
+    let normalized = source_rel_path.replace('\\', "/");
+    let trimmed = normalized.trim_start_matches("./").trim_start_matches('/');
+
+    // 拡張子を除去したドキュメント名
+    let docname = match trimmed.rsplit_once('.') {
+        Some((stem, ext)) if SOURCE_EXTENSIONS.contains(&ext) && !stem.ends_with('/') => stem,
+        _ => trimmed,
+    };
+
+    match builder {
+        Builder::Html => format!("{}.html", docname),
+        Builder::DirHtml => {
+            // index はディレクトリそのもの
+            if docname == "index" {
+                String::new()
+            } else if let Some(dir) = docname.strip_suffix("/index") {
+                format!("{}/", dir)
+            } else {
+                format!("{}/", docname)
+            }
+        }
+    }
+}
+
+/// 存在しないファイル（未保存の新規ファイル）は親ディレクトリだけを解決する
+fn canonicalize_lenient(path: &Path) -> std::path::PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 絶対パスをソースディレクトリからの相対パス（`/` 区切り）に変換
+/// 存在するパスはシンボリックリンクを解決してから比較する。ソースディレクトリ外ならエラー
+pub fn relativize_source(source_dir: &Path, abs_path: &Path) -> Result<String, String> {
+    let source_dir = source_dir
+        .canonicalize()
+        .unwrap_or_else(|_| source_dir.to_path_buf());
+    let abs_path = canonicalize_lenient(abs_path);
+    let relative = abs_path.strip_prefix(&source_dir).map_err(|_| {
+        format!(
+            "ソースディレクトリ外のファイルです: {} (ソースディレクトリ: {})",
+            abs_path.display(),
+            source_dir.display()
+        )
+    })?;
+
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "ソースディレクトリ外のファイルです: {}",
+                    abs_path.display()
+                ))
+            }
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// ベースパスを `prefix/` 形式に正規化（空ならルート）
+fn normalize_base_path(base_path: Option<&str>) -> String {
+    let trimmed = base_path.unwrap_or("").trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", trimmed)
+    }
+}
+
+/// プレビューサーバーのURLを組み立てる
+/// base_pathはリバースプロキシ配下に埋め込む場合のパスプレフィックス
+pub fn preview_url(host: &str, port: u16, base_path: Option<&str>, page_path: &str) -> String {
+    format!(
+        "http://{}:{}/{}{}",
+        host,
+        port,
+        normalize_base_path(base_path),
+        page_path
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_path_html() {
+        assert_eq!(
+            page_path_for_source("index.rst", Builder::Html),
+            "index.html"
+        );
+        assert_eq!(
+            page_path_for_source("guide/intro.rst", Builder::Html),
+            "guide/intro.html"
+        );
+        assert_eq!(
+            page_path_for_source("guide\\setup.md", Builder::Html),
+            "guide/setup.html"
+        );
+    }
+
+    #[test]
+    fn test_page_path_dirhtml() {
+        assert_eq!(page_path_for_source("index.rst", Builder::DirHtml), "");
+        assert_eq!(
+            page_path_for_source("guide/intro.rst", Builder::DirHtml),
+            "guide/intro/"
+        );
+        assert_eq!(
+            page_path_for_source("guide/index.rst", Builder::DirHtml),
+            "guide/"
+        );
+    }
+
+    #[test]
+    fn test_relativize_source() {
+        let project = tempfile::tempdir().unwrap();
+        let source_dir = project.path().join("docs");
+        std::fs::create_dir_all(source_dir.join("guide")).unwrap();
+        std::fs::write(source_dir.join("guide/intro.rst"), "").unwrap();
+        std::fs::write(project.path().join("README.rst"), "").unwrap();
+
+        assert_eq!(
+            relativize_source(&source_dir, &source_dir.join("guide/intro.rst")),
+            Ok("guide/intro.rst".to_string())
+        );
+        // 未保存の新規ファイルも変換できる
+        assert_eq!(
+            relativize_source(&source_dir, &source_dir.join("new.md")),
+            Ok("new.md".to_string())
+        );
+        assert!(relativize_source(&source_dir, &project.path().join("README.rst")).is_err());
+    }
+
+    #[test]
+    fn test_builder_from_args() {
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(Builder::from_args(&args(&[])), Builder::Html);
+        assert_eq!(
+            Builder::from_args(&args(&["-b", "dirhtml"])),
+            Builder::DirHtml
+        );
+        assert_eq!(Builder::from_args(&args(&["-bdirhtml"])), Builder::DirHtml);
+        assert_eq!(
+            Builder::from_args(&args(&["--builder=dirhtml"])),
+            Builder::DirHtml
+        );
+        assert_eq!(Builder::from_args(&args(&["-b", "html"])), Builder::Html);
+    }
+
+    #[test]
+    fn test_preview_url() {
+        assert_eq!(
+            preview_url("127.0.0.1", 8000, None, "guide/intro.html"),
+            "http://127.0.0.1:8000/guide/intro.html"
+        );
+        assert_eq!(
+            preview_url("127.0.0.1", 8000, Some(""), ""),
+            "http://127.0.0.1:8000/"
+        );
+    }
+
+    #[test]
+    fn test_preview_url_with_base_path() {
+        assert_eq!(
+            preview_url("127.0.0.1", 8000, Some("docs"), "guide/intro.html"),
+            "http://127.0.0.1:8000/docs/guide/intro.html"
+        );
+        assert_eq!(
+            preview_url("127.0.0.1", 8000, Some("/preview/docs/"), ""),
+            "http://127.0.0.1:8000/preview/docs/"
+        );
+    }
+}
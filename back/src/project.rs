@@ -0,0 +1,64 @@
+//! プロジェクトのディレクトリの検証
+
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// プロジェクトのパスを正規化した絶対パスにし、ディレクトリであることを確認する
+pub fn canonicalize_project(path: &Path) -> Result<PathBuf, AppError> {
+    let display = path.to_string_lossy().to_string();
+    let canonical = path.canonicalize().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::ProjectNotFound {
+            path: display.clone(),
+            suggestions: Vec::new(),
+        },
+        _ => AppError::Other(format!(
+            "プロジェクトのパスを解決できません: {} ({})",
+            display, e
+        )),
+    })?;
+    if !canonical.is_dir() {
+        return Err(AppError::ProjectNotDirectory { path: display });
+    }
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_project_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        let canonical = canonicalize_project(&dir.path().join("docs/../docs")).unwrap();
+        assert!(canonical.is_absolute());
+        assert_eq!(canonical, dir.path().join("docs").canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_canonicalize_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("conf.py");
+        std::fs::write(&file, "").unwrap();
+        assert_eq!(
+            canonicalize_project(&file),
+            Err(AppError::ProjectNotDirectory {
+                path: file.to_string_lossy().to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_project_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            canonicalize_project(&missing),
+            Err(AppError::ProjectNotFound {
+                path: missing.to_string_lossy().to_string(),
+                suggestions: Vec::new(),
+            })
+        );
+    }
+}
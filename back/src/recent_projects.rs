@@ -0,0 +1,149 @@
+//! 最近開いたプロジェクトの一覧（新しい順、重複なし）
+
+use std::path::{Path, PathBuf};
+
+use crate::project;
+
+/// 一覧のファイル名
+const RECENT_FILE: &str = "recent.json";
+
+/// 保存する件数の上限
+const RECENT_LIMIT: usize = 20;
+
+/// 最近開いたプロジェクト（正規化済みパス、新しい順）
+pub struct RecentProjects {
+    path: PathBuf,
+    projects: Vec<String>,
+}
+
+impl RecentProjects {
+    /// 一覧ファイルを読み込む（存在しない・壊れている場合は空）
+    pub fn open(path: PathBuf) -> Self {
+        let projects = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, projects }
+    }
+
+    /// 既定の場所（~/.local/share/orthrus/recent.json）の一覧を開く
+    pub fn open_default() -> Option<Self> {
+        let dir = dirs::data_dir()?.join("orthrus");
+        Some(Self::open(dir.join(RECENT_FILE)))
+    }
+
+    /// 開いたプロジェクトを先頭に記録して保存（既にあれば先頭へ移動）
+    /// 存在しなくなったプロジェクトはこのとき取り除く
+    pub fn record(&mut self, project_path: &Path) -> Result<(), String> {
+        let key = project::canonicalize_project(project_path)?
+            .to_string_lossy()
+            .to_string();
+        self.projects.retain(|project| *project != key);
+        self.projects.insert(0, key);
+        self.projects.retain(|project| Path::new(project).is_dir());
+        self.projects.truncate(RECENT_LIMIT);
+        self.save()
+    }
+
+    /// 存在するプロジェクトを新しい順に最大limit件
+    pub fn list(&self, limit: usize) -> Vec<String> {
+        self.projects
+            .iter()
+            .filter(|project| Path::new(project).is_dir())
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("データディレクトリの作成に失敗: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(&self.projects)
+            .map_err(|e| format!("最近のプロジェクトのシリアライズに失敗: {}", e))?;
+        std::fs::write(&self.path, content)
+            .map_err(|e| format!("最近のプロジェクトの書き込みに失敗: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 一時ディレクトリにプロジェクトを作り、正規化済みパスを返す
+    fn make_project(dir: &Path, name: &str) -> PathBuf {
+        let project = dir.join(name);
+        std::fs::create_dir(&project).unwrap();
+        project.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_record_orders_and_dedupes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data").join(RECENT_FILE);
+        let a = make_project(dir.path(), "a");
+        let b = make_project(dir.path(), "b");
+
+        let mut recent = RecentProjects::open(file.clone());
+        recent.record(&a).unwrap();
+        recent.record(&b).unwrap();
+        // 別の表記で開き直しても重複せず先頭へ移動する
+        recent.record(&b.join("..").join("a")).unwrap();
+
+        let expected = [a.to_string_lossy(), b.to_string_lossy()];
+        assert_eq!(recent.list(10), expected);
+        assert_eq!(RecentProjects::open(file).list(10), expected);
+        assert_eq!(recent.list(1), [a.to_string_lossy()]);
+    }
+
+    #[test]
+    fn test_missing_projects_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(RECENT_FILE);
+        let a = make_project(dir.path(), "a");
+        let b = make_project(dir.path(), "b");
+
+        let mut recent = RecentProjects::open(file.clone());
+        recent.record(&a).unwrap();
+        recent.record(&b).unwrap();
+        std::fs::remove_dir(&a).unwrap();
+        assert_eq!(recent.list(10), [b.to_string_lossy()]);
+
+        // 記録し直すと保存された一覧からも消える
+        recent.record(&b).unwrap();
+        let saved: Vec<String> =
+            serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(saved, [b.to_string_lossy()]);
+    }
+
+    #[test]
+    fn test_record_caps_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recent = RecentProjects::open(dir.path().join(RECENT_FILE));
+        for i in 0..RECENT_LIMIT + 5 {
+            recent
+                .record(&make_project(dir.path(), &format!("p{}", i)))
+                .unwrap();
+        }
+        let listed = recent.list(usize::MAX);
+        assert_eq!(listed.len(), RECENT_LIMIT);
+        assert!(listed[0].ends_with(&format!("p{}", RECENT_LIMIT + 4)));
+    }
+
+    #[test]
+    fn test_record_rejects_missing_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recent = RecentProjects::open(dir.path().join(RECENT_FILE));
+        assert!(recent.record(&dir.path().join("missing")).is_err());
+        assert!(recent.list(10).is_empty());
+    }
+
+    #[test]
+    fn test_open_ignores_broken_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(RECENT_FILE);
+        std::fs::write(&file, "not json").unwrap();
+        assert!(RecentProjects::open(file).list(10).is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+//! 不具合報告用の実行状態のダンプ
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::ansi;
+use crate::config::Config;
+use crate::diagnostics::{Severity, SphinxDiagnostic};
+use crate::error::AppError;
+use crate::sphinx::{SphinxManager, SphinxSessionInfo};
+use crate::terminal::{self, TerminalInfo, TerminalManager};
+
+/// ターミナルの出力から含める末尾の行数
+const LOG_TAIL_LINES: usize = 20;
+
+/// ターミナルセッションの状態
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalDump {
+    pub session_id: String,
+    #[serde(flatten)]
+    pub info: TerminalInfo,
+    /// スクロールバックの末尾（エスケープシーケンスは除く。含めない場合はNone）
+    pub log_tail: Option<Vec<String>>,
+}
+
+/// sphinxセッションの状態
+#[derive(Debug, Clone, Serialize)]
+pub struct SphinxDump {
+    #[serde(flatten)]
+    pub info: SphinxSessionInfo,
+    /// sphinx-autobuildに渡した引数（attachしたものはNone）
+    pub args: Option<Vec<String>>,
+    /// 直近のビルドの警告・エラー
+    pub diagnostics: Vec<SphinxDiagnostic>,
+}
+
+/// `diagnostic_dump` の内容
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticDump {
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub config_path: String,
+    /// 読み込んだグローバル設定（読み込めなかった場合はNoneで `config_error` に理由）
+    pub config: Option<Config>,
+    pub config_error: Option<String>,
+    /// 設定と環境から決まる既定のシェル
+    pub shell: String,
+    pub terminals: Vec<TerminalDump>,
+    pub sphinx: Vec<SphinxDump>,
+}
+
+/// `app_version` の内容（About・診断の表示用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppVersion {
+    pub version: &'static str,
+    /// ビルドしたコミット（gitのない環境でビルドした場合はNone）
+    pub git_sha: Option<&'static str>,
+    /// `debug` / `release`
+    pub build_profile: &'static str,
+    pub tauri_version: &'static str,
+}
+
+/// アプリのバージョンとビルド情報
+pub fn app_version() -> AppVersion {
+    AppVersion {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: option_env!("ORTHRUS_GIT_SHA").filter(|sha| !sha.is_empty()),
+        build_profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        tauri_version: tauri::VERSION,
+    }
+}
+
+/// 現在の状態を集める
+/// 設定はマネージャのロックを取る前に読み込んでおく（`Config::load` の結果）
+/// include_log_tailがtrueの場合のみ、各ターミナルの出力の末尾を含める
+pub fn collect(
+    config: Result<Config, AppError>,
+    terminals: &mut TerminalManager,
+    sphinx: &SphinxManager,
+    include_log_tail: bool,
+) -> DiagnosticDump {
+    let (config, config_error) = match config {
+        Ok(config) => (Some(config), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let shell = config
+        .as_ref()
+        .map(|config| {
+            terminal::detect_shell(
+                config.terminal.shell.as_deref(),
+                &config.terminal.shell_fallbacks,
+            )
+        })
+        .unwrap_or_else(|| terminal::detect_shell(None, &[]));
+
+    let terminals = terminals
+        .session_ids()
+        .into_iter()
+        .filter_map(|session_id| {
+            let info = terminals.info(&session_id).ok()?;
+            let log_tail = include_log_tail.then(|| {
+                let scrollback = terminals.scrollback(&session_id).unwrap_or_default();
+                let text = ansi::strip_ansi(&String::from_utf8_lossy(&scrollback));
+                tail_lines(&text, LOG_TAIL_LINES)
+            });
+            Some(TerminalDump {
+                log_tail,
+                session_id,
+                info,
+            })
+        })
+        .collect();
+
+    let sphinx = sphinx
+        .list_sessions()
+        .into_iter()
+        .map(|info| SphinxDump {
+            args: sphinx.launch_args(&info.session_id),
+            diagnostics: sphinx
+                .diagnostics(&info.session_id, Severity::Warning)
+                .unwrap_or_default(),
+            info,
+        })
+        .collect();
+
+    DiagnosticDump {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        config_path: Config::config_path().to_string_lossy().to_string(),
+        config,
+        config_error,
+        shell,
+        terminals,
+        sphinx,
+    }
+}
+
+/// 末尾のn行（空行は除く）
+fn tail_lines(text: &str, n: usize) -> Vec<String> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(n)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// JSON内の文字列に含まれるホームディレクトリを `~` に置き換える
+/// パスの区切りで終わる場合のみ置き換える（`/home/al` は `/home/alice` に一致しない）
+pub fn redact_home(value: &mut serde_json::Value, home: &Path) {
+    let home = home.to_string_lossy();
+    let home = home.trim_end_matches(['/', '\\']);
+    if home.is_empty() {
+        return;
+    }
+    replace_in_strings(value, home, "~");
+}
+
+/// パスの名前の一部になる文字か
+fn is_path_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '_' | '-')
+}
+
+/// textに含まれるfromを、前後がパスの区切りになっている箇所だけtoに置き換える
+fn replace_path(text: &str, from: &str, to: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+    while let Some(index) = rest.find(from) {
+        let before = rest[..index].chars().next_back().or(prev);
+        let after = rest[index + from.len()..].chars().next();
+        let end = index + from.len();
+        result.push_str(&rest[..index]);
+        if !before.is_some_and(is_path_name_char) && !after.is_some_and(is_path_name_char) {
+            result.push_str(to);
+        } else {
+            result.push_str(from);
+        }
+        prev = rest[..end].chars().next_back();
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn replace_in_strings(value: &mut serde_json::Value, from: &str, to: &str) {
+    match value {
+        serde_json::Value::String(text) if text.contains(from) => {
+            *text = replace_path(text, from, to);
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                replace_in_strings(item, from, to);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for item in map.values_mut() {
+                replace_in_strings(item, from, to);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_serializes_with_top_level_keys() {
+        let mut terminals = TerminalManager::new();
+        let sphinx = SphinxManager::new();
+        let dump =
+            serde_json::to_value(collect(Config::load(), &mut terminals, &sphinx, false)).unwrap();
+
+        let object = dump.as_object().unwrap();
+        for key in [
+            "version",
+            "os",
+            "arch",
+            "config_path",
+            "config",
+            "config_error",
+            "shell",
+            "terminals",
+            "sphinx",
+        ] {
+            assert!(object.contains_key(key), "{} がない", key);
+        }
+        assert_eq!(dump["os"], std::env::consts::OS);
+        assert_eq!(dump["terminals"], serde_json::json!([]));
+        assert!(!dump["shell"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_app_version_matches_package() {
+        // Cargo.tomlの [package] のversion
+        let manifest = include_str!("../Cargo.toml");
+        let package_version = manifest
+            .lines()
+            .find_map(|line| line.strip_prefix("version = "))
+            .map(|value| value.trim_matches('"'))
+            .unwrap();
+
+        let version = app_version();
+        assert_eq!(version.version, package_version);
+        assert_eq!(version.tauri_version, tauri::VERSION);
+        assert!(["debug", "release"].contains(&version.build_profile));
+
+        let value = serde_json::to_value(&version).unwrap();
+        for key in ["version", "git_sha", "build_profile", "tauri_version"] {
+            assert!(value.get(key).is_some(), "{} がない", key);
+        }
+    }
+
+    #[test]
+    fn test_redact_home() {
+        let mut value = serde_json::json!({
+            "config_path": "/home/alice/.config/orthrus/config.toml",
+            "terminals": [{ "cwd": "/home/alice/docs", "cols": 80 }],
+            "shell": "/bin/zsh",
+        });
+        redact_home(&mut value, Path::new("/home/alice"));
+        assert_eq!(value["config_path"], "~/.config/orthrus/config.toml");
+        assert_eq!(value["terminals"][0]["cwd"], "~/docs");
+        assert_eq!(value["terminals"][0]["cols"], 80);
+        assert_eq!(value["shell"], "/bin/zsh");
+    }
+
+    #[test]
+    fn test_redact_home_matches_only_whole_path() {
+        let mut value = serde_json::json!({
+            "alice": "/home/alice/docs",
+            "al": "/home/al",
+            "nested": "/srv/home/al/docs",
+            "message": "cwd=/home/al/docs, shell=/home/al/.local/bin/fish",
+        });
+        redact_home(&mut value, Path::new("/home/al/"));
+        assert_eq!(value["alice"], "/home/alice/docs");
+        assert_eq!(value["al"], "~");
+        assert_eq!(value["nested"], "/srv/home/al/docs");
+        assert_eq!(value["message"], "cwd=~/docs, shell=~/.local/bin/fish");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_log_tail_is_opt_in_and_stripped() {
+        use crate::terminal::test_support::{fake_shell, wait_for_scrollback};
+        let (_dir, shell) = fake_shell(
+            "#!/bin/sh\nprintf '\\033[32mok\\033[0m\\n\\033]0;title\\007done\\n'\nsleep 30\n",
+        );
+
+        let mut terminals = TerminalManager::new();
+        let (tx, _rx) = std::sync::mpsc::channel();
+        terminals
+            .spawn_with_sink("test".to_string(), None, Some(shell), 80, 24, tx)
+            .unwrap();
+        wait_for_scrollback(&terminals, "test", "done");
+        let sphinx = SphinxManager::new();
+
+        let dump = collect(Config::load(), &mut terminals, &sphinx, false);
+        assert_eq!(dump.terminals[0].log_tail, None);
+
+        let dump = collect(Config::load(), &mut terminals, &sphinx, true);
+        assert_eq!(
+            dump.terminals[0].log_tail,
+            Some(vec!["ok".to_string(), "done".to_string()])
+        );
+        let _ = terminals.kill("test");
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\r\nb\r\n\r\nc\r\n", 2), ["b", "c"]);
+        assert!(tail_lines("", 5).is_empty());
+    }
+}
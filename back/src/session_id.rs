@@ -0,0 +1,81 @@
+//! セッションIDの生成（フロントエンドとバックエンドで同じ方式を使う）
+
+/// ラベルに使える文字（それ以外は `-` に置き換える）
+fn sanitize_label(label: &str) -> String {
+    label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
+
+/// UUID v4（ランダム）の文字列表記
+pub fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    // バージョン4・RFC 4122のバリアント
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// 新しいセッションID。labelを指定すると `label-<uuid>` の形にする（表示やログでの識別用）
+pub fn new_session_id(label: Option<&str>) -> String {
+    let id = uuid_v4();
+    match label.map(sanitize_label).filter(|label| !label.is_empty()) {
+        Some(label) => format!("{}-{}", label, id),
+        None => id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_session_ids_are_unique() {
+        let ids: HashSet<String> = (0..10_000).map(|_| new_session_id(None)).collect();
+        assert_eq!(ids.len(), 10_000);
+    }
+
+    #[test]
+    fn test_uuid_v4_format() {
+        let id = uuid_v4();
+        let groups: Vec<&str> = id.split('-').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('4'));
+        assert!(matches!(
+            groups[3].chars().next(),
+            Some('8' | '9' | 'a' | 'b')
+        ));
+        assert!(id.chars().all(|c| c == '-' || c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn test_label_prefix() {
+        let id = new_session_id(Some(" docs/main "));
+        assert!(id.starts_with("docs-main-"), "{}", id);
+        assert_eq!(id.len(), "docs-main-".len() + 36);
+        assert_eq!(new_session_id(Some("  ")).len(), 36);
+        assert_eq!(new_session_id(Some("///")).len(), 36);
+    }
+}
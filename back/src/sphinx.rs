@@ -1,29 +1,388 @@
+use crate::ansi;
+use crate::build_output;
+use crate::config::{self, ServerConfig, SphinxConfig};
+use crate::diagnostics::{self, DiagnosticsBuffer, Severity, SphinxDiagnostic};
+use crate::error::AppError;
+use crate::events;
+use crate::logging::{self, LogLevel};
+use crate::port_cache::PortCache;
+use crate::preview::{self, Builder};
+use crate::project;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader};
 use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+/// sphinx-autobuildがlistenするホスト
+const HOST: &str = "127.0.0.1";
 
 /// sphinx-autobuildプロセス情報
 pub struct SphinxProcess {
-    child: Child,
+    /// Noneは `attach` で登録した外部のサーバー（停止・Dropでkillしない）
+    child: Option<Child>,
+    host: String,
     port: u16,
-    /// 停止フラグ（ポーリングスレッド終了用）
+    /// 出力のビルダー（プレビューURLの対応付けに使用）
+    builder: Builder,
+    /// プレビューURLのパスプレフィックス（リバースプロキシ配下用）
+    base_url_path: Option<String>,
+    /// 停止フラグ（監視スレッドの終了とログ通知の停止用）
     stopped: Arc<AtomicBool>,
+    /// 起動待ちのポーリングのキャンセル（停止後に `sphinx_started` を通知しない）
+    readiness: ProbeCancel,
+    /// 起動時のパラメータ（`reconfigure` で使用。attachしたものはNone）
+    launch: Option<LaunchSpec>,
+    /// 直近のビルドの警告・エラー（ログ監視スレッドと共有）
+    diagnostics: Arc<Mutex<DiagnosticsBuffer>>,
+    /// 次のビルド完了を待っている呼び出し（ログ監視スレッドと共有）
+    build_waiters: BuildWaiters,
+}
+
+/// ビルド完了の待ち受けに送る結果（Errはプロセスの終了）
+pub type BuildResult = Result<(), String>;
+
+/// 次のビルド完了の待ち受け
+/// ビルド完了でOk、プロセス終了でErrを送る。停止で破棄された場合は受信側がエラーになる
+#[derive(Debug, Clone, Default)]
+struct BuildWaiters(Arc<Mutex<Vec<oneshot::Sender<BuildResult>>>>);
+
+impl BuildWaiters {
+    fn subscribe(&self) -> oneshot::Receiver<BuildResult> {
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut waiters) = self.0.lock() {
+            waiters.push(tx);
+        }
+        rx
+    }
+
+    /// ログ行（エスケープシーケンス除去済み）がビルド完了なら待ち受けに通知する
+    fn on_line(&self, line: &str) {
+        if is_build_finished(line) {
+            self.notify(Ok(()));
+        }
+    }
+
+    fn on_exited(&self) {
+        self.notify(Err("sphinx-autobuildが終了しました".to_string()));
+    }
+
+    fn notify(&self, result: BuildResult) {
+        if let Ok(mut waiters) = self.0.lock() {
+            for waiter in waiters.drain(..) {
+                let _ = waiter.send(result.clone());
+            }
+        }
+    }
+}
+
+impl SphinxProcess {
+    /// 監視スレッドに停止を通知し、起動待ちのポーリングをキャンセルする
+    fn mark_stopped(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.readiness.cancel();
+    }
+}
+
+/// 起動待ちのポーリングのキャンセル
+/// キャンセルは通知中の `run_unless_cancelled` の完了を待つため、
+/// `cancel` が返った後に通知されることはない
+#[derive(Debug, Clone, Default)]
+struct ProbeCancel(Arc<(Mutex<bool>, Condvar)>);
+
+impl ProbeCancel {
+    fn cancel(&self) {
+        let (cancelled, cvar) = &*self.0;
+        if let Ok(mut cancelled) = cancelled.lock() {
+            *cancelled = true;
+        }
+        cvar.notify_all();
+    }
+
+    /// timeoutだけ待つ（キャンセルされたらすぐに戻り、trueを返す）
+    fn wait(&self, timeout: Duration) -> bool {
+        let (cancelled, cvar) = &*self.0;
+        let Ok(guard) = cancelled.lock() else {
+            return true;
+        };
+        match cvar.wait_timeout_while(guard, timeout, |cancelled| !*cancelled) {
+            Ok((cancelled, _)) => *cancelled,
+            Err(_) => true,
+        }
+    }
+
+    /// キャンセルされていなければfを実行し、実行したらtrueを返す
+    fn run_unless_cancelled(&self, f: impl FnOnce()) -> bool {
+        let Ok(cancelled) = self.0 .0.lock() else {
+            return false;
+        };
+        if *cancelled {
+            return false;
+        }
+        f();
+        true
+    }
+}
+
+/// intervalごとにprobeを試し、成功したらon_readyを呼ぶ（呼んだらtrue）
+/// キャンセルされたら以後は試さず、キャンセル後にon_readyを呼ぶことはない
+fn poll_until_ready(
+    cancel: &ProbeCancel,
+    interval: Duration,
+    mut probe: impl FnMut() -> bool,
+    on_ready: impl FnOnce(),
+) -> bool {
+    loop {
+        if cancel.wait(interval) {
+            return false;
+        }
+        if probe() {
+            return cancel.run_unless_cancelled(on_ready);
+        }
+    }
+}
+
+/// sphinx-autobuildを起動したときのパラメータ
+#[derive(Debug, Clone)]
+struct LaunchSpec {
+    project_path: String,
+    python_path: String,
+    options: SphinxConfig,
+    /// 実際に渡した引数（変更の有無の比較用）
+    args: Vec<String>,
+}
+
+/// `list_sphinx_sessions` で返すセッション情報
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SphinxSessionInfo {
+    pub session_id: String,
+    pub host: String,
+    pub port: u16,
+    /// orthrusが起動したプロセスか（falseは `attach_sphinx` で登録した外部のサーバー）
+    pub owned: bool,
+}
+
+/// `detach_sphinx` の戻り値（管理を外したプロセスを呼び出し側で追跡するため）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DetachedSphinx {
+    /// 起動したプロセスのPID（attachしたセッションはNone）
+    pub pid: Option<u32>,
+    pub host: String,
+    pub port: u16,
+}
+
+/// 管理から外した停止途中のセッション
+/// 停止の猶予の間に他の操作を止めないよう、マネージャのロックを外してから `terminate` する
+#[derive(Debug)]
+pub struct StoppingSphinx {
+    port: u16,
+    /// Noneはattachしたセッション（外部のプロセスには触れない）
+    child: Option<Child>,
+}
+
+impl StoppingSphinx {
+    /// attachしたセッションか（止めるプロセスがない）
+    pub fn is_attached(&self) -> bool {
+        self.child.is_none()
+    }
+
+    /// 穏やかに停止し（SIGTERM後、猶予を過ぎたらkill）、使っていたポートを返す
+    pub fn terminate(mut self) -> Result<u16, String> {
+        if let Some(mut child) = self.child.take() {
+            terminate_gracefully(&mut child, GRACEFUL_STOP_TIMEOUT)
+                .map_err(|e| format!("プロセスの停止に失敗: {}", e))?;
+        }
+        Ok(self.port)
+    }
+}
+
+/// `start_sphinx_v2` の戻り値
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SphinxStartResult {
+    pub host: String,
+    pub port: u16,
+    /// プレビューのトップページのURL（base_url_pathを含む）
+    pub url: String,
+}
+
+/// 単発ビルド（sphinx-build）のプロセス情報
+#[derive(Clone)]
+pub struct OneShotBuild {
+    /// 終了待ちスレッドと共有
+    child: Arc<Mutex<Child>>,
+    /// キャンセルされたか（終了時の通知を切り替える）
+    cancelled: Arc<AtomicBool>,
+}
+
+impl OneShotBuild {
+    fn is_running(&self) -> bool {
+        self.child
+            .lock()
+            .map(|mut child| matches!(child.try_wait(), Ok(None)))
+            .unwrap_or(false)
+    }
+
+    /// 実行中ならキャンセルする（SIGTERM後、猶予を過ぎたらkill）
+    /// 猶予の間マネージャのロックを持たないよう、`SphinxManager::running_build` で取り出してから呼ぶ。
+    /// 既に終了していればfalse（その終了は `sphinx_build_finished` で通知される）
+    pub fn cancel(&self) -> Result<bool, String> {
+        let mut child = self.child.lock().map_err(|e| e.to_string())?;
+        if child
+            .try_wait()
+            .map_err(|e| format!("ビルドの状態の取得に失敗: {}", e))?
+            .is_some()
+        {
+            return Ok(false);
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+        terminate_gracefully(&mut child, GRACEFUL_STOP_TIMEOUT)
+            .map_err(|e| format!("ビルドの停止に失敗: {}", e))?;
+        Ok(true)
+    }
+}
+
+/// 単発ビルドの結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BuildOutcome {
+    Succeeded,
+    Failed(Option<i32>),
+    Cancelled,
 }
 
+impl BuildOutcome {
+    /// キャンセル後でも成功で終わっていれば、キャンセルが間に合わなかったものとして完了扱い
+    fn from_exit(cancelled: bool, status: std::process::ExitStatus) -> Self {
+        if status.success() {
+            BuildOutcome::Succeeded
+        } else if cancelled {
+            BuildOutcome::Cancelled
+        } else {
+            BuildOutcome::Failed(status.code())
+        }
+    }
+}
+
+/// ログの出力元
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// `sphinx_log` イベントのペイロード
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct SphinxLogLine {
+    session_id: String,
+    /// stdout/stderrをまたいで単調増加する通し番号
+    seq: u64,
+    stream: LogStream,
+    /// 表示用の行（strip_ansi有効時はエスケープシーケンスを除去済み）
+    line: String,
+    /// 除去前の行（除去して内容が変わった場合のみ）
+    raw: Option<String>,
+}
+
+/// `sphinx_log` の1行の上限の既定値（バイト）
+pub const DEFAULT_MAX_LOG_LINE_BYTES: usize = 8 * 1024;
+
+/// 切り詰めた行の末尾に付ける印
+const LOG_TRUNCATION_MARKER: &str = " …[truncated]";
+
+/// max_bytesを超えた行を文字の境界で切り詰めて印を付ける（切り詰めたらtrue）
+fn truncate_log_line(line: &mut String, max_bytes: usize) -> bool {
+    if line.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    line.truncate(end);
+    line.push_str(LOG_TRUNCATION_MARKER);
+    true
+}
+
+impl SphinxLogLine {
+    /// 上限を超えた行（rawも含む）を切り詰め、切り詰めた場合は元の長さ（バイト）を返す
+    fn truncate(&mut self, max_bytes: usize) -> Option<usize> {
+        let original = self
+            .raw
+            .as_ref()
+            .map_or(self.line.len(), |raw| raw.len().max(self.line.len()));
+        let line_truncated = truncate_log_line(&mut self.line, max_bytes);
+        let raw_truncated = self
+            .raw
+            .as_mut()
+            .is_some_and(|raw| truncate_log_line(raw, max_bytes));
+        (line_truncated || raw_truncated).then_some(original)
+    }
+
+    /// `sphinx_log` を通知する（上限を超えた行は切り詰め、`sphinx_log_truncated` も通知）
+    /// 切り詰めた行は `sphinx_error` などの後続の通知にもそのまま使う
+    fn emit(&mut self, handle: &AppHandle, max_bytes: usize) {
+        let truncated = self.truncate(max_bytes);
+        let _ = handle.emit(events::SPHINX_LOG, &*self);
+        if let Some(original) = truncated {
+            let _ = handle.emit(
+                events::SPHINX_LOG_TRUNCATED,
+                events::SphinxLogTruncated(&self.session_id, self.seq, original),
+            );
+        }
+    }
+
+    /// 共有カウンタから通し番号を採番してログ行を作る
+    fn next(seq: &AtomicU64, session_id: &str, stream: LogStream, line: &str, strip: bool) -> Self {
+        let stripped = if strip {
+            ansi::strip_ansi(line)
+        } else {
+            line.to_string()
+        };
+        let raw = (stripped != line).then(|| line.to_string());
+        Self {
+            session_id: session_id.to_string(),
+            seq: seq.fetch_add(1, Ordering::Relaxed),
+            stream,
+            line: stripped,
+            raw,
+        }
+    }
+}
+
+/// `reconfigure` の判定結果
+#[derive(Debug)]
+enum ReconfigurePlan {
+    /// 引数に変更なし（現在のポート）
+    Unchanged(u16),
+    /// 変更あり（起動し直すためのパラメータ。optionsとargsは変更後のもの）
+    Restart(Box<LaunchSpec>),
+}
+
+/// 起動待ちのポーリングの間隔
+const READINESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// キャンセル・再設定時にSIGTERM後、強制終了するまでの猶予
+const GRACEFUL_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
 /// Sphinxプロセスマネージャ
 pub struct SphinxManager {
     processes: HashMap<String, SphinxProcess>,
+    /// 単発ビルド（autobuildとは別管理）
+    builds: HashMap<String, OneShotBuild>,
 }
 
 impl SphinxManager {
     pub fn new() -> Self {
         Self {
             processes: HashMap::new(),
+            builds: HashMap::new(),
         }
     }
 
@@ -36,68 +395,143 @@ impl SphinxManager {
             .map(|addr| addr.port())
     }
 
-    /// sphinx-autobuildを起動
-    #[allow(clippy::too_many_arguments)]
+    /// ポートが空いているか（bindできるか）
+    fn is_port_free(port: u16) -> bool {
+        TcpListener::bind((HOST, port)).is_ok()
+    }
+
+    /// ポートが空いているかを確認（設定画面での検証用）
+    /// 使用中ならfalse、権限不足（特権ポートなど）はエラーとして区別する
+    pub fn check_port(port: u16) -> Result<bool, AppError> {
+        match TcpListener::bind((HOST, port)) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => Ok(false),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                Err(AppError::PortPermissionDenied { port })
+            }
+            Err(e) => Err(format!("ポート {} の確認に失敗: {}", port, e).into()),
+        }
+    }
+
+    /// 使用するポートを決定
+    /// - 0: 空きポートを自動割り当て
+    /// - 指定ポートが空いている: そのまま使用
+    /// - 指定ポートが使用中: fallback_to_randomなら空きポート、そうでなければPortInUse
+    fn resolve_port(requested_port: u16, fallback_to_random: bool) -> Result<u16, AppError> {
+        if requested_port == 0 {
+            return Ok(Self::find_available_port()?);
+        }
+        if Self::is_port_free(requested_port) {
+            return Ok(requested_port);
+        }
+        if fallback_to_random {
+            Ok(Self::find_available_port()?)
+        } else {
+            Err(AppError::PortInUse {
+                port: requested_port,
+                session_id: None,
+            })
+        }
+    }
+
+    /// 明示したポートを管理中の別セッションが使っていればPortInUse
+    /// 起動直後でまだbindしていないセッションとの衝突も検出できるよう、ポートの空きではなく登録で判定する
+    fn check_port_conflict(
+        &self,
+        session_id: &str,
+        requested_port: u16,
+        fallback_to_random: bool,
+    ) -> Result<(), AppError> {
+        if requested_port == 0 || fallback_to_random {
+            return Ok(());
+        }
+        let mut conflicting: Vec<&String> = self
+            .processes
+            .iter()
+            .filter(|(id, process)| id.as_str() != session_id && process.port == requested_port)
+            .map(|(id, _)| id)
+            .collect();
+        conflicting.sort();
+        match conflicting.first() {
+            Some(id) => Err(AppError::PortInUse {
+                port: requested_port,
+                session_id: Some(id.to_string()),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// 自動割り当て（0）の場合は前回のポートが空いていれば優先して使う
+    fn resolve_port_with_cache(
+        requested_port: u16,
+        fallback_to_random: bool,
+        cached_port: Option<u16>,
+    ) -> Result<u16, AppError> {
+        if requested_port == 0 {
+            if let Some(port) = cached_port.filter(|&port| Self::is_port_free(port)) {
+                return Ok(port);
+            }
+        }
+        Self::resolve_port(requested_port, fallback_to_random)
+    }
+
+    /// sphinx-autobuildを起動し、実際に使用するポートを返す
     pub fn start(
         &mut self,
         session_id: String,
         project_path: String,
-        source_dir: String,
-        build_dir: String,
         python_path: String,
-        requested_port: u16,
+        options: &SphinxConfig,
         extra_args: Vec<String>,
         app_handle: AppHandle,
-    ) -> Result<u16, String> {
+    ) -> Result<u16, AppError> {
+        // 不正な設定で既存セッションを止めてしまわないよう、最初に検証する
+        validate_re_ignore(&options.re_ignore)?;
+        let project_path = validate_project_path(Path::new(&project_path), options)?
+            .to_string_lossy()
+            .to_string();
+
+        self.check_port_conflict(
+            &session_id,
+            options.server.port,
+            options.server.fallback_to_random,
+        )?;
+
         // 既存セッションがあれば停止
         if self.processes.contains_key(&session_id) {
             self.stop(&session_id)?;
         }
 
-        let port = if requested_port == 0 {
-            Self::find_available_port()?
-        } else {
-            requested_port
-        };
+        let mut port_cache = PortCache::open_default();
+        let cached_port = port_cache
+            .as_ref()
+            .and_then(|cache| cache.get(Path::new(&project_path)));
+        let port = Self::resolve_port_with_cache(
+            options.server.port,
+            options.server.fallback_to_random,
+            cached_port,
+        )?;
 
-        // python_pathが相対パスの場合、project_pathを基準に解決
-        let resolved_python_path = if std::path::Path::new(&python_path).is_relative() {
-            let full_path = std::path::Path::new(&project_path).join(&python_path);
-            if !full_path.exists() {
-                return Err(format!(
-                    "Pythonインタプリタが見つかりません: {} (プロジェクト: {})",
-                    full_path.display(),
-                    project_path
-                ));
-            }
-            full_path.to_string_lossy().to_string()
-        } else {
-            python_path.clone()
-        };
+        let resolved_python_path = resolve_python_path(&project_path, &python_path)?;
 
-        let source_path = std::path::Path::new(&project_path).join(&source_dir);
-        let build_path = std::path::Path::new(&project_path).join(&build_dir);
+        check_conf_py(Path::new(&project_path), options)?;
+        if options.check_build_dir {
+            let status = build_dir_status(Path::new(&project_path), &options.build_dir);
+            if !status.writable {
+                return Err(format!("ビルドディレクトリに書き込めません: {}", status.path).into());
+            }
+        }
 
-        // 基本引数を構築
-        let mut args = vec![
-            "-m".to_string(),
-            "sphinx_autobuild".to_string(),
-            source_path.to_str().unwrap().to_string(),
-            build_path.to_str().unwrap().to_string(),
-            "--port".to_string(),
-            port.to_string(),
-            "--host".to_string(),
-            "127.0.0.1".to_string(),
-        ];
-        // 追加引数をマージ
-        args.extend(extra_args);
+        let args = build_autobuild_args(Path::new(&project_path), port, options, &extra_args);
+        if logging::enabled(LogLevel::Debug) {
+            eprintln!(
+                "sphinx-autobuildの引数: {} {:?} (作業ディレクトリ: {})",
+                resolved_python_path, args, project_path
+            );
+        }
 
         // sphinx-autobuildを起動
-        let mut child = Command::new(&resolved_python_path)
-            .args(&args)
-            .current_dir(&project_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+        let mut child = build_sphinx_command(&resolved_python_path, &args, &project_path, options)
             .spawn()
             .map_err(|e| {
                 format!(
@@ -106,87 +540,554 @@ impl SphinxManager {
                 )
             })?;
 
+        // ヘルスチェックの状態（ログ監視スレッドとプローブスレッドで共有）
+        let health = Arc::new(Mutex::new(HealthMonitor::default()));
+        let diagnostics = Arc::new(Mutex::new(DiagnosticsBuffer::default()));
+        let build_waiters = BuildWaiters::default();
+
+        // stdout/stderrの各行を通し番号付きの `sphinx_log` として通知
+        let log_seq = Arc::new(AtomicU64::new(0));
+        let strip = options.strip_ansi;
+        let max_line_bytes = options
+            .max_log_line_bytes
+            .filter(|bytes| *bytes > 0)
+            .unwrap_or(DEFAULT_MAX_LOG_LINE_BYTES);
+
+        // 停止フラグ（停止・detach後は出力を通知せず、プロセスが書き込みで詰まらないよう読み捨てる）
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        if let Some(stdout) = child.stdout.take() {
+            let sid = session_id.clone();
+            let handle = app_handle.clone();
+            let log_seq = Arc::clone(&log_seq);
+            let diagnostics = Arc::clone(&diagnostics);
+            let stopped_log = Arc::clone(&stopped);
+            thread::spawn(move || {
+                let reader = BufReader::new(stdout);
+                for line in reader.lines().map_while(Result::ok) {
+                    if stopped_log.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let mut log =
+                        SphinxLogLine::next(&log_seq, &sid, LogStream::Stdout, &line, strip);
+                    if let Ok(mut diagnostics) = diagnostics.lock() {
+                        diagnostics.record(&ansi::strip_ansi(&line));
+                    }
+                    log.emit(&handle, max_line_bytes);
+                }
+            });
+        }
+
         // stderrを監視してビルドイベントを通知
         let stderr = child.stderr.take();
         let sid = session_id.clone();
         let handle = app_handle.clone();
+        let health_log = Arc::clone(&health);
+        let diagnostics_log = Arc::clone(&diagnostics);
+        let build_waiters_log = build_waiters.clone();
+        let stopped_log = Arc::clone(&stopped);
+        // 起動直後の初回ビルドは開始行を待たずに計測を始める
+        let mut build_cycle = BuildCycleTracker::new(session_id.clone(), now_millis());
+        let mut output_changes = options.notify_output_changes.then(|| {
+            OutputChangeTracker::new(
+                resolve_project_dir(Path::new(&project_path), &options.build_dir),
+                SystemTime::now(),
+            )
+        });
 
         if let Some(stderr) = stderr {
             thread::spawn(move || {
                 let reader = BufReader::new(stderr);
-                for line in reader.lines().map_while(Result::ok) {
+                for raw_line in reader.lines().map_while(Result::ok) {
+                    if stopped_log.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let mut log =
+                        SphinxLogLine::next(&log_seq, &sid, LogStream::Stderr, &raw_line, strip);
+                    // 色付きの出力でも判定できるよう、分類は常に除去後の行で行う
+                    let line = ansi::strip_ansi(&raw_line);
+                    log.emit(&handle, max_line_bytes);
+                    // ビルド開始を検出（ビルド中はヘルスチェックを一時停止）
+                    if is_build_started(&line) {
+                        if let Ok(mut health) = health_log.lock() {
+                            health.on_build_started();
+                        }
+                        if let Ok(mut diagnostics) = diagnostics_log.lock() {
+                            diagnostics.on_build_started();
+                        }
+                    }
+                    if let Ok(mut diagnostics) = diagnostics_log.lock() {
+                        diagnostics.record(&line);
+                    }
+                    if let Some(cycle) = build_cycle.on_line(&line, now_millis()) {
+                        let _ = handle.emit(events::SPHINX_BUILD_CYCLE, cycle);
+                    }
+                    build_waiters_log.on_line(&line);
+                    if let Some(ref mut tracker) = output_changes {
+                        if let Some(since) = tracker.on_line(&line, SystemTime::now()) {
+                            spawn_output_scan(
+                                sid.clone(),
+                                tracker.build_dir.clone(),
+                                since,
+                                handle.clone(),
+                            );
+                        }
+                    }
                     // ビルド完了を検出
-                    if line.contains("build succeeded") || line.contains("waiting for changes") {
-                        let _ = handle.emit("sphinx_built", &sid);
+                    if is_build_finished(&line) {
+                        if let Ok(mut health) = health_log.lock() {
+                            health.on_build_finished();
+                        }
+                        let _ = handle.emit(events::SPHINX_BUILT, events::SessionId(&sid));
                     }
                     // エラーを検出
                     if line.contains("ERROR") || line.contains("error:") {
-                        let _ = handle.emit("sphinx_error", (&sid, &line));
+                        let _ =
+                            handle.emit(events::SPHINX_ERROR, events::SphinxError(&sid, &log.line));
                     }
                 }
+                // 出力が閉じた = プロセス終了
+                if let Ok(mut health) = health_log.lock() {
+                    health.on_exited();
+                }
+                build_waiters_log.on_exited();
             });
         }
 
-        // 停止フラグを作成
-        let stopped = Arc::new(AtomicBool::new(false));
-        let stopped_poll = Arc::clone(&stopped);
+        let readiness = ProbeCancel::default();
+        let readiness_poll = readiness.clone();
 
         // サーバー起動をポーリングで検出（ポートへの接続を試みる）
         let sid_poll = session_id.clone();
         let handle_poll = app_handle.clone();
         let poll_port = port;
+        let serving_url =
+            preview::preview_url(HOST, port, options.server.base_url_path.as_deref(), "");
         thread::spawn(move || {
             use std::net::TcpStream;
-            use std::time::Duration;
 
             let addr = format!("127.0.0.1:{}", poll_port);
             // 停止されるまで1秒ごとにポーリング
-            loop {
-                // 停止フラグをチェック
-                if stopped_poll.load(Ordering::Relaxed) {
-                    return;
-                }
-                thread::sleep(Duration::from_secs(1));
-                if TcpStream::connect(&addr).is_ok() {
-                    let _ = handle_poll.emit("sphinx_started", (&sid_poll, poll_port));
-                    return;
-                }
-            }
+            poll_until_ready(
+                &readiness_poll,
+                READINESS_POLL_INTERVAL,
+                || TcpStream::connect(&addr).is_ok(),
+                || {
+                    let _ = handle_poll.emit(
+                        events::SPHINX_STARTED,
+                        events::SphinxStarted(&sid_poll, poll_port),
+                    );
+                    let _ = handle_poll.emit(
+                        events::SPHINX_SERVING,
+                        events::SphinxServing(&sid_poll, &serving_url),
+                    );
+                },
+            );
         });
 
+        // 定期ヘルスチェック（プロセスは生きているがHTTPが応答しない状態を検出）
+        if let Some(interval) = options.server.health_check_interval_secs.filter(|&i| i > 0) {
+            let stopped_health = Arc::clone(&stopped);
+            let sid_health = session_id.clone();
+            let handle_health = app_handle.clone();
+            thread::spawn(move || {
+                use std::net::{SocketAddr, TcpStream};
+                use std::time::Duration;
+
+                let addr = SocketAddr::from(([127, 0, 0, 1], port));
+                loop {
+                    thread::sleep(Duration::from_secs(interval));
+                    if stopped_health.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let healthy = TcpStream::connect_timeout(&addr, HEALTH_PROBE_TIMEOUT).is_ok();
+                    let Ok(mut monitor) = health.lock() else {
+                        return;
+                    };
+                    if monitor.exited {
+                        return;
+                    }
+                    if monitor.record_probe(healthy) {
+                        let _ = handle_health
+                            .emit(events::SPHINX_UNHEALTHY, events::SessionId(&sid_health));
+                    }
+                }
+            });
+        }
+
         let process = SphinxProcess {
-            child,
+            child: Some(child),
+            host: HOST.to_string(),
             port,
+            builder: Builder::from_args(&args),
+            base_url_path: options.server.base_url_path.clone(),
             stopped,
+            readiness,
+            launch: Some(LaunchSpec {
+                project_path: project_path.clone(),
+                python_path,
+                options: options.clone(),
+                args,
+            }),
+            diagnostics,
+            build_waiters,
         };
+        if logging::enabled(LogLevel::Info) {
+            eprintln!(
+                "sphinx-autobuildを起動: {} (ポート: {}, PID: {:?})",
+                session_id,
+                port,
+                process.child.as_ref().map(Child::id)
+            );
+        }
         self.processes.insert(session_id.clone(), process);
 
+        // 自動割り当てしたポートを次回のために記録（失敗しても起動は継続）
+        if options.server.port == 0 {
+            if let Some(ref mut cache) = port_cache {
+                match cache.set(Path::new(&project_path), port) {
+                    Err(e) if logging::enabled(LogLevel::Warn) => eprintln!("{}", e),
+                    _ => {}
+                }
+            }
+        }
+
         Ok(port)
     }
 
+    /// 外部で起動済みのsphinx-autobuildをセッションとして登録する
+    /// プレビューURLの計算などには使えるが、プロセスのライフサイクルは管理しない
+    pub fn attach(&mut self, session_id: String, host: String, port: u16) -> Result<(), String> {
+        if port == 0 {
+            return Err("ポート番号が不正です: 0".to_string());
+        }
+        if host.trim().is_empty() {
+            return Err("ホストが指定されていません".to_string());
+        }
+        // 既存セッションがあれば停止（起動したものならkill、attachしたものなら登録解除）
+        if self.processes.contains_key(&session_id) {
+            self.stop(&session_id)?;
+        }
+
+        let process = SphinxProcess {
+            child: None,
+            host,
+            port,
+            builder: Builder::default(),
+            base_url_path: None,
+            stopped: Arc::new(AtomicBool::new(false)),
+            readiness: ProbeCancel::default(),
+            launch: None,
+            diagnostics: Arc::new(Mutex::new(DiagnosticsBuffer::default())),
+            build_waiters: BuildWaiters::default(),
+        };
+        self.processes.insert(session_id, process);
+        Ok(())
+    }
+
+    /// 再起動が必要かを新旧の引数の比較で判定する
+    /// 自動割り当て（port = 0）のままなら現在のポートを引き継ぐ。
+    /// 再起動する場合は、現在のプロセスを止める前に新しい設定で起動できるかを検証する
+    fn plan_reconfigure(
+        &self,
+        session_id: &str,
+        options: Option<SphinxConfig>,
+        extra_args: &[String],
+    ) -> Result<ReconfigurePlan, AppError> {
+        let process = self
+            .processes
+            .get(session_id)
+            .ok_or_else(|| format!("セッションが起動していません: {}", session_id))?;
+        let launch = process
+            .launch
+            .as_ref()
+            .ok_or_else(|| format!("attachしたセッションは再設定できません: {}", session_id))?;
+
+        let mut options = options.unwrap_or_else(|| launch.options.clone());
+        if options.server.port == 0 {
+            options.server.port = process.port;
+        }
+        let args = build_autobuild_args(
+            Path::new(&launch.project_path),
+            options.server.port,
+            &options,
+            extra_args,
+        );
+        if args == launch.args {
+            return Ok(ReconfigurePlan::Unchanged(process.port));
+        }
+
+        validate_re_ignore(&options.re_ignore)?;
+        check_conf_py(Path::new(&launch.project_path), &options)?;
+        // 同じポートなら現在のプロセスの停止で空く
+        let port = options.server.port;
+        if port != process.port {
+            let fallback = options.server.fallback_to_random;
+            self.check_port_conflict(session_id, port, fallback)?;
+            if !fallback && !Self::is_port_free(port) {
+                return Err(AppError::PortInUse {
+                    port,
+                    session_id: None,
+                });
+            }
+        }
+        Ok(ReconfigurePlan::Restart(Box::new(LaunchSpec {
+            project_path: launch.project_path.clone(),
+            python_path: launch.python_path.clone(),
+            options,
+            args,
+        })))
+    }
+
     /// sphinx-autobuildを停止
+    /// attachしたセッションは登録を解除するだけで、外部のプロセスには触れない
     pub fn stop(&mut self, session_id: &str) -> Result<(), String> {
         if let Some(mut process) = self.processes.remove(session_id) {
+            if logging::enabled(LogLevel::Info) {
+                eprintln!("sphinx-autobuildを停止: {}", session_id);
+            }
             // ポーリングスレッドに停止を通知
-            process.stopped.store(true, Ordering::Relaxed);
+            process.mark_stopped();
+            let Some(mut child) = process.child.take() else {
+                return Ok(());
+            };
             // プロセスをkill
-            if let Err(e) = process.child.kill() {
+            if let Err(e) = child.kill() {
                 // 既に終了している場合はエラーを無視
                 if e.kind() != std::io::ErrorKind::InvalidInput {
                     return Err(format!("プロセスの停止に失敗: {}", e));
                 }
             }
             // 確実に終了を待機（ゾンビプロセス防止）
-            let _ = process.child.wait();
+            let _ = child.wait();
+        }
+        Ok(())
+    }
+
+    /// セッションを管理から外す（プロセスはkillしない）
+    /// 監視スレッドは止め、以降の出力はイベントを通知せずに読み捨てる
+    /// （プロセスが書き込みで詰まらないよう、パイプは終了まで読み続ける）
+    pub fn detach(&mut self, session_id: &str) -> Result<DetachedSphinx, String> {
+        let mut process = self
+            .processes
+            .remove(session_id)
+            .ok_or_else(|| format!("セッションが起動していません: {}", session_id))?;
+        process.mark_stopped();
+        // Childをdropしてもプロセスは終了しない
+        let pid = process.child.take().map(|child| child.id());
+        Ok(DetachedSphinx {
+            pid,
+            host: process.host.clone(),
+            port: process.port,
+        })
+    }
+
+    /// セッションの一覧（セッションID順）
+    pub fn list_sessions(&self) -> Vec<SphinxSessionInfo> {
+        let mut sessions: Vec<SphinxSessionInfo> = self
+            .processes
+            .iter()
+            .map(|(session_id, process)| SphinxSessionInfo {
+                session_id: session_id.clone(),
+                host: process.host.clone(),
+                port: process.port,
+                owned: process.child.is_some(),
+            })
+            .collect();
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        sessions
+    }
+
+    /// sphinx-autobuildに渡した引数（attachしたセッションや未起動ならNone）
+    pub fn launch_args(&self, session_id: &str) -> Option<Vec<String>> {
+        let launch = self.processes.get(session_id)?.launch.as_ref()?;
+        Some(launch.args.clone())
+    }
+
+    /// sphinx-buildで1回だけビルドする（PDFなど時間のかかるビルダー向け）
+    /// 完了時に `sphinx_build_finished`、キャンセル時に `sphinx_build_cancelled` を通知
+    pub fn build_once(
+        &mut self,
+        session_id: String,
+        project_path: String,
+        python_path: String,
+        options: &SphinxConfig,
+        builder: &str,
+        app_handle: AppHandle,
+    ) -> Result<(), String> {
+        self.prune_finished_builds();
+        if self.builds.contains_key(&session_id) {
+            return Err(format!("ビルドは既に実行中です: {}", session_id));
+        }
+
+        let resolved_python_path = resolve_python_path(&project_path, &python_path)?;
+        let project = Path::new(&project_path);
+        let mut args = vec![
+            "-m".to_string(),
+            "sphinx".to_string(),
+            "-b".to_string(),
+            builder.to_string(),
+            resolve_project_dir(project, &options.source_dir)
+                .to_string_lossy()
+                .to_string(),
+            resolve_project_dir(project, &options.build_dir)
+                .to_string_lossy()
+                .to_string(),
+        ];
+        if options.config_dir.is_some() {
+            args.push("-c".to_string());
+            args.push(conf_dir(project, options).to_string_lossy().to_string());
         }
+
+        let child = Command::new(&resolved_python_path)
+            .args(&args)
+            .current_dir(&project_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                format!(
+                    "sphinx-buildの起動に失敗: {} (Python: {}, 作業ディレクトリ: {})",
+                    e, resolved_python_path, project_path
+                )
+            })?;
+
+        let build = OneShotBuild {
+            child: Arc::new(Mutex::new(child)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+
+        // 終了を待って結果を通知
+        let watched = build.clone();
+        let sid = session_id.clone();
+        thread::spawn(move || {
+            watch_build(&watched, |outcome| match outcome {
+                BuildOutcome::Cancelled => {
+                    let _ =
+                        app_handle.emit(events::SPHINX_BUILD_CANCELLED, events::SessionId(&sid));
+                }
+                BuildOutcome::Succeeded => {
+                    let _ = app_handle.emit(
+                        events::SPHINX_BUILD_FINISHED,
+                        events::SphinxBuildFinished(&sid, Some(0)),
+                    );
+                }
+                BuildOutcome::Failed(code) => {
+                    let _ = app_handle.emit(
+                        events::SPHINX_BUILD_FINISHED,
+                        events::SphinxBuildFinished(&sid, code),
+                    );
+                }
+            })
+        });
+
+        self.builds.insert(session_id, build);
         Ok(())
     }
 
+    /// 単発ビルドが実行中か
+    #[allow(dead_code)]
+    pub fn is_building(&self, session_id: &str) -> bool {
+        self.builds
+            .get(session_id)
+            .is_some_and(OneShotBuild::is_running)
+    }
+
+    /// 終了した単発ビルドを管理から外す
+    fn prune_finished_builds(&mut self) {
+        self.builds.retain(|_, build| build.is_running());
+    }
+
+    /// 実行中の単発ビルド（キャンセル用。終了したビルドは管理から外す）
+    pub fn running_build(&mut self, session_id: &str) -> Option<OneShotBuild> {
+        self.prune_finished_builds();
+        self.builds.get(session_id).cloned()
+    }
+
+    /// セッションを管理から外し、監視スレッドを止める（セッションがなければNone）
+    /// プロセスの停止は戻り値の `terminate` で行う
+    pub fn take_for_stop(&mut self, session_id: &str) -> Option<StoppingSphinx> {
+        let mut process = self.processes.remove(session_id)?;
+        process.mark_stopped();
+        if logging::enabled(LogLevel::Info) {
+            eprintln!("sphinx-autobuildを停止: {}", session_id);
+        }
+        Some(StoppingSphinx {
+            port: process.port,
+            child: process.child.take(),
+        })
+    }
+
+    /// 直近のビルドの診断のうち、指定した重大度以上のもの
+    /// attachしたセッションはログを読めないため常に空
+    pub fn diagnostics(
+        &self,
+        session_id: &str,
+        min_severity: Severity,
+    ) -> Result<Vec<SphinxDiagnostic>, String> {
+        let process = self
+            .processes
+            .get(session_id)
+            .ok_or_else(|| format!("セッションが起動していません: {}", session_id))?;
+        let diagnostics = process.diagnostics.lock().map_err(|e| e.to_string())?;
+        Ok(diagnostics.at_least(min_severity))
+    }
+
     /// ポートを取得
     pub fn get_port(&self, session_id: &str) -> Option<u16> {
         self.processes.get(session_id).map(|p| p.port)
     }
 
+    /// 次のビルド完了を待つ受信側を登録する
+    /// attachしたセッションはログを読めないため完了が通知されない
+    pub fn wait_for_build(
+        &self,
+        session_id: &str,
+    ) -> Result<oneshot::Receiver<BuildResult>, String> {
+        let process = self
+            .processes
+            .get(session_id)
+            .ok_or_else(|| format!("セッションが起動していません: {}", session_id))?;
+        Ok(process.build_waiters.subscribe())
+    }
+
+    /// 起動済みセッションのホスト・ポート・プレビューURL
+    pub fn start_result(&self, session_id: &str) -> Result<SphinxStartResult, String> {
+        let process = self
+            .processes
+            .get(session_id)
+            .ok_or_else(|| format!("セッションが起動していません: {}", session_id))?;
+        Ok(SphinxStartResult {
+            host: process.host.clone(),
+            port: process.port,
+            url: preview::preview_url(
+                &process.host,
+                process.port,
+                process.base_url_path.as_deref(),
+                "",
+            ),
+        })
+    }
+
+    /// ソースファイルのプレビューURLを計算（ブラウザは開かない）
+    pub fn preview_url_for(
+        &self,
+        session_id: &str,
+        source_rel_path: &str,
+    ) -> Result<String, String> {
+        let process = self
+            .processes
+            .get(session_id)
+            .ok_or_else(|| format!("セッションが起動していません: {}", session_id))?;
+        let page_path = preview::page_path_for_source(source_rel_path, process.builder);
+        Ok(preview::preview_url(
+            &process.host,
+            process.port,
+            process.base_url_path.as_deref(),
+            &page_path,
+        ))
+    }
+
     /// 実行中かどうか
     #[allow(dead_code)]
     pub fn is_running(&self, session_id: &str) -> bool {
@@ -198,15 +1099,736 @@ impl Drop for SphinxManager {
     fn drop(&mut self) {
         // 全プロセスを停止
         for (_, mut process) in self.processes.drain() {
-            process.stopped.store(true, Ordering::Relaxed);
-            let _ = process.child.kill();
-            let _ = process.child.wait();
+            process.mark_stopped();
+            // attachしたサーバーは外部の所有物なので残す
+            if let Some(mut child) = process.child.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+        for (_, build) in self.builds.drain() {
+            build.cancelled.store(true, Ordering::Relaxed);
+            if let Ok(mut child) = build.child.lock() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
         }
     }
 }
 
-pub type SharedSphinxManager = Arc<Mutex<SphinxManager>>;
-
+/// 設定・追加引数を変更して必要な場合だけ再起動する
+/// 引数が変わらなければ何もせず現在のポートを返す。
+/// 変わった場合は新しい設定を検証してから現在のプロセスを穏やかに停止し、同じポートで起動し直す。
+/// 停止の猶予の間はマネージャのロックを外す
+pub fn reconfigure(
+    manager: &SharedSphinxManager,
+    session_id: String,
+    options: Option<SphinxConfig>,
+    extra_args: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<u16, AppError> {
+    let (launch, stopping) = {
+        let mut inner = manager.lock().map_err(|e| e.to_string())?;
+        match inner.plan_reconfigure(&session_id, options, &extra_args)? {
+            ReconfigurePlan::Unchanged(port) => {
+                if logging::enabled(LogLevel::Debug) {
+                    eprintln!("再設定: 引数に変更がないため再起動しません: {}", session_id);
+                }
+                return Ok(port);
+            }
+            ReconfigurePlan::Restart(launch) => {
+                if logging::enabled(LogLevel::Info) {
+                    eprintln!("再設定: 引数が変わったため再起動します: {}", session_id);
+                }
+                (launch, inner.take_for_stop(&session_id))
+            }
+        }
+    };
+
+    if let Some(stopping) = stopping {
+        stopping.terminate()?;
+    }
+
+    let mut inner = manager.lock().map_err(|e| e.to_string())?;
+    inner.start(
+        session_id,
+        launch.project_path,
+        launch.python_path,
+        &launch.options,
+        extra_args,
+        app_handle,
+    )
+}
+
+/// 単発ビルドの終了を待って結果を渡す
+/// キャンセル時にロックを取れるよう、try_waitでポーリングする
+fn watch_build(build: &OneShotBuild, on_exit: impl FnOnce(BuildOutcome)) {
+    loop {
+        let status = match build.child.lock() {
+            Ok(mut child) => child.try_wait(),
+            Err(_) => return,
+        };
+        match status {
+            Ok(Some(status)) => {
+                on_exit(BuildOutcome::from_exit(
+                    build.cancelled.load(Ordering::Relaxed),
+                    status,
+                ));
+                return;
+            }
+            Ok(None) => thread::sleep(std::time::Duration::from_millis(100)),
+            Err(_) => return,
+        }
+    }
+}
+
+/// ポートの解放を確認する間隔
+const PORT_FREE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// ポートがbindできるようになるまで待つ（timeout内に空けばtrue）
+pub fn wait_for_port_free(port: u16, timeout: std::time::Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        if SphinxManager::is_port_free(port) {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(PORT_FREE_POLL_INTERVAL);
+    }
+}
+
+/// プロセスを穏やかに停止する
+/// Unixでは SIGTERM を送り、`timeout` 以内に終了しなければ SIGKILL
+fn terminate_gracefully(child: &mut Child, timeout: std::time::Duration) -> std::io::Result<()> {
+    if child.try_wait()?.is_some() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    {
+        // SAFETY: 自身が起動し、まだ回収していない子プロセスのPIDに対するシグナル送信
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+        }
+        let deadline = std::time::Instant::now() + timeout;
+        while std::time::Instant::now() < deadline {
+            if child.try_wait()?.is_some() {
+                return Ok(());
+            }
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = timeout;
+
+    match child.kill() {
+        Ok(()) => {}
+        // 既に終了している場合
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {}
+        Err(e) => return Err(e),
+    }
+    child.wait().map(|_| ())
+}
+
+/// python_pathが相対パスの場合、project_pathを基準に解決
+fn resolve_python_path(project_path: &str, python_path: &str) -> Result<String, String> {
+    if !Path::new(python_path).is_relative() {
+        return Ok(python_path.to_string());
+    }
+
+    let full_path = Path::new(project_path).join(python_path);
+    if !full_path.exists() {
+        return Err(format!(
+            "Pythonインタプリタが見つかりません: {} (プロジェクト: {})",
+            full_path.display(),
+            project_path
+        ));
+    }
+    Ok(full_path.to_string_lossy().to_string())
+}
+
+/// プロジェクト相対のディレクトリを解決
+/// 絶対パス（tmpfs上のビルドディレクトリなど）はそのまま使用する
+pub fn resolve_project_dir(project_path: &Path, dir: &str) -> PathBuf {
+    let dir = Path::new(dir);
+    if dir.is_absolute() {
+        dir.to_path_buf()
+    } else {
+        project_path.join(dir)
+    }
+}
+
+/// ビルドディレクトリの書き込み可否（起動前の確認用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildDirStatus {
+    /// プロジェクトからの相対指定を解決したパス
+    pub path: String,
+    pub writable: bool,
+}
+
+/// ビルドディレクトリに書き込めるか（なければ作成してから一時ファイルで確認する）
+pub fn build_dir_status(project_path: &Path, build_dir: &str) -> BuildDirStatus {
+    let dir = resolve_project_dir(project_path, build_dir);
+    BuildDirStatus {
+        writable: config::probe_dir_writable(&dir),
+        path: dir.to_string_lossy().to_string(),
+    }
+}
+
+/// `build_single_file` の結果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SingleFileBuild {
+    /// 対象ファイルの出力先
+    pub output_path: String,
+    /// sphinx-buildの終了コード（シグナルで終了した場合はNone）
+    pub exit_code: Option<i32>,
+    /// sphinx-buildの標準エラー出力（警告・エラーの確認用）
+    pub stderr: String,
+}
+
+/// 単一ファイルのビルドに対応するビルダー
+/// singlehtmlやlatexなどはファイルを指定しても全体を1つにまとめるため除く
+const SINGLE_FILE_BUILDERS: &[(&str, Builder)] =
+    &[("html", Builder::Html), ("dirhtml", Builder::DirHtml)];
+
+/// 単一ファイルをビルドするsphinx-buildの引数と出力先を組み立てる
+/// source_dir / build_dir / config_dir / extra_argsはsphinx-autobuildと同じものを使う
+pub fn single_file_build_args(
+    project_path: &Path,
+    options: &SphinxConfig,
+    builder: &str,
+    source_rel_path: &str,
+) -> Result<(Vec<String>, PathBuf), String> {
+    let Some(&(name, kind)) = SINGLE_FILE_BUILDERS
+        .iter()
+        .find(|(name, _)| *name == builder)
+    else {
+        let supported: Vec<&str> = SINGLE_FILE_BUILDERS.iter().map(|(name, _)| *name).collect();
+        return Err(format!(
+            "ビルダー {} は単一ファイルのビルドに対応していません（対応: {}）",
+            builder,
+            supported.join(", ")
+        ));
+    };
+    let relative = Path::new(source_rel_path);
+    if source_rel_path.is_empty()
+        || relative
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(format!(
+            "ソースディレクトリ外のパスです: {}",
+            source_rel_path
+        ));
+    }
+
+    let source = resolve_project_dir(project_path, &options.source_dir);
+    let build = resolve_project_dir(project_path, &options.build_dir);
+    let mut page = preview::page_path_for_source(source_rel_path, kind);
+    if page.is_empty() || page.ends_with('/') {
+        page.push_str("index.html");
+    }
+    let mut args = vec![
+        "-m".to_string(),
+        "sphinx".to_string(),
+        "-b".to_string(),
+        name.to_string(),
+    ];
+    args.extend(config_dir_args(project_path, options));
+    args.extend(options.extra_args.iter().cloned());
+    args.extend([
+        source.to_string_lossy().to_string(),
+        build.to_string_lossy().to_string(),
+        source.join(relative).to_string_lossy().to_string(),
+    ]);
+    Ok((args, build.join(page)))
+}
+
+/// sphinx-buildで1ファイルだけビルドし、終了まで待つ（大規模なプロジェクトでの素早い確認用）
+/// localeはsphinx-autobuildと同じく環境変数で渡す
+pub fn build_single_file(
+    project_path: &str,
+    python_path: &str,
+    options: &SphinxConfig,
+    builder: &str,
+    source_rel_path: &str,
+) -> Result<SingleFileBuild, String> {
+    let (args, output_path) =
+        single_file_build_args(Path::new(project_path), options, builder, source_rel_path)?;
+    let resolved_python_path = resolve_python_path(project_path, python_path)?;
+    let output = build_sphinx_command(&resolved_python_path, &args, project_path, options)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            format!(
+                "sphinx-buildの起動に失敗: {} (Python: {}, 作業ディレクトリ: {})",
+                e, resolved_python_path, project_path
+            )
+        })?;
+    Ok(SingleFileBuild {
+        output_path: output_path.to_string_lossy().to_string(),
+        exit_code: output.status.code(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    })
+}
+
+/// 再起動待ちの既定値
+const DEFAULT_RESTART_BACKOFF_MS: u64 = 1000;
+const DEFAULT_RESTART_BACKOFF_MAX_MS: u64 = 30_000;
+const DEFAULT_RESTART_JITTER: f64 = 0.2;
+
+/// sphinx-autobuildを再起動するまでの待ち時間の決め方
+/// 試行ごとに倍にして上限で止め、±jitterの割合でばらつかせる
+/// （自動再起動はないため、再起動を繰り返す呼び出し側が待ち時間を決めるのに使う）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RestartBackoff {
+    pub base_ms: u64,
+    pub max_ms: u64,
+    pub jitter: f64,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_RESTART_BACKOFF_MS,
+            DEFAULT_RESTART_BACKOFF_MAX_MS,
+            DEFAULT_RESTART_JITTER,
+        )
+    }
+}
+
+impl RestartBackoff {
+    /// `[sphinx.server]` の設定から作る（未設定の項目は既定値）
+    pub fn from_config(server: &ServerConfig) -> Self {
+        Self::new(
+            server
+                .restart_backoff_ms
+                .unwrap_or(DEFAULT_RESTART_BACKOFF_MS),
+            server
+                .restart_backoff_max_ms
+                .unwrap_or(DEFAULT_RESTART_BACKOFF_MAX_MS),
+            server.restart_jitter.unwrap_or(DEFAULT_RESTART_JITTER),
+        )
+    }
+
+    /// 値を補正して作る（base_msは1以上、max_msはbase_ms以上、jitterは0.0〜1.0）
+    pub fn new(base_ms: u64, max_ms: u64, jitter: f64) -> Self {
+        let base_ms = base_ms.max(1);
+        Self {
+            base_ms,
+            max_ms: max_ms.max(base_ms),
+            jitter: if jitter.is_finite() {
+                jitter.clamp(0.0, 1.0)
+            } else {
+                DEFAULT_RESTART_JITTER
+            },
+        }
+    }
+
+    /// 揺らぎを加える前の待ち時間（attemptは0始まり）
+    pub fn nominal_ms(&self, attempt: u32) -> u64 {
+        self.base_ms
+            .checked_shl(attempt)
+            .filter(|&ms| ms >> attempt == self.base_ms)
+            .map_or(self.max_ms, |ms| ms.min(self.max_ms))
+    }
+
+    /// 待ち時間。`random` は [0, 1) の乱数
+    pub fn delay_ms(&self, attempt: u32, random: f64) -> u64 {
+        let nominal = self.nominal_ms(attempt) as f64;
+        let factor = 1.0 - self.jitter + 2.0 * self.jitter * random.clamp(0.0, 1.0);
+        (nominal * factor).round() as u64
+    }
+
+    pub fn delay(&self, attempt: u32) -> Duration {
+        Duration::from_millis(self.delay_ms(attempt, rand::random::<f64>()))
+    }
+}
+
+/// ヘルスチェック1回あたりの接続タイムアウト
+const HEALTH_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 何回連続で失敗したら応答なしとみなすか
+const HEALTH_FAILURE_THRESHOLD: u32 = 3;
+
+/// `wait_for_build` の受信側でビルド完了を待つ
+/// timeoutを過ぎるか、プロセスが終了・停止したらエラー
+pub async fn await_build(
+    session_id: &str,
+    rx: oneshot::Receiver<BuildResult>,
+    timeout: Duration,
+) -> Result<(), String> {
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(format!("セッションが停止されました: {}", session_id)),
+        Err(_) => Err(format!(
+            "ビルドの完了を待つ間にタイムアウトしました: {}",
+            session_id
+        )),
+    }
+}
+
+/// 次のビルドが完了したら `open` を1回だけ呼ぶ（失敗・タイムアウト時は呼ばない）
+pub async fn open_after_build<F>(
+    session_id: &str,
+    rx: oneshot::Receiver<BuildResult>,
+    timeout: Duration,
+    open: F,
+) -> Result<(), String>
+where
+    F: FnOnce() -> Result<(), String>,
+{
+    await_build(session_id, rx, timeout).await?;
+    open()
+}
+
+/// 出力ファイルの変更通知のためにビルドの開始時刻を追う
+/// 1回のビルドで完了行が複数出ても、走査はビルドごとに1回にまとめる
+struct OutputChangeTracker {
+    build_dir: PathBuf,
+    /// 実行中のビルドの開始時刻（完了を通知したらNone）
+    build_started: Option<SystemTime>,
+}
+
+impl OutputChangeTracker {
+    fn new(build_dir: PathBuf, started: SystemTime) -> Self {
+        Self {
+            build_dir,
+            build_started: Some(started),
+        }
+    }
+
+    /// ビルドが完了した行なら、更新を調べる基準の時刻（ビルド開始時刻）を返す
+    fn on_line(&mut self, line: &str, now: SystemTime) -> Option<SystemTime> {
+        if is_build_started(line) {
+            // 開始行が続いても最初の時刻を基準にする
+            self.build_started.get_or_insert(now);
+            return None;
+        }
+        if is_build_finished(line) {
+            return self.build_started.take();
+        }
+        None
+    }
+}
+
+/// ビルドディレクトリを走査し、`since` 以降に更新されたファイルを通知する
+/// 大きなビルドで走査に時間がかかってもログの読み取りを止めないよう別スレッドで行う
+fn spawn_output_scan(
+    session_id: String,
+    build_dir: PathBuf,
+    since: SystemTime,
+    app_handle: AppHandle,
+) {
+    thread::spawn(move || {
+        let Ok((paths, truncated)) =
+            build_output::changed_since(&build_dir, since, build_output::CHANGED_FILES_LIMIT)
+        else {
+            return;
+        };
+        if paths.is_empty() {
+            return;
+        }
+        let _ = app_handle.emit(
+            events::SPHINX_OUTPUT_CHANGED,
+            events::SphinxOutputChanged {
+                session_id,
+                paths,
+                truncated,
+            },
+        );
+    });
+}
+
+/// ビルド開始を示すログ行か
+fn is_build_started(line: &str) -> bool {
+    line.contains("Running Sphinx") || line.contains("Detected change")
+}
+
+/// ビルド完了を示すログ行か
+fn is_build_finished(line: &str) -> bool {
+    line.contains("build succeeded") || line.contains("waiting for changes")
+}
+
+/// ビルドの失敗を示すログ行か（sphinx-autobuildがsphinxの終了コードを報告する）
+fn is_build_failed(line: &str) -> bool {
+    line.contains("Sphinx exited with exit code")
+}
+
+/// 現在時刻（UNIXエポックからのミリ秒）
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// ビルドの開始から完了・失敗までを追跡し、`sphinx_build_cycle` を組み立てる
+#[derive(Debug)]
+struct BuildCycleTracker {
+    session_id: String,
+    /// 進行中のビルドの開始時刻（ビルド中でなければNone）
+    started_at: Option<u64>,
+    warnings: usize,
+    errors: usize,
+}
+
+impl BuildCycleTracker {
+    /// `started_at` に開始したビルドが進行中の状態で作る
+    fn new(session_id: String, started_at: u64) -> Self {
+        Self {
+            session_id,
+            started_at: Some(started_at),
+            warnings: 0,
+            errors: 0,
+        }
+    }
+
+    /// ログ行（エスケープシーケンス除去済み）を処理し、ビルドが終わったらまとめを返す
+    fn on_line(&mut self, line: &str, now: u64) -> Option<events::SphinxBuildCycle> {
+        // "Detected change" と "Running Sphinx" が続けて出るため、進行中なら開始し直さない
+        if is_build_started(line) && self.started_at.is_none() {
+            self.started_at = Some(now);
+            self.warnings = 0;
+            self.errors = 0;
+        }
+        let started_at = self.started_at?;
+
+        match diagnostics::parse_diagnostic(line).map(|d| d.severity) {
+            Some(Severity::Warning) => self.warnings += 1,
+            Some(Severity::Error) => self.errors += 1,
+            _ => {}
+        }
+
+        let succeeded = if is_build_failed(line) {
+            false
+        } else if is_build_finished(line) {
+            true
+        } else {
+            return None;
+        };
+        self.started_at = None;
+        Some(events::SphinxBuildCycle {
+            session_id: self.session_id.clone(),
+            started_at,
+            duration_ms: now.saturating_sub(started_at),
+            succeeded,
+            warnings: self.warnings,
+            errors: self.errors,
+        })
+    }
+}
+
+/// ヘルスチェックの状態機械
+/// - サーバーが一度応答するまでは失敗を数えない（初回ビルド中の誤検知防止）
+/// - ビルド中は再ビルドでHTTPが遅れるためプローブ結果を無視する
+/// - 連続失敗がしきい値に達したら1度だけ通知し、応答が戻ったらリセット
+#[derive(Debug, Default)]
+struct HealthMonitor {
+    seen_healthy: bool,
+    building: bool,
+    failures: u32,
+    reported: bool,
+    exited: bool,
+}
+
+impl HealthMonitor {
+    fn on_build_started(&mut self) {
+        self.building = true;
+    }
+
+    fn on_build_finished(&mut self) {
+        self.building = false;
+        self.failures = 0;
+    }
+
+    fn on_exited(&mut self) {
+        self.exited = true;
+    }
+
+    /// プローブ結果を記録し、`sphinx_unhealthy` を通知すべきならtrueを返す
+    fn record_probe(&mut self, healthy: bool) -> bool {
+        if healthy {
+            self.seen_healthy = true;
+            self.failures = 0;
+            self.reported = false;
+            return false;
+        }
+        if !self.seen_healthy || self.building || self.exited {
+            return false;
+        }
+
+        self.failures += 1;
+        if self.failures >= HEALTH_FAILURE_THRESHOLD && !self.reported {
+            self.reported = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// conf.pyを置くディレクトリ（config_dir未指定ならsource_dir）
+fn conf_dir(project_path: &Path, options: &SphinxConfig) -> PathBuf {
+    let dir = options.config_dir.as_deref().unwrap_or(&options.source_dir);
+    resolve_project_dir(project_path, dir)
+}
+
+/// sphinx-autobuild / sphinx-buildの起動コマンドを構築（stdout / stderrはパイプ）
+/// ログの判定を安定させるため、設定のロケールを `LC_ALL` / `LANG` に設定する
+fn build_sphinx_command(
+    python_path: &str,
+    args: &[String],
+    project_path: &str,
+    options: &SphinxConfig,
+) -> Command {
+    let mut command = Command::new(python_path);
+    command
+        .args(args)
+        .current_dir(project_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if !options.locale.is_empty() {
+        command
+            .env("LC_ALL", &options.locale)
+            .env("LANG", &options.locale);
+    }
+    command
+}
+
+/// 存在しないプロジェクトパスに対して提案する候補の上限
+const PROJECT_SUGGESTIONS_LIMIT: usize = 5;
+
+/// conf.pyがなければsphinx-autobuildは起動直後に失敗するため事前に検出する
+fn check_conf_py(project_path: &Path, options: &SphinxConfig) -> Result<(), AppError> {
+    let conf_py = conf_dir(project_path, options).join("conf.py");
+    if conf_py.is_file() {
+        Ok(())
+    } else {
+        Err(format!("conf.pyが見つかりません: {}", conf_py.display()).into())
+    }
+}
+
+/// プロジェクトのディレクトリを検証し、正規化した絶対パスを返す
+/// 存在しなければ、同じ親ディレクトリからconf.pyのあるディレクトリを候補として添える
+fn validate_project_path(project_path: &Path, options: &SphinxConfig) -> Result<PathBuf, AppError> {
+    project::canonicalize_project(project_path).map_err(|e| match e {
+        AppError::ProjectNotFound { path, .. } => AppError::ProjectNotFound {
+            path,
+            suggestions: suggest_projects(project_path, options),
+        },
+        e => e,
+    })
+}
+
+/// 親ディレクトリ直下のSphinxプロジェクトらしきディレクトリ（パス順）
+fn suggest_projects(project_path: &Path, options: &SphinxConfig) -> Vec<String> {
+    let Some(entries) = project_path
+        .parent()
+        .and_then(|parent| std::fs::read_dir(parent).ok())
+    else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter(|path| {
+            conf_dir(path, options).join("conf.py").is_file() || path.join("conf.py").is_file()
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    candidates.sort();
+    candidates.truncate(PROJECT_SUGGESTIONS_LIMIT);
+    candidates
+}
+
+/// `re_ignore` の正規表現に明らかな構文エラー（閉じていない括弧など）がないか検証
+/// sphinx-autobuildはPythonの `re` で解釈するため、先読み・後読みや後方参照のように
+/// Rustの `regex` が対応していないだけの構文は通す
+fn validate_re_ignore(patterns: &[String]) -> Result<(), String> {
+    for pattern in patterns {
+        match regex::Regex::new(pattern) {
+            Ok(_) => {}
+            Err(regex::Error::Syntax(message)) if message.contains("not supported") => {}
+            Err(e) => {
+                return Err(format!(
+                    "re_ignore の正規表現が不正です: {}: {}",
+                    pattern, e
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// sphinx-autobuildの引数を構築（`python` に渡す引数列）
+///
+/// 追加引数は設定の `extra_args` → 呼び出し側の `extra_args` の順に並べる。
+/// 後に指定した値が優先されるため、呼び出し側で設定の値を追加・上書きできる
+fn build_autobuild_args(
+    project_path: &Path,
+    port: u16,
+    options: &SphinxConfig,
+    extra_args: &[String],
+) -> Vec<String> {
+    let source_path = resolve_project_dir(project_path, &options.source_dir);
+    let build_path = resolve_project_dir(project_path, &options.build_dir);
+
+    // 基本引数を構築
+    let mut args = vec![
+        "-m".to_string(),
+        "sphinx_autobuild".to_string(),
+        source_path.to_string_lossy().to_string(),
+        build_path.to_string_lossy().to_string(),
+        "--port".to_string(),
+        port.to_string(),
+        "--host".to_string(),
+        HOST.to_string(),
+    ];
+    args.extend(config_dir_args(project_path, options));
+    // --open-browser は値を取らないフラグのため、無効時は何も渡さない
+    if options.server.open_browser {
+        args.push("--open-browser".to_string());
+    }
+    if options.no_initial {
+        args.push("--no-initial".to_string());
+    }
+    if let Some(delay) = options.delay_secs {
+        args.push("--delay".to_string());
+        args.push(delay.to_string());
+    }
+    for pattern in &options.re_ignore {
+        args.push("--re-ignore".to_string());
+        args.push(pattern.clone());
+    }
+    // ビルド前に実行するコマンド（指定順に実行される）
+    for command in &options.pre_build {
+        args.push("--pre-build".to_string());
+        args.push(command.clone());
+    }
+    // 追加引数をマージ（設定 → 呼び出し側）
+    args.extend(options.extra_args.iter().cloned());
+    args.extend(extra_args.iter().cloned());
+    args
+}
+
+/// conf.pyがソースと別ディレクトリの場合にsphinx-buildへ渡す -c
+fn config_dir_args(project_path: &Path, options: &SphinxConfig) -> Vec<String> {
+    match options.config_dir {
+        Some(ref config_dir) => vec![
+            "-c".to_string(),
+            resolve_project_dir(project_path, config_dir)
+                .to_string_lossy()
+                .to_string(),
+        ],
+        None => Vec::new(),
+    }
+}
+
+pub type SharedSphinxManager = Arc<Mutex<SphinxManager>>;
+
 pub fn create_sphinx_manager() -> SharedSphinxManager {
     Arc::new(Mutex::new(SphinxManager::new()))
 }
@@ -221,16 +1843,1361 @@ mod tests {
         assert!(!manager.is_running("test"));
     }
 
+    #[test]
+    fn test_attach_lists_non_owning_session() {
+        let mut manager = SphinxManager::new();
+        manager
+            .attach("ext".to_string(), "localhost".to_string(), 8123)
+            .unwrap();
+
+        assert!(manager.is_running("ext"));
+        assert_eq!(manager.get_port("ext"), Some(8123));
+        assert_eq!(
+            manager.list_sessions(),
+            vec![SphinxSessionInfo {
+                session_id: "ext".to_string(),
+                host: "localhost".to_string(),
+                port: 8123,
+                owned: false,
+            }]
+        );
+        assert_eq!(
+            manager.preview_url_for("ext", "guide/intro.rst").unwrap(),
+            "http://localhost:8123/guide/intro.html"
+        );
+
+        assert!(manager
+            .attach("bad".to_string(), "localhost".to_string(), 0)
+            .is_err());
+        assert!(manager
+            .attach("bad".to_string(), " ".to_string(), 8000)
+            .is_err());
+        assert!(!manager.is_running("bad"));
+    }
+
+    #[test]
+    fn test_stop_attached_session_only_unregisters() {
+        let mut manager = SphinxManager::new();
+        manager
+            .attach("ext".to_string(), "127.0.0.1".to_string(), 8123)
+            .unwrap();
+        // 同じIDで再attachすると置き換わる
+        manager
+            .attach("ext".to_string(), "127.0.0.1".to_string(), 8124)
+            .unwrap();
+        assert_eq!(manager.get_port("ext"), Some(8124));
+
+        manager.stop("ext").unwrap();
+        assert!(!manager.is_running("ext"));
+        assert!(manager.list_sessions().is_empty());
+
+        // Drop時にも外部のサーバーには触れない（killするプロセスがない）
+        manager
+            .attach("ext".to_string(), "127.0.0.1".to_string(), 8123)
+            .unwrap();
+        drop(manager);
+    }
+
+    #[test]
+    fn test_single_file_build_args_target_one_file() {
+        let options = SphinxConfig::default();
+        let (args, output) =
+            single_file_build_args(Path::new("/proj"), &options, "html", "guide/intro.rst")
+                .unwrap();
+        assert_eq!(
+            args,
+            [
+                "-m",
+                "sphinx",
+                "-b",
+                "html",
+                "/proj/docs",
+                "/proj/_build/html",
+                "/proj/docs/guide/intro.rst",
+            ]
+        );
+        assert_eq!(output, PathBuf::from("/proj/_build/html/guide/intro.html"));
+
+        let options = SphinxConfig {
+            build_dir: "_build".to_string(),
+            ..Default::default()
+        };
+        let (_, output) =
+            single_file_build_args(Path::new("/proj"), &options, "dirhtml", "guide/intro.md")
+                .unwrap();
+        assert_eq!(output, PathBuf::from("/proj/_build/guide/intro/index.html"));
+    }
+
+    #[test]
+    fn test_single_file_build_args_use_config_dir_and_extra_args() {
+        let options = SphinxConfig {
+            config_dir: Some("conf".to_string()),
+            extra_args: vec!["-W".to_string()],
+            ..Default::default()
+        };
+        let (args, _) =
+            single_file_build_args(Path::new("/proj"), &options, "html", "index.rst").unwrap();
+        assert_eq!(
+            args,
+            [
+                "-m",
+                "sphinx",
+                "-b",
+                "html",
+                "-c",
+                "/proj/conf",
+                "-W",
+                "/proj/docs",
+                "/proj/_build/html",
+                "/proj/docs/index.rst",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_build_single_file_captures_stderr_and_locale() {
+        use std::os::unix::fs::PermissionsExt;
+        // sphinx-buildの代わりに引数とLANGを標準エラーへ書くスクリプト
+        let dir = tempfile::tempdir().unwrap();
+        let python = dir.path().join("fake-python");
+        std::fs::write(&python, "#!/bin/sh\necho \"LANG=$LANG $*\" >&2\nexit 2\n").unwrap();
+        std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let options = SphinxConfig {
+            locale: "C.UTF-8".to_string(),
+            extra_args: vec!["-W".to_string()],
+            ..Default::default()
+        };
+
+        let project_path = dir.path().to_string_lossy().to_string();
+        let result = build_single_file(
+            &project_path,
+            &python.to_string_lossy(),
+            &options,
+            "html",
+            "index.rst",
+        )
+        .unwrap();
+        assert_eq!(result.exit_code, Some(2));
+        assert!(result
+            .stderr
+            .starts_with("LANG=C.UTF-8 -m sphinx -b html -W "));
+    }
+
+    #[test]
+    fn test_single_file_build_rejects_unsupported() {
+        let options = SphinxConfig::default();
+        let err =
+            single_file_build_args(Path::new("/proj"), &options, "latex", "a.rst").unwrap_err();
+        assert!(err.contains("latex"));
+        assert!(err.contains("html, dirhtml"));
+
+        assert!(single_file_build_args(Path::new("/proj"), &options, "html", "../a.rst").is_err());
+        assert!(single_file_build_args(Path::new("/proj"), &options, "html", "").is_err());
+    }
+
+    #[test]
+    fn test_detach_keeps_process_alive() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "docs", 8123, Builder::default());
+
+        let detached = manager.detach("docs").unwrap();
+        assert_eq!(detached.host, HOST);
+        assert_eq!(detached.port, 8123);
+        assert!(!manager.is_running("docs"));
+        assert!(manager.detach("docs").is_err());
+
+        // 管理を外した後にDropしてもkillされない
+        drop(manager);
+        let pid = detached.pid.unwrap().to_string();
+        let alive = || {
+            Command::new("kill")
+                .args(["-0", &pid])
+                .status()
+                .unwrap()
+                .success()
+        };
+        assert!(alive());
+
+        let _ = Command::new("kill").arg(&pid).status();
+    }
+
+    #[test]
+    fn test_detach_attached_session_has_no_pid() {
+        let mut manager = SphinxManager::new();
+        manager
+            .attach("ext".to_string(), "localhost".to_string(), 8123)
+            .unwrap();
+        assert_eq!(
+            manager.detach("ext").unwrap(),
+            DetachedSphinx {
+                pid: None,
+                host: "localhost".to_string(),
+                port: 8123,
+            }
+        );
+        assert!(manager.list_sessions().is_empty());
+    }
+
     #[test]
     fn test_find_available_port() {
         let port = SphinxManager::find_available_port().unwrap();
         assert!(port > 0);
     }
 
+    #[test]
+    fn test_check_port() {
+        let held = TcpListener::bind("127.0.0.1:0").unwrap();
+        let busy = held.local_addr().unwrap().port();
+        assert_eq!(SphinxManager::check_port(busy), Ok(false));
+
+        let free = SphinxManager::find_available_port().unwrap();
+        assert_eq!(SphinxManager::check_port(free), Ok(true));
+    }
+
+    #[test]
+    fn test_resolve_port_uses_free_requested_port() {
+        // 一度bindして解放したポートは空いている
+        let port = SphinxManager::find_available_port().unwrap();
+        assert_eq!(SphinxManager::resolve_port(port, false), Ok(port));
+    }
+
+    #[test]
+    fn test_resolve_port_busy_with_fallback() {
+        let held = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = held.local_addr().unwrap().port();
+
+        let resolved = SphinxManager::resolve_port(port, true).unwrap();
+        assert_ne!(resolved, port);
+        assert!(resolved > 0);
+    }
+
+    #[test]
+    fn test_resolve_port_busy_without_fallback() {
+        let held = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = held.local_addr().unwrap().port();
+
+        assert_eq!(
+            SphinxManager::resolve_port(port, false),
+            Err(AppError::PortInUse {
+                port,
+                session_id: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_same_explicit_port_conflicts_with_other_session() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "first", 8123, Builder::default());
+
+        assert_eq!(
+            manager.check_port_conflict("second", 8123, false),
+            Err(AppError::PortInUse {
+                port: 8123,
+                session_id: Some("first".to_string())
+            })
+        );
+        // 同じセッションの再起動・別のポート・自動割り当て・フォールバックは衝突しない
+        assert!(manager.check_port_conflict("first", 8123, false).is_ok());
+        assert!(manager.check_port_conflict("second", 8124, false).is_ok());
+        assert!(manager.check_port_conflict("second", 0, false).is_ok());
+        assert!(manager.check_port_conflict("second", 8123, true).is_ok());
+
+        manager.stop("first").unwrap();
+        assert!(manager.check_port_conflict("second", 8123, false).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_port_auto() {
+        let port = SphinxManager::resolve_port(0, false).unwrap();
+        assert!(port > 0);
+    }
+
+    #[test]
+    fn test_resolve_port_with_cache_hit_free() {
+        let cached = SphinxManager::find_available_port().unwrap();
+        assert_eq!(
+            SphinxManager::resolve_port_with_cache(0, false, Some(cached)),
+            Ok(cached)
+        );
+    }
+
+    #[test]
+    fn test_resolve_port_with_cache_hit_busy() {
+        let held = TcpListener::bind("127.0.0.1:0").unwrap();
+        let cached = held.local_addr().unwrap().port();
+
+        let resolved = SphinxManager::resolve_port_with_cache(0, false, Some(cached)).unwrap();
+        assert_ne!(resolved, cached);
+        assert!(resolved > 0);
+    }
+
+    #[test]
+    fn test_resolve_port_with_cache_miss() {
+        let port = SphinxManager::resolve_port_with_cache(0, false, None).unwrap();
+        assert!(port > 0);
+
+        // 明示的なポート指定はキャッシュより優先
+        let requested = SphinxManager::find_available_port().unwrap();
+        assert_eq!(
+            SphinxManager::resolve_port_with_cache(requested, false, Some(1)),
+            Ok(requested)
+        );
+    }
+
+    #[test]
+    fn test_build_autobuild_args_basic() {
+        let args = build_autobuild_args(
+            Path::new("/proj"),
+            8000,
+            &SphinxConfig::default(),
+            &["--watch".to_string(), "src".to_string()],
+        );
+        assert_eq!(
+            args,
+            vec![
+                "-m",
+                "sphinx_autobuild",
+                "/proj/docs",
+                "/proj/_build/html",
+                "--port",
+                "8000",
+                "--host",
+                "127.0.0.1",
+                "--watch",
+                "src",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_autobuild_args_merges_extra_args() {
+        let options = SphinxConfig {
+            extra_args: vec!["-j".to_string(), "auto".to_string()],
+            ..Default::default()
+        };
+        let args = build_autobuild_args(
+            Path::new("/proj"),
+            8000,
+            &options,
+            &["-j".to_string(), "4".to_string()],
+        );
+        // 設定の引数の後に呼び出し側の引数が続く
+        assert_eq!(&args[args.len() - 4..], ["-j", "auto", "-j", "4"]);
+    }
+
+    #[test]
+    fn test_log_line_strip_ansi() {
+        let seq = AtomicU64::new(0);
+        let colored = "\x1b[91mWARNING: undefined label\x1b[39;49;00m";
+
+        let log = SphinxLogLine::next(&seq, "test", LogStream::Stderr, colored, true);
+        assert_eq!(log.line, "WARNING: undefined label");
+        assert_eq!(log.raw.as_deref(), Some(colored));
+
+        // 無効時はそのまま
+        let log = SphinxLogLine::next(&seq, "test", LogStream::Stderr, colored, false);
+        assert_eq!(log.line, colored);
+        assert_eq!(log.raw, None);
+
+        // 除去しても変わらない行はrawを持たない
+        let log = SphinxLogLine::next(&seq, "test", LogStream::Stdout, "build succeeded.", true);
+        assert_eq!(log.raw, None);
+    }
+
+    #[test]
+    fn test_log_line_truncates_long_lines() {
+        let seq = AtomicU64::new(0);
+        let traceback = format!("Traceback: {}", "あ".repeat(5000));
+        let mut log = SphinxLogLine::next(&seq, "test", LogStream::Stderr, &traceback, true);
+        assert_eq!(
+            log.truncate(DEFAULT_MAX_LOG_LINE_BYTES),
+            Some(traceback.len())
+        );
+        assert!(log.line.ends_with(LOG_TRUNCATION_MARKER));
+        assert!(log.line.len() <= DEFAULT_MAX_LOG_LINE_BYTES + LOG_TRUNCATION_MARKER.len());
+        assert!(traceback.starts_with(log.line.trim_end_matches(LOG_TRUNCATION_MARKER)));
+
+        // 上限以内の行はそのまま
+        let mut log =
+            SphinxLogLine::next(&seq, "test", LogStream::Stdout, "build succeeded.", true);
+        assert_eq!(log.truncate(DEFAULT_MAX_LOG_LINE_BYTES), None);
+        assert_eq!(log.line, "build succeeded.");
+
+        // 除去前の行も切り詰める
+        let colored = format!("\x1b[91m{}\x1b[39;49;00m", "x".repeat(20));
+        let mut log = SphinxLogLine::next(&seq, "test", LogStream::Stderr, &colored, true);
+        assert_eq!(log.truncate(16), Some(colored.len()));
+        assert_eq!(
+            log.line,
+            format!("{}{}", "x".repeat(16), LOG_TRUNCATION_MARKER)
+        );
+        assert!(log.raw.unwrap().ends_with(LOG_TRUNCATION_MARKER));
+    }
+
+    #[test]
+    fn test_log_seq_unique_and_increasing_across_streams() {
+        let seq = Arc::new(AtomicU64::new(0));
+        let writers: Vec<_> = [LogStream::Stdout, LogStream::Stderr]
+            .into_iter()
+            .map(|stream| {
+                let seq = Arc::clone(&seq);
+                thread::spawn(move || {
+                    (0..500)
+                        .map(|i| SphinxLogLine::next(&seq, "test", stream, &i.to_string(), false))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let logs: Vec<Vec<SphinxLogLine>> =
+            writers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        // 各ストリーム内では増加し続ける
+        for stream_logs in &logs {
+            assert!(stream_logs.windows(2).all(|w| w[0].seq < w[1].seq));
+        }
+        // ストリームをまたいで重複しない
+        let mut all: Vec<u64> = logs.iter().flatten().map(|log| log.seq).collect();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_build_autobuild_args_pre_build() {
+        let options = SphinxConfig {
+            pre_build: vec![
+                "sphinx-apidoc -o docs/api src".to_string(),
+                "make gen".to_string(),
+            ],
+            ..Default::default()
+        };
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert_eq!(
+            &args[args.len() - 4..],
+            [
+                "--pre-build",
+                "sphinx-apidoc -o docs/api src",
+                "--pre-build",
+                "make gen"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_autobuild_args_delay_and_re_ignore() {
+        let options = SphinxConfig::default();
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert!(!args.contains(&"--delay".to_string()));
+        assert!(!args.contains(&"--re-ignore".to_string()));
+
+        let options = SphinxConfig {
+            delay_secs: Some(3),
+            re_ignore: vec![r".*\.swp$".to_string(), "_autosummary/".to_string()],
+            pre_build: vec!["make gen".to_string()],
+            ..Default::default()
+        };
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert_eq!(
+            &args[args.len() - 8..],
+            [
+                "--delay",
+                "3",
+                "--re-ignore",
+                r".*\.swp$",
+                "--re-ignore",
+                "_autosummary/",
+                "--pre-build",
+                "make gen"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_sphinx_command_locale() {
+        let env_of = |command: &Command, key: &str| {
+            command
+                .get_envs()
+                .find(|(k, _)| *k == key)
+                .and_then(|(_, v)| v)
+                .map(|v| v.to_string_lossy().to_string())
+        };
+
+        let options = SphinxConfig::default();
+        let command = build_sphinx_command("python", &[], "/proj", &options);
+        assert_eq!(env_of(&command, "LC_ALL"), Some("C".to_string()));
+        assert_eq!(env_of(&command, "LANG"), Some("C".to_string()));
+        assert_eq!(command.get_current_dir(), Some(Path::new("/proj")));
+
+        let options = SphinxConfig {
+            locale: "ja_JP.UTF-8".to_string(),
+            ..Default::default()
+        };
+        let command = build_sphinx_command("python", &[], "/proj", &options);
+        assert_eq!(env_of(&command, "LC_ALL"), Some("ja_JP.UTF-8".to_string()));
+
+        // 空なら環境のロケールを引き継ぐ
+        let options = SphinxConfig {
+            locale: String::new(),
+            ..Default::default()
+        };
+        let command = build_sphinx_command("python", &[], "/proj", &options);
+        assert_eq!(command.get_envs().count(), 0);
+    }
+
+    #[test]
+    fn test_validate_re_ignore() {
+        assert!(validate_re_ignore(&[]).is_ok());
+        assert!(validate_re_ignore(&[r".*\.swp$".to_string(), "^build/".to_string()]).is_ok());
+
+        let err = validate_re_ignore(&["ok".to_string(), "(unclosed".to_string()]).unwrap_err();
+        assert!(err.contains("(unclosed"), "{}", err);
+
+        // Pythonの `re` では有効な先読み・後読み・後方参照
+        for pattern in [r".*(?=\.tmp$)", r"(?<!_build)/index", r"(\w+)/\1\.rst"] {
+            assert!(
+                validate_re_ignore(&[pattern.to_string()]).is_ok(),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_autobuild_args_open_browser() {
+        let mut options = SphinxConfig::default();
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert!(!args.iter().any(|a| a.starts_with("--open-browser")));
+
+        options.server.open_browser = true;
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert!(args.contains(&"--open-browser".to_string()));
+    }
+
+    #[test]
+    fn test_build_autobuild_args_no_initial() {
+        let mut options = SphinxConfig::default();
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert!(!args.contains(&"--no-initial".to_string()));
+
+        options.no_initial = true;
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert!(args.contains(&"--no-initial".to_string()));
+    }
+
+    #[test]
+    fn test_build_autobuild_args_config_dir() {
+        let mut options = SphinxConfig::default();
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        assert!(!args.contains(&"-c".to_string()));
+
+        options.config_dir = Some("conf".to_string());
+        let args = build_autobuild_args(Path::new("/proj"), 8000, &options, &[]);
+        let pos = args.iter().position(|a| a == "-c").unwrap();
+        assert_eq!(args[pos + 1], "/proj/conf");
+        // ソースディレクトリは変わらない
+        assert!(args.contains(&"/proj/docs".to_string()));
+    }
+
+    #[test]
+    fn test_conf_dir() {
+        let mut options = SphinxConfig::default();
+        assert_eq!(
+            conf_dir(Path::new("/proj"), &options),
+            PathBuf::from("/proj/docs")
+        );
+
+        options.config_dir = Some("conf".to_string());
+        assert_eq!(
+            conf_dir(Path::new("/proj"), &options),
+            PathBuf::from("/proj/conf")
+        );
+    }
+
+    #[test]
+    fn test_stop_cancels_readiness_probe() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "docs", 8123, Builder::default());
+        let readiness = manager.processes["docs"].readiness.clone();
+
+        // 接続を試している最中に停止される
+        let (probing_tx, probing_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let started = Arc::new(AtomicBool::new(false));
+        let started_probe = Arc::clone(&started);
+        let probe = thread::spawn(move || {
+            poll_until_ready(
+                &readiness,
+                Duration::from_millis(10),
+                || {
+                    let _ = probing_tx.send(());
+                    let _ = release_rx.recv();
+                    true
+                },
+                || started_probe.store(true, Ordering::Relaxed),
+            )
+        });
+
+        probing_rx.recv().unwrap();
+        manager.stop("docs").unwrap();
+        release_tx.send(()).unwrap();
+        assert!(!probe.join().unwrap());
+        assert!(!started.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_readiness_probe_stops_polling_after_cancel() {
+        let cancel = ProbeCancel::default();
+        let attempts = Arc::new(AtomicU64::new(0));
+        let attempts_probe = Arc::clone(&attempts);
+        let probe_cancel = cancel.clone();
+        let probe = thread::spawn(move || {
+            poll_until_ready(
+                &probe_cancel,
+                Duration::from_secs(60),
+                || {
+                    attempts_probe.fetch_add(1, Ordering::Relaxed);
+                    true
+                },
+                || {},
+            )
+        });
+        // 待機中のキャンセルはすぐに反映され、接続を試さない
+        cancel.cancel();
+        assert!(!probe.join().unwrap());
+        assert_eq!(attempts.load(Ordering::Relaxed), 0);
+
+        let cancel = ProbeCancel::default();
+        let mut ready = false;
+        assert!(poll_until_ready(
+            &cancel,
+            Duration::from_millis(1),
+            || true,
+            || ready = true
+        ));
+        assert!(ready);
+    }
+
+    /// テスト用に長時間動くプロセスをセッションとして登録
+    fn insert_test_process(
+        manager: &mut SphinxManager,
+        session_id: &str,
+        port: u16,
+        builder: Builder,
+    ) {
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        let options = SphinxConfig::default();
+        let args = build_autobuild_args(Path::new("/proj"), port, &options, &[]);
+        manager.processes.insert(
+            session_id.to_string(),
+            SphinxProcess {
+                child: Some(child),
+                host: HOST.to_string(),
+                port,
+                builder,
+                base_url_path: None,
+                stopped: Arc::new(AtomicBool::new(false)),
+                readiness: ProbeCancel::default(),
+                launch: Some(LaunchSpec {
+                    project_path: "/proj".to_string(),
+                    python_path: "python".to_string(),
+                    options,
+                    args,
+                }),
+                diagnostics: Arc::new(Mutex::new(DiagnosticsBuffer::default())),
+                build_waiters: BuildWaiters::default(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_wait_for_port_free() {
+        let held = TcpListener::bind((HOST, 0)).unwrap();
+        let port = held.local_addr().unwrap().port();
+        assert!(!wait_for_port_free(
+            port,
+            std::time::Duration::from_millis(100)
+        ));
+
+        let release = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(200));
+            drop(held);
+        });
+        assert!(wait_for_port_free(port, std::time::Duration::from_secs(5)));
+        release.join().unwrap();
+    }
+
+    #[test]
+    fn test_take_for_stop_terminates_outside_manager() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8000, Builder::Html);
+        let stopping = manager.take_for_stop("test").unwrap();
+        // 停止を待つ前に管理から外れている
+        assert!(!manager.is_running("test"));
+        assert!(manager.take_for_stop("test").is_none());
+        assert!(!stopping.is_attached());
+        assert_eq!(stopping.terminate(), Ok(8000));
+
+        manager
+            .attach("ext".to_string(), "127.0.0.1".to_string(), 8001)
+            .unwrap();
+        let stopping = manager.take_for_stop("ext").unwrap();
+        assert!(stopping.is_attached());
+        assert_eq!(stopping.terminate(), Ok(8001));
+    }
+
+    #[test]
+    fn test_output_change_tracker_reports_touched_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let build_dir = dir.path().join("_build/html");
+        std::fs::create_dir_all(&build_dir).unwrap();
+        std::fs::write(build_dir.join("untouched.html"), "").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(build_dir.join("untouched.html"))
+            .unwrap()
+            .set_modified(UNIX_EPOCH)
+            .unwrap();
+
+        let started = SystemTime::now() - std::time::Duration::from_secs(1);
+        let mut tracker = OutputChangeTracker::new(build_dir.clone(), UNIX_EPOCH);
+        // 初回ビルドの完了で基準時刻を返し、続く完了行では返さない
+        assert_eq!(
+            tracker.on_line("build succeeded.", SystemTime::now()),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(
+            tracker.on_line("waiting for changes...", SystemTime::now()),
+            None
+        );
+
+        assert_eq!(
+            tracker.on_line("Detected change(s) in index.rst", started),
+            None
+        );
+        assert_eq!(
+            tracker.on_line("Running Sphinx v7.2.6", SystemTime::now()),
+            None
+        );
+        std::fs::write(build_dir.join("index.html"), "<html>").unwrap();
+        let since = tracker
+            .on_line("build succeeded.", SystemTime::now())
+            .unwrap();
+        assert_eq!(since, started);
+
+        let (paths, truncated) =
+            build_output::changed_since(&build_dir, since, build_output::CHANGED_FILES_LIMIT)
+                .unwrap();
+        assert_eq!(paths, ["index.html"]);
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test_validate_project_path_suggests_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("docs")).unwrap();
+        std::fs::write(project.join("docs/conf.py"), "").unwrap();
+        // conf.pyのないディレクトリは候補にしない
+        std::fs::create_dir_all(dir.path().join("other/docs")).unwrap();
+
+        let options = SphinxConfig::default();
+        assert_eq!(
+            validate_project_path(&project, &options),
+            Ok(project.canonicalize().unwrap())
+        );
+
+        let missing = dir.path().join("projcet");
+        assert_eq!(
+            validate_project_path(&missing, &options),
+            Err(AppError::ProjectNotFound {
+                path: missing.to_string_lossy().to_string(),
+                suggestions: vec![project.to_string_lossy().to_string()],
+            })
+        );
+
+        // 親ディレクトリもなければ候補なし
+        let err = validate_project_path(&dir.path().join("a/b"), &options).unwrap_err();
+        assert!(
+            matches!(err, AppError::ProjectNotFound { suggestions, .. } if suggestions.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_build_cycle_tracker() {
+        let mut tracker = BuildCycleTracker::new("s1".to_string(), 1_000);
+        assert_eq!(
+            tracker.on_line("/docs/a.rst:1: WARNING: undefined label: 'a'", 1_100),
+            None
+        );
+        assert_eq!(
+            tracker.on_line("build succeeded, 1 warning.", 1_500),
+            Some(events::SphinxBuildCycle {
+                session_id: "s1".to_string(),
+                started_at: 1_000,
+                duration_ms: 500,
+                succeeded: true,
+                warnings: 1,
+                errors: 0,
+            })
+        );
+        // ビルド中でなければ完了行が続いても通知しない
+        assert_eq!(tracker.on_line("waiting for changes...", 1_600), None);
+
+        // 再ビルド（開始行が2つ続いても最初の時刻から計る）
+        assert_eq!(
+            tracker.on_line("Detected change(s) in index.rst", 2_000),
+            None
+        );
+        assert_eq!(tracker.on_line("Running Sphinx v7.2.6", 2_050), None);
+        tracker.on_line(
+            "/docs/b.rst:2: ERROR: Unknown directive type \"foo\".",
+            2_100,
+        );
+        tracker.on_line("/docs/c.rst:3: WARNING: undefined label: 'c'", 2_200);
+        let cycle = tracker
+            .on_line("[sphinx-autobuild] Sphinx exited with exit code: 2", 2_300)
+            .unwrap();
+        assert_eq!(
+            (cycle.started_at, cycle.duration_ms, cycle.succeeded),
+            (2_000, 300, false)
+        );
+        assert_eq!((cycle.warnings, cycle.errors), (1, 1));
+    }
+
+    #[test]
+    fn test_wait_for_build_resolves_on_success_line() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8000, Builder::Html);
+        let mut rx = manager.wait_for_build("test").unwrap();
+        let waiters = manager.processes["test"].build_waiters.clone();
+
+        waiters.on_line("Running Sphinx v7.2.6");
+        assert!(rx.try_recv().is_err());
+        waiters.on_line("build succeeded.");
+        assert_eq!(rx.blocking_recv(), Ok(Ok(())));
+
+        // プロセスが終了したらエラー
+        let rx = manager.wait_for_build("test").unwrap();
+        waiters.on_exited();
+        assert!(rx.blocking_recv().unwrap().is_err());
+
+        // 停止で待ち受けごと破棄されたら受信側がエラー
+        let rx = manager.wait_for_build("test").unwrap();
+        drop(waiters);
+        manager.stop("test").unwrap();
+        assert!(rx.blocking_recv().is_err());
+        assert!(manager.wait_for_build("test").is_err());
+    }
+
+    #[test]
+    fn test_open_after_build_opens_once() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8000, Builder::Html);
+        let waiters = manager.processes["test"].build_waiters.clone();
+        let opened = std::cell::Cell::new(0);
+        let open = || {
+            opened.set(opened.get() + 1);
+            Ok(())
+        };
+
+        // 完了行が続いても（2回目以降のビルドでも）開くのは最初の1回だけ
+        let rx = manager.wait_for_build("test").unwrap();
+        waiters.on_line("build succeeded.");
+        waiters.on_line("waiting for changes...");
+        waiters.on_line("build succeeded.");
+        let result = runtime.block_on(open_after_build("test", rx, Duration::from_secs(1), open));
+        assert_eq!(result, Ok(()));
+        assert_eq!(opened.get(), 1);
+
+        // ビルドせずに終了・タイムアウトした場合は開かない
+        let rx = manager.wait_for_build("test").unwrap();
+        waiters.on_exited();
+        let result = runtime.block_on(open_after_build("test", rx, Duration::from_secs(1), open));
+        assert!(result.is_err());
+        let rx = manager.wait_for_build("test").unwrap();
+        let result = runtime.block_on(open_after_build(
+            "test",
+            rx,
+            Duration::from_millis(10),
+            open,
+        ));
+        assert!(result.unwrap_err().contains("タイムアウト"));
+        assert_eq!(opened.get(), 1);
+    }
+
+    #[test]
+    fn test_start_result() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8000, Builder::Html);
+        manager.processes.get_mut("test").unwrap().base_url_path = Some("/docs/".to_string());
+
+        assert_eq!(
+            manager.start_result("test").unwrap(),
+            SphinxStartResult {
+                host: HOST.to_string(),
+                port: 8000,
+                url: "http://127.0.0.1:8000/docs/".to_string(),
+            }
+        );
+        assert!(manager.start_result("missing").is_err());
+    }
+
+    #[test]
+    fn test_diagnostics_filtered_by_severity() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8000, Builder::Html);
+        {
+            let mut diagnostics = manager.processes["test"].diagnostics.lock().unwrap();
+            diagnostics.record("/docs/a.rst:1: WARNING: undefined label: 'a'");
+            diagnostics.record("/docs/b.rst:2: ERROR: Unknown directive type \"foo\".");
+        }
+
+        let errors = manager.diagnostics("test", Severity::Error).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].file, Some("/docs/b.rst".to_string()));
+        assert_eq!(
+            manager.diagnostics("test", Severity::Info).unwrap().len(),
+            2
+        );
+        assert!(manager.diagnostics("missing", Severity::Info).is_err());
+    }
+
+    #[test]
+    fn test_plan_reconfigure_unchanged_is_noop() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8123, Builder::Html);
+
+        // 設定を省略しても、同じ設定を渡しても変更なし（自動割り当てのポートは引き継ぐ）
+        for options in [None, Some(SphinxConfig::default())] {
+            match manager.plan_reconfigure("test", options, &[]).unwrap() {
+                ReconfigurePlan::Unchanged(port) => assert_eq!(port, 8123),
+                plan => panic!("unexpected plan: {:?}", plan),
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_reconfigure_restart_when_args_change() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/conf.py"), "").unwrap();
+        let project_path = dir.path().to_string_lossy().to_string();
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8123, Builder::Html);
+        if let Some(launch) = manager.processes.get_mut("test").unwrap().launch.as_mut() {
+            launch.project_path = project_path.clone();
+        }
+
+        let options = SphinxConfig {
+            re_ignore: vec!["_build/".to_string()],
+            ..Default::default()
+        };
+        match manager
+            .plan_reconfigure("test", Some(options), &[])
+            .unwrap()
+        {
+            ReconfigurePlan::Restart(launch) => {
+                assert_eq!(launch.project_path, project_path);
+                assert_eq!(launch.options.server.port, 8123);
+                assert!(launch.args.contains(&"--re-ignore".to_string()));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        // 呼び出し側の追加引数の変更でも再起動
+        assert!(matches!(
+            manager
+                .plan_reconfigure("test", None, &["-W".to_string()])
+                .unwrap(),
+            ReconfigurePlan::Restart(_)
+        ));
+    }
+
+    #[test]
+    fn test_plan_reconfigure_validates_before_stopping() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_path = dir.path().to_string_lossy().to_string();
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8123, Builder::Html);
+        insert_test_process(&mut manager, "other", 8124, Builder::Html);
+        if let Some(launch) = manager.processes.get_mut("test").unwrap().launch.as_mut() {
+            launch.project_path = project_path;
+        }
+        let held = TcpListener::bind((HOST, 0)).unwrap();
+        let busy_port = held.local_addr().unwrap().port();
+        let with_port = |port| {
+            let mut options = SphinxConfig::default();
+            options.server.port = port;
+            options
+        };
+
+        // conf.pyがない
+        assert!(manager
+            .plan_reconfigure("test", None, &["-W".to_string()])
+            .is_err());
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/conf.py"), "").unwrap();
+
+        let invalid_re_ignore = SphinxConfig {
+            re_ignore: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(manager
+            .plan_reconfigure("test", Some(invalid_re_ignore), &[])
+            .is_err());
+        assert!(matches!(
+            manager.plan_reconfigure("test", Some(with_port(8124)), &[]),
+            Err(AppError::PortInUse {
+                session_id: Some(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            manager.plan_reconfigure("test", Some(with_port(busy_port)), &[]),
+            Err(AppError::PortInUse {
+                session_id: None,
+                ..
+            })
+        ));
+        // 検証に失敗しても現在のプロセスは動いたまま
+        assert!(manager.is_running("test"));
+        drop(held);
+        let _ = manager.stop("test");
+        let _ = manager.stop("other");
+    }
+
+    #[test]
+    fn test_plan_reconfigure_rejects_unknown_and_attached() {
+        let mut manager = SphinxManager::new();
+        assert!(manager.plan_reconfigure("missing", None, &[]).is_err());
+
+        manager
+            .attach("ext".to_string(), "127.0.0.1".to_string(), 8000)
+            .unwrap();
+        assert!(manager.plan_reconfigure("ext", None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_preview_url_for_html() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8000, Builder::Html);
+        assert_eq!(
+            manager.preview_url_for("test", "guide/intro.rst").unwrap(),
+            "http://127.0.0.1:8000/guide/intro.html"
+        );
+    }
+
+    #[test]
+    fn test_preview_url_for_dirhtml() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8001, Builder::DirHtml);
+        assert_eq!(
+            manager.preview_url_for("test", "guide/intro.rst").unwrap(),
+            "http://127.0.0.1:8001/guide/intro/"
+        );
+        assert_eq!(
+            manager.preview_url_for("test", "index.rst").unwrap(),
+            "http://127.0.0.1:8001/"
+        );
+    }
+
+    #[test]
+    fn test_preview_url_for_with_base_path() {
+        let mut manager = SphinxManager::new();
+        insert_test_process(&mut manager, "test", 8002, Builder::Html);
+        manager.processes.get_mut("test").unwrap().base_url_path = Some("/docs/".to_string());
+        assert_eq!(
+            manager.preview_url_for("test", "guide/intro.rst").unwrap(),
+            "http://127.0.0.1:8002/docs/guide/intro.html"
+        );
+    }
+
+    #[test]
+    fn test_preview_url_for_not_running() {
+        let manager = SphinxManager::new();
+        assert!(manager.preview_url_for("nonexistent", "index.rst").is_err());
+    }
+
+    #[test]
+    fn test_health_monitor_reports_after_repeated_failures() {
+        let mut monitor = HealthMonitor::default();
+        assert!(!monitor.record_probe(true));
+
+        assert!(!monitor.record_probe(false));
+        assert!(!monitor.record_probe(false));
+        assert!(monitor.record_probe(false));
+        // 通知は1度だけ
+        assert!(!monitor.record_probe(false));
+
+        // 応答が戻ればリセットされ、再度通知できる
+        assert!(!monitor.record_probe(true));
+        for _ in 1..HEALTH_FAILURE_THRESHOLD {
+            assert!(!monitor.record_probe(false));
+        }
+        assert!(monitor.record_probe(false));
+    }
+
+    #[test]
+    fn test_health_monitor_ignores_failures_before_first_response() {
+        let mut monitor = HealthMonitor::default();
+        for _ in 0..10 {
+            assert!(!monitor.record_probe(false));
+        }
+    }
+
+    #[test]
+    fn test_health_monitor_paused_during_build() {
+        let mut monitor = HealthMonitor::default();
+        monitor.record_probe(true);
+
+        monitor.on_build_started();
+        for _ in 0..10 {
+            assert!(!monitor.record_probe(false));
+        }
+
+        // ビルド完了後は失敗カウントが0から再開
+        monitor.on_build_finished();
+        assert!(!monitor.record_probe(false));
+        assert!(!monitor.record_probe(false));
+        assert!(monitor.record_probe(false));
+    }
+
+    #[test]
+    fn test_health_monitor_silent_after_exit() {
+        let mut monitor = HealthMonitor::default();
+        monitor.record_probe(true);
+        monitor.on_exited();
+        for _ in 0..10 {
+            assert!(!monitor.record_probe(false));
+        }
+    }
+
+    #[test]
+    fn test_build_log_classification() {
+        assert!(is_build_started("Running Sphinx v7.2.6"));
+        assert!(is_build_started(
+            "[sphinx-autobuild] Detected change: docs/index.rst"
+        ));
+        assert!(is_build_finished("build succeeded, 2 warnings."));
+        assert!(is_build_finished(
+            "watching for changes... waiting for changes"
+        ));
+        assert!(!is_build_started("reading sources... [100%] index"));
+    }
+
+    #[test]
+    fn test_resolve_project_dir_relative() {
+        assert_eq!(
+            resolve_project_dir(Path::new("/proj"), "_build/html"),
+            PathBuf::from("/proj/_build/html")
+        );
+        assert_eq!(
+            resolve_project_dir(Path::new("/proj"), "docs"),
+            PathBuf::from("/proj/docs")
+        );
+    }
+
+    #[test]
+    fn test_resolve_project_dir_absolute() {
+        assert_eq!(
+            resolve_project_dir(Path::new("/proj"), "/tmp/orthrus-build"),
+            PathBuf::from("/tmp/orthrus-build")
+        );
+        assert_eq!(
+            resolve_project_dir(Path::new("/proj"), "/srv/docs/source"),
+            PathBuf::from("/srv/docs/source")
+        );
+    }
+
+    #[test]
+    fn test_build_dir_status_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        // なければ作成する
+        let status = build_dir_status(dir.path(), "_build/html");
+        let build_dir = dir.path().join("_build/html");
+        assert!(status.writable);
+        assert_eq!(status.path, build_dir.to_string_lossy());
+        assert!(build_dir.is_dir());
+        // 確認用のファイルは残さない
+        assert_eq!(std::fs::read_dir(&build_dir).unwrap().count(), 0);
+
+        // 親がファイルで作成できない
+        std::fs::write(dir.path().join("file"), "").unwrap();
+        assert!(!build_dir_status(dir.path(), "file/html").writable);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_build_dir_status_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+        // rootは権限に関係なく書き込めるため確認できない
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let build_dir = dir.path().join("_build");
+        std::fs::create_dir(&build_dir).unwrap();
+        std::fs::set_permissions(&build_dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        assert!(!build_dir_status(dir.path(), "_build").writable);
+        std::fs::set_permissions(&build_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// テスト用のビルドを登録し、終了待ちの結果を受け取る
+    fn insert_test_build(
+        manager: &mut SphinxManager,
+        session_id: &str,
+        command: &mut Command,
+    ) -> std::sync::mpsc::Receiver<BuildOutcome> {
+        let build = OneShotBuild {
+            child: Arc::new(Mutex::new(command.spawn().unwrap())),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watched = build.clone();
+        thread::spawn(move || {
+            watch_build(&watched, |outcome| {
+                let _ = tx.send(outcome);
+            })
+        });
+        manager.builds.insert(session_id.to_string(), build);
+        rx
+    }
+
+    #[test]
+    fn test_cancel_build() {
+        let mut manager = SphinxManager::new();
+        let outcome = insert_test_build(&mut manager, "test", Command::new("sleep").arg("30"));
+        assert!(manager.is_building("test"));
+
+        // マネージャの外でキャンセルする
+        let build = manager.running_build("test").unwrap();
+        assert!(build.cancel().unwrap());
+        assert!(!manager.is_building("test"));
+
+        assert_eq!(
+            outcome.recv_timeout(Duration::from_secs(5)),
+            Ok(BuildOutcome::Cancelled)
+        );
+        // 終了したビルドは管理から外れる
+        assert!(manager.running_build("test").is_none());
+        assert!(manager.builds.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_build_after_natural_exit() {
+        let mut manager = SphinxManager::new();
+        let outcome = insert_test_build(&mut manager, "test", &mut Command::new("true"));
+        let build = manager.builds["test"].clone();
+        assert_eq!(
+            outcome.recv_timeout(Duration::from_secs(5)),
+            Ok(BuildOutcome::Succeeded)
+        );
+
+        // 終了済みのビルドはキャンセル扱いにしない
+        assert!(!build.cancel().unwrap());
+        assert!(manager.running_build("test").is_none());
+        assert!(manager.running_build("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_build_outcome_from_exit() {
+        let success = Command::new("true").status().unwrap();
+        let failure = Command::new("false").status().unwrap();
+        assert_eq!(
+            BuildOutcome::from_exit(false, success),
+            BuildOutcome::Succeeded
+        );
+        assert_eq!(
+            BuildOutcome::from_exit(false, failure),
+            BuildOutcome::Failed(Some(1))
+        );
+        assert_eq!(
+            BuildOutcome::from_exit(true, failure),
+            BuildOutcome::Cancelled
+        );
+        // キャンセルが間に合わず成功したものは完了扱い
+        assert_eq!(
+            BuildOutcome::from_exit(true, success),
+            BuildOutcome::Succeeded
+        );
+    }
+
     #[test]
     fn test_stop_nonexistent_session() {
         let mut manager = SphinxManager::new();
         // 存在しないセッションの停止は成功する
         assert!(manager.stop("nonexistent").is_ok());
     }
+
+    #[test]
+    fn test_restart_backoff_sequence() {
+        let backoff = RestartBackoff::new(500, 10_000, 0.25);
+
+        let nominal: Vec<u64> = (0..8).map(|a| backoff.nominal_ms(a)).collect();
+        assert_eq!(
+            nominal,
+            [500, 1000, 2000, 4000, 8000, 10_000, 10_000, 10_000]
+        );
+        assert!(nominal.windows(2).all(|w| w[0] <= w[1]));
+        // シフトで溢れても上限に張り付く
+        assert_eq!(backoff.nominal_ms(63), 10_000);
+        assert_eq!(backoff.nominal_ms(200), 10_000);
+
+        for attempt in 0..8 {
+            let nominal = backoff.nominal_ms(attempt);
+            assert_eq!(backoff.delay_ms(attempt, 0.0), nominal * 3 / 4);
+            assert_eq!(backoff.delay_ms(attempt, 1.0), nominal * 5 / 4);
+            for _ in 0..20 {
+                let delay = backoff.delay(attempt).as_millis() as u64;
+                assert!((nominal * 3 / 4..=nominal * 5 / 4).contains(&delay));
+            }
+        }
+    }
+
+    #[test]
+    fn test_restart_backoff_defaults_and_clamping() {
+        let backoff = RestartBackoff::default();
+        assert_eq!(
+            backoff,
+            RestartBackoff {
+                base_ms: 1000,
+                max_ms: 30_000,
+                jitter: 0.2
+            }
+        );
+
+        let backoff = RestartBackoff::new(5000, 100, 3.0);
+        assert_eq!(backoff.max_ms, 5000);
+        assert_eq!(backoff.jitter, 1.0);
+        assert_eq!(backoff.delay_ms(0, 0.0), 0);
+        assert_eq!(RestartBackoff::new(0, 0, f64::NAN).jitter, 0.2);
+        assert_eq!(RestartBackoff::new(0, 0, 0.0).nominal_ms(0), 1);
+    }
+
+    #[test]
+    fn test_restart_backoff_from_config() {
+        assert_eq!(
+            RestartBackoff::from_config(&ServerConfig::default()),
+            RestartBackoff::default()
+        );
+        let backoff = RestartBackoff::from_config(&ServerConfig {
+            restart_backoff_ms: Some(500),
+            restart_backoff_max_ms: Some(10_000),
+            restart_jitter: Some(0.25),
+            ..Default::default()
+        });
+        assert_eq!(backoff, RestartBackoff::new(500, 10_000, 0.25));
+        // 上限だけ指定した場合も初期値以上に補正する
+        let backoff = RestartBackoff::from_config(&ServerConfig {
+            restart_backoff_max_ms: Some(10),
+            ..Default::default()
+        });
+        assert_eq!(backoff.max_ms, 1000);
+    }
 }
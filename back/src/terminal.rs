@@ -1,19 +1,36 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::io::{Read, Write};
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex, Weak};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+use crate::color_scheme::{self, ColorScheme};
+use crate::error::AppError;
+use crate::events;
+use crate::logging::{self, LogLevel};
 
 /// シェルパスを決定する
-/// 優先順位: 設定値 > $SHELL環境変数 > /bin/sh
-fn detect_shell(config_shell: Option<&str>) -> String {
+/// 優先順位: 設定値 > 実行できる最初のフォールバック > $SHELL環境変数 > /bin/sh
+pub fn detect_shell(config_shell: Option<&str>, fallbacks: &[String]) -> String {
     // 設定で指定されていれば優先
     if let Some(shell) = config_shell {
         return shell.to_string();
     }
 
+    // フォールバックの候補を順に試す
+    if let Some(shell) = fallbacks.iter().find(|shell| check_shell(shell).is_ok()) {
+        return shell.clone();
+    }
+
     // $SHELL 環境変数
     if let Ok(shell) = std::env::var("SHELL") {
         return shell;
@@ -23,233 +40,3645 @@ fn detect_shell(config_shell: Option<&str>) -> String {
     "/bin/sh".to_string()
 }
 
-/// PTYセッションを管理する構造体
-pub struct PtySession {
-    writer: Box<dyn Write + Send>,
-    size: PtySize,
-    #[allow(dead_code)]
-    child: Box<dyn Child + Send + Sync>,
-    #[allow(dead_code)]
-    master: Box<dyn MasterPty + Send>,
+/// シェルを起動できるか事前に確認
+/// portable-ptyのエラーは文字列のみで原因を判別できないため、ここで分類する
+fn check_shell(shell_path: &str) -> Result<(), AppError> {
+    let path = Path::new(shell_path);
+    let candidates: Vec<PathBuf> = if path.components().count() > 1 {
+        vec![path.to_path_buf()]
+    } else {
+        // コマンド名のみの場合はPATHから探す
+        std::env::var_os("PATH")
+            .map(|paths| {
+                std::env::split_paths(&paths)
+                    .map(|dir| dir.join(path))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    // 存在はするが実行できない（実行権限がない・ディレクトリ）ものがあったか
+    let mut not_executable = false;
+    for candidate in &candidates {
+        if candidate.is_file() && is_executable(candidate) {
+            return Ok(());
+        }
+        not_executable |= candidate.exists();
+    }
+
+    let path = shell_path.to_string();
+    if not_executable {
+        Err(AppError::PermissionDenied { path })
+    } else {
+        Err(AppError::ShellNotFound { path })
+    }
 }
 
-/// 全PTYセッションを管理するマネージャー
-pub struct TerminalManager {
-    sessions: HashMap<String, PtySession>,
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
 }
 
-impl Default for TerminalManager {
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// 起動時のI/Oエラーをシェルのパス付きのエラーに変換
+fn spawn_error(shell_path: &str, kind: Option<std::io::ErrorKind>, message: String) -> AppError {
+    let path = shell_path.to_string();
+    match kind {
+        Some(std::io::ErrorKind::NotFound) => AppError::ShellNotFound { path },
+        Some(std::io::ErrorKind::PermissionDenied) => AppError::PermissionDenied { path },
+        _ => AppError::SpawnFailed { path, message },
+    }
+}
+
+/// 割り込み文字（Ctrl-C / ETX）
+const INTERRUPT: &[u8] = b"\x03";
+
+/// フォーカス通知（xtermのフォーカスレポート）
+const FOCUS_IN: &[u8] = b"\x1b[I";
+const FOCUS_OUT: &[u8] = b"\x1b[O";
+
+/// 出力中のフォーカスレポート（DECSET 1004）の有効化・無効化を検出
+/// 1回の出力に両方含まれる場合は後のものを返す
+fn parse_focus_reporting(data: &[u8]) -> Option<bool> {
+    const ENABLE: &[u8] = b"\x1b[?1004h";
+    const DISABLE: &[u8] = b"\x1b[?1004l";
+    data.windows(ENABLE.len()).rev().find_map(|window| {
+        if window == ENABLE {
+            Some(true)
+        } else if window == DISABLE {
+            Some(false)
+        } else {
+            None
+        }
+    })
+}
+
+/// バックエンドで保持するスクロールバックの上限（バイト）
+const SCROLLBACK_LIMIT_BYTES: usize = 1024 * 1024;
+
+/// 出力の末尾を保持するリングバッファ（再接続時の再生用）
+#[derive(Debug)]
+pub struct Scrollback {
+    buffer: VecDeque<u8>,
+    limit: usize,
+}
+
+impl Default for Scrollback {
     fn default() -> Self {
-        Self::new()
+        Self::with_limit(SCROLLBACK_LIMIT_BYTES)
     }
 }
 
-impl TerminalManager {
-    pub fn new() -> Self {
+impl Scrollback {
+    pub fn with_limit(limit: usize) -> Self {
         Self {
-            sessions: HashMap::new(),
+            buffer: VecDeque::new(),
+            limit,
         }
     }
 
-    /// 新しいPTYセッションを生成
-    pub fn spawn(
-        &mut self,
-        session_id: String,
-        cwd: Option<String>,
-        shell: Option<String>,
-        cols: u16,
-        rows: u16,
-        app_handle: AppHandle,
-    ) -> Result<(), String> {
-        // 既に同じセッションが存在する場合はスキップ（React StrictMode対策）
-        if self.sessions.contains_key(&session_id) {
-            return Ok(());
+    /// 出力を追記し、上限を超えた分を先頭から捨てる
+    pub fn push(&mut self, data: &[u8]) {
+        let data = &data[data.len().saturating_sub(self.limit)..];
+        let overflow = (self.buffer.len() + data.len()).saturating_sub(self.limit);
+        self.buffer.drain(..overflow);
+        self.buffer.extend(data);
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.buffer.iter().copied().collect()
+    }
+}
+
+/// 書き込みキューに溜められるチャンク数
+/// これを超えるとPTY側が詰まっているとみなし、書き込みを拒否する
+const WRITE_QUEUE_CAPACITY: usize = 64;
+
+/// 貼り付けサイズの上限の既定値（誤って巨大なテキストを貼り付けてPTYが固まるのを防ぐ）
+pub const DEFAULT_MAX_PASTE_BYTES: usize = 1024 * 1024;
+
+/// 貼り付けサイズの上限を決める（呼び出し側の指定 > 設定の `max_paste_bytes` > 既定値）
+pub fn resolve_paste_limit(requested: Option<usize>, configured: Option<usize>) -> usize {
+    requested.or(configured).unwrap_or(DEFAULT_MAX_PASTE_BYTES)
+}
+
+/// バイト列をgzipで圧縮（スクロールバックの転送量削減用）
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// 読み取り速度を計算する直近の期間
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(5);
+
+/// セッションの出力量の計測（読み取りスレッドが更新する）
+#[derive(Debug, Default)]
+struct OutputMetrics {
+    bytes_read: AtomicU64,
+    events_emitted: AtomicU64,
+    /// THROUGHPUT_WINDOW内の読み取り（時刻, バイト数）
+    recent: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+impl OutputMetrics {
+    fn record_read(&self, bytes: usize, now: Instant) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.push_back((now, bytes as u64));
+            Self::prune(&mut recent, now);
+        }
+    }
+
+    fn record_event(&self) {
+        self.events_emitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn prune(recent: &mut VecDeque<(Instant, u64)>, now: Instant) {
+        while recent
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > THROUGHPUT_WINDOW)
+        {
+            recent.pop_front();
         }
+    }
 
-        let pty_system = native_pty_system();
+    fn snapshot(&self, now: Instant) -> TerminalMetrics {
+        let recent_bytes = self
+            .recent
+            .lock()
+            .map(|mut recent| {
+                Self::prune(&mut recent, now);
+                recent.iter().map(|(_, bytes)| bytes).sum::<u64>()
+            })
+            .unwrap_or(0);
+        TerminalMetrics {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            events_emitted: self.events_emitted.load(Ordering::Relaxed),
+            bytes_per_sec: recent_bytes as f64 / THROUGHPUT_WINDOW.as_secs_f64(),
+        }
+    }
+}
 
-        let size = PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        };
+/// `get_terminal_metrics` の戻り値
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TerminalMetrics {
+    /// PTYから読み取った累計バイト数
+    pub bytes_read: u64,
+    /// フロントエンドへ通知した累計イベント数
+    pub events_emitted: u64,
+    /// 直近5秒の平均の読み取り速度
+    pub bytes_per_sec: f64,
+}
 
-        let pair = pty_system
-            .openpty(size)
-            .map_err(|e| format!("Failed to open pty: {}", e))?;
+/// PTYへの書き込みを専用スレッドで行う
+/// 読み手が詰まってもIPCスレッドがブロックしないよう、キュー経由で渡す
+fn spawn_writer_thread(mut writer: Box<dyn Write + Send>) -> SyncSender<Vec<u8>> {
+    let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(WRITE_QUEUE_CAPACITY);
+    thread::spawn(move || {
+        for data in rx {
+            if writer
+                .write_all(&data)
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    tx
+}
 
-        // シェルを検出してログインシェルとして起動
-        let shell_path = detect_shell(shell.as_deref());
-        let mut cmd = CommandBuilder::new(&shell_path);
-        cmd.arg("-l");
+/// キープアライブで書き込むバイト列（NUL。ほとんどのシェルは無視する）
+const KEEPALIVE_BYTES: &[u8] = b"\0";
 
-        if let Some(ref dir) = cwd {
-            cmd.cwd(dir);
+/// 一定間隔で `send` を呼び、SSH越しのシェルが無通信で切断されるのを防ぐ
+/// `send` がfalseを返したら（セッションの終了など）止まる
+fn spawn_keepalive_thread<F>(interval: Duration, mut send: F)
+where
+    F: FnMut() -> bool + Send + 'static,
+{
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        if !send() {
+            break;
         }
+    });
+}
 
-        cmd.env("TERM", "xterm-256color");
-        cmd.env("COLORTERM", "truecolor");
-        cmd.env("SHELL", &shell_path);
+/// 起動後、最初の出力が届くまでを監視する
+/// timeout内に `first_output` が届かなければ、その時点のセッションIDで `on_slow` を呼ぶ
+/// （idはセッションと共有し、`rename` 後は新しいIDになる。セッションには触れない）
+fn spawn_startup_watchdog<F>(
+    timeout: Duration,
+    first_output: mpsc::Receiver<()>,
+    id: Arc<Mutex<String>>,
+    on_slow: F,
+) where
+    F: FnOnce(&str) + Send + 'static,
+{
+    thread::spawn(move || {
+        // 出力前に読み取りスレッドが終了した場合（Disconnected）は警告しない
+        if let Err(RecvTimeoutError::Timeout) = first_output.recv_timeout(timeout) {
+            let session_id = id.lock().map(|id| id.clone()).unwrap_or_default();
+            on_slow(&session_id);
+        }
+    });
+}
 
-        let child = pair
-            .slave
-            .spawn_command(cmd)
-            .map_err(|e| format!("Failed to spawn command: {}", e))?;
+/// 出力からウィンドウタイトル（OSC 0 / OSC 2）を取り出す
+/// 1回の出力に複数含まれる場合は最後のものを返す
+fn parse_osc_title(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let mut title = None;
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find("\x1b]") {
+        let body = &rest[start + 2..];
+        let Some(end) = body.find(['\x07', '\x1b']) else {
+            break;
+        };
+        if let Some(value) = body[..end]
+            .strip_prefix("0;")
+            .or_else(|| body[..end].strip_prefix("2;"))
+        {
+            title = Some(value.to_string());
+        }
+        rest = &body[end..];
+    }
+    title
+}
 
-        // macOS: spawn後の短いスリープでレースコンディション回避
-        thread::sleep(Duration::from_millis(50));
+/// 出力から作業ディレクトリの通知（OSC 7、`file://host/path`）を取り出す
+/// 1回の出力に複数含まれる場合は最後のものを返す
+fn parse_osc_cwd(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let mut cwd = None;
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find("\x1b]7;") {
+        let body = &rest[start + 4..];
+        let Some(end) = body.find(['\x07', '\x1b']) else {
+            break;
+        };
+        if let Some(path) = body[..end]
+            .strip_prefix("file://")
+            .and_then(|location| location.find('/').map(|i| &location[i..]))
+        {
+            cwd = Some(percent_decode(path));
+        }
+        rest = &body[end..];
+    }
+    cwd
+}
 
-        // slaveをdrop（親で保持するとEOF問題が発生）
-        drop(pair.slave);
+/// `%XX` 形式のエスケープを戻す（不正な並びはそのまま残す）
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
 
-        let mut reader = pair
-            .master
-            .try_clone_reader()
-            .map_err(|e| format!("Failed to clone reader: {}", e))?;
+/// シェル統合のプロンプトの区切り（OSC 133）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptMarker {
+    /// A: プロンプトの表示開始
+    PromptStart,
+    /// B: プロンプトの終わり（コマンドの入力開始）
+    CommandStart,
+    /// C: コマンドの実行開始（ここから出力）
+    CommandExecuted,
+    /// D: コマンドの終了（終了コード付き）
+    CommandFinished(Option<i32>),
+}
 
-        let writer = pair
-            .master
-            .take_writer()
-            .map_err(|e| format!("Failed to take writer: {}", e))?;
-
-        let session = PtySession {
-            writer,
-            size,
-            child,
-            master: pair.master,
+impl PromptMarker {
+    /// `pty_prompt_marker` で送る種別（OSC 133の文字そのまま）
+    pub fn letter(self) -> &'static str {
+        match self {
+            PromptMarker::PromptStart => "A",
+            PromptMarker::CommandStart => "B",
+            PromptMarker::CommandExecuted => "C",
+            PromptMarker::CommandFinished(_) => "D",
+        }
+    }
+
+    pub fn exit_code(self) -> Option<i32> {
+        match self {
+            PromptMarker::CommandFinished(code) => code,
+            _ => None,
+        }
+    }
+}
+
+/// 出力からOSC 133のプロンプトの区切りを出現順に取り出す
+/// `D` の終了コードは `133;D;<code>` の1つ目の値（省略・不正ならNone）
+fn parse_prompt_markers(data: &[u8]) -> Vec<PromptMarker> {
+    let text = String::from_utf8_lossy(data);
+    let mut markers = Vec::new();
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find("\x1b]133;") {
+        let body = &rest[start + 6..];
+        let Some(end) = body.find(['\x07', '\x1b']) else {
+            break;
+        };
+        let mut params = body[..end].split(';');
+        let marker = match params.next() {
+            Some("A") => Some(PromptMarker::PromptStart),
+            Some("B") => Some(PromptMarker::CommandStart),
+            Some("C") => Some(PromptMarker::CommandExecuted),
+            Some("D") => Some(PromptMarker::CommandFinished(
+                params.next().and_then(|code| code.parse().ok()),
+            )),
+            _ => None,
         };
-        self.sessions.insert(session_id.clone(), session);
+        markers.extend(marker);
+        rest = &body[end..];
+    }
+    markers
+}
 
-        // 出力読み取りスレッド（即時送信）
-        let sid = session_id.clone();
+/// シェル統合の設定を書き込めるシェルの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShellKind {
+    Bash,
+    Zsh,
+}
 
-        thread::spawn(move || {
-            let mut buffer = [0u8; 4096];
+impl ShellKind {
+    /// シェルのパスから判定する（ログインシェルの `-bash` なども含む）
+    fn detect(shell: &str) -> Option<Self> {
+        let name = Path::new(shell).file_name()?.to_str()?;
+        match name.trim_start_matches('-') {
+            "bash" => Some(ShellKind::Bash),
+            "zsh" => Some(ShellKind::Zsh),
+            _ => None,
+        }
+    }
 
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => {
-                        let _ = app_handle.emit("pty_exit", (&sid, 0));
-                        break;
-                    }
-                    Ok(n) => {
-                        // 読み取ったデータを即座に送信
-                        let data = String::from_utf8_lossy(&buffer[..n]).to_string();
-                        let _ = app_handle.emit("pty_data", (&sid, data));
-                    }
-                    Err(_) => {
-                        let _ = app_handle.emit("pty_exit", (&sid, 1));
-                        break;
-                    }
+    /// 起動直後に入力するOSC 133を出力する設定
+    /// 既存のPROMPT_COMMAND / precmdの前に置き、直前のコマンドの終了コードを取れるようにする。
+    /// 先頭の空白はHISTCONTROL=ignorespace / hist_ignore_spaceで履歴に残さないため
+    fn integration_script(self) -> &'static str {
+        match self {
+            ShellKind::Bash => concat!(
+                r#" __orthrus_prompt() { local s=$?; printf '\e]133;D;%s\a\e]133;A\a' "$s"; };"#,
+                r#" PROMPT_COMMAND="__orthrus_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}";"#,
+                r#" PS1="$PS1\[\e]133;B\a\]"; PS0='\e]133;C\a'"#,
+                "\n",
+            ),
+            ShellKind::Zsh => concat!(
+                r#" __orthrus_precmd() { local s=$?; print -n "\e]133;D;$s\a\e]133;A\a"; };"#,
+                r#" __orthrus_preexec() { print -n "\e]133;C\a"; };"#,
+                r#" precmd_functions=(__orthrus_precmd $precmd_functions);"#,
+                r#" preexec_functions+=(__orthrus_preexec);"#,
+                r#" PS1="$PS1%{"$'\e]133;B\a'"%}""#,
+                "\n",
+            ),
+        }
+    }
+}
+
+/// アクセントカラーを検証して `#rrggbb` に正規化する
+fn validate_accent(accent: &str) -> Result<String, AppError> {
+    color_scheme::parse_hex_color(accent)
+        .ok_or_else(|| AppError::Other(format!("Invalid accent color: {}", accent)))
+}
+
+/// ターミナルセッションの情報（ツールチップ表示用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TerminalInfo {
+    pub pid: Option<u32>,
+    pub shell: String,
+    pub cols: u16,
+    pub rows: u16,
+    /// 起動時の作業ディレクトリ
+    pub cwd: Option<String>,
+    /// シェルが設定したウィンドウタイトル
+    pub title: Option<String>,
+    /// 起動時刻（UNIXエポックからのミリ秒）
+    pub started_at: u64,
+    pub alive: bool,
+    /// 出力がEIOで閉じたか（slave側が先に閉じた。EOFで閉じた場合や動作中はfalse）
+    pub hangup: bool,
+    /// 起動時に指定したアクセントカラー
+    pub accent: Option<String>,
+    /// 最後に適用したカラースキーム（再接続時にフロントエンドが復元する）
+    pub color_scheme: Option<ColorScheme>,
+}
+
+/// `restart_all_terminals` の結果（それぞれセッションID順）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RestartAllResult {
+    /// 起動し直したセッション
+    pub restarted: Vec<String>,
+    /// 起動し直せなかったセッション（古いプロセスは終了済みで、セッションは破棄される）
+    pub failed: Vec<RestartFailure>,
+}
+
+/// 起動し直せなかったセッションと理由
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RestartFailure {
+    pub session_id: String,
+    pub error: AppError,
+}
+
+/// `list_terminals` の1件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TerminalListEntry {
+    pub session_id: String,
+    #[serde(flatten)]
+    pub info: TerminalInfo,
+}
+
+/// PTYセッションを管理する構造体
+pub struct PtySession {
+    /// 書き込みスレッドへのキュー
+    writer: SyncSender<Vec<u8>>,
+    size: PtySize,
+    /// 出力のスクロールバック（読み取りスレッドと共有）
+    scrollback: Arc<Mutex<Scrollback>>,
+    /// 起動したシェル
+    shell: String,
+    /// シェルに渡した追加の引数
+    shell_args: Vec<String>,
+    /// 起動時の作業ディレクトリ
+    cwd: Option<String>,
+    /// ウィンドウタイトル（読み取りスレッドと共有）
+    title: Arc<Mutex<Option<String>>>,
+    /// 実行中のアプリがフォーカスレポートを有効にしているか（読み取りスレッドと共有）
+    focus_reporting: Arc<AtomicBool>,
+    /// 現在のセッションID（読み取りスレッドと共有。`rename` で変わる）
+    id: Arc<Mutex<String>>,
+    /// 再起動で置き換え済み（読み取りスレッドは以後の出力・終了を通知しない）
+    replaced: Arc<AtomicBool>,
+    /// 終了処理中（以後の入力を受け付けない）
+    closing: bool,
+    /// 出力が閉じた（読み取りスレッドが設定）
+    exited: Arc<ExitedFlag>,
+    /// 出力がEIOで閉じた（読み取りスレッドが設定。EOFで閉じた場合はfalse）
+    hangup: Arc<AtomicBool>,
+    /// 次に通知する `pty_data` の連番（読み取りスレッドと共有）
+    output_seq: Arc<AtomicU64>,
+    /// 出力量の計測（読み取りスレッドと共有）
+    metrics: Arc<OutputMetrics>,
+    started_at: SystemTime,
+    /// 最後に入出力があった時刻（読み取りスレッドと共有）
+    last_activity: Arc<Mutex<Instant>>,
+    /// アイドル時の自動終了の対象外にするか
+    keep_alive: bool,
+    /// キープアライブの間隔（Noneなら送らない）
+    keepalive_interval: Option<Duration>,
+    /// シェル統合の設定を書き込んだか（再起動時に引き継ぐ）
+    shell_integration: bool,
+    /// rcファイルを読まずに起動したか（再起動時に引き継ぐ）
+    safe_mode: bool,
+    /// slaveを最初の読み取りまで保持したか（再起動時に引き継ぐ）
+    defer_slave_drop: bool,
+    /// UIでタブの見出しなどを色分けするためのアクセントカラー（`#rrggbb`）
+    accent: Option<String>,
+    /// 最後に適用したカラースキーム（再起動時に引き継ぐ）
+    color_scheme: Option<ColorScheme>,
+    /// シェルに追加した環境変数（再起動時に引き継ぐ）
+    env: HashMap<String, String>,
+    /// 出力の送り先（再起動時に引き継ぐ。起動前はNone）
+    sink: Option<OutputSink>,
+    /// シェルのプロセス（読み取りスレッドと共有）
+    child: SharedChild,
+    /// 終了コードの待ち受け（読み取りスレッドが記録）
+    exit: ExitWaiters,
+    master: Box<dyn MasterPty + Send>,
+}
+
+/// 読み取りスレッドと共有するシェルのプロセス
+#[derive(Clone)]
+struct SharedChild(Arc<Mutex<Box<dyn Child + Send + Sync>>>);
+
+impl SharedChild {
+    fn new(child: Box<dyn Child + Send + Sync>) -> Self {
+        Self(Arc::new(Mutex::new(child)))
+    }
+
+    fn lock(&self) -> std::io::Result<std::sync::MutexGuard<'_, Box<dyn Child + Send + Sync>>> {
+        self.0
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))
+    }
+
+    fn process_id(&self) -> Option<u32> {
+        self.lock().ok()?.process_id()
+    }
+
+    fn try_wait(&self) -> std::io::Result<Option<portable_pty::ExitStatus>> {
+        self.lock()?.try_wait()
+    }
+
+    fn kill(&self) -> std::io::Result<()> {
+        self.lock()?.kill()
+    }
+
+    fn wait(&self) -> std::io::Result<portable_pty::ExitStatus> {
+        self.lock()?.wait()
+    }
+}
+
+/// 終了コードの待ち受けに送る結果
+pub type ExitResult = Result<u32, String>;
+
+/// シェルの終了コードの待ち受け
+/// 読み取りスレッドが出力の終了後に記録して待ち受けに送る。
+/// 記録後に登録した待ち受けにはすぐ送るため、終了後にセッションを閉じても受け取れる
+#[derive(Clone, Default)]
+struct ExitWaiters(Arc<Mutex<ExitState>>);
+
+#[derive(Default)]
+struct ExitState {
+    result: Option<ExitResult>,
+    waiters: Vec<oneshot::Sender<ExitResult>>,
+}
+
+impl ExitWaiters {
+    fn subscribe(&self) -> oneshot::Receiver<ExitResult> {
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut state) = self.0.lock() {
+            match state.result {
+                Some(ref result) => {
+                    let _ = tx.send(result.clone());
                 }
+                None => state.waiters.push(tx),
             }
-        });
+        }
+        rx
+    }
 
-        Ok(())
+    fn notify(&self, result: ExitResult) {
+        if let Ok(mut state) = self.0.lock() {
+            for waiter in state.waiters.drain(..) {
+                let _ = waiter.send(result.clone());
+            }
+            state.result = Some(result);
+        }
     }
+}
 
-    /// PTYにデータを書き込む
-    pub fn write(&mut self, session_id: &str, data: &[u8]) -> Result<(), String> {
-        let session = self
-            .sessions
-            .get_mut(session_id)
-            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+/// 終了を確認する間隔
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
-        session
-            .writer
-            .write_all(data)
-            .map_err(|e| format!("Failed to write: {}", e))?;
+/// 出力が閉じた後、シェルの終了を待って終了コードを記録するスレッドを起動
+/// （マネージャのロックは取らず、プロセスのロックだけを短く取る）
+fn spawn_exit_recorder(child: SharedChild, exit: ExitWaiters) {
+    thread::spawn(move || loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return exit.notify(Ok(status.exit_code())),
+            Ok(None) => thread::sleep(EXIT_POLL_INTERVAL),
+            Err(e) => {
+                return exit.notify(Err(format!("Failed to check process status: {}", e)));
+            }
+        }
+    });
+}
 
-        session
-            .writer
-            .flush()
-            .map_err(|e| format!("Failed to flush: {}", e))?;
+/// 読み取りスレッドから通知する出力
+enum PtyOutput {
+    /// `seq` はセッションごとに0から1ずつ増える（フロントエンドで欠落を検出できる）
+    Data { seq: u64, data: Vec<u8> },
+    /// 出力の終了。codeは正常に閉じた場合0、読み取りエラーの場合1
+    /// errorは再試行しても読み取れなかった（セッションは破棄される）
+    Exit { code: i32, error: bool },
+    /// シェルがOSC 7で通知した作業ディレクトリ（直前と同じ場合は送らない）
+    Cwd(String),
+    /// シェル統合のプロンプトの区切り（OSC 133）
+    PromptMarker(PromptMarker),
+}
 
-        Ok(())
+/// 一時的な読み取りエラーを連続で再試行する回数
+const READ_RETRY_LIMIT: u32 = 3;
+/// 再試行までの待ち時間
+const READ_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// 読み取りエラーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadFailure {
+    /// 再試行すれば読める可能性がある
+    Transient,
+    /// slave側が閉じた（シェルの終了。LinuxではEOFではなくEIOになる）
+    Closed,
+    /// それ以外の継続できないエラー
+    Fatal,
+}
+
+fn classify_read_error(e: &std::io::Error) -> ReadFailure {
+    use std::io::ErrorKind;
+    match e.kind() {
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+            ReadFailure::Transient
+        }
+        #[cfg(unix)]
+        _ if e.raw_os_error() == Some(libc::EIO) => ReadFailure::Closed,
+        _ => ReadFailure::Fatal,
     }
+}
 
-    /// PTYのサイズを変更
-    pub fn resize(&mut self, session_id: &str, cols: u16, rows: u16) -> Result<(), String> {
-        let session = self
-            .sessions
-            .get_mut(session_id)
-            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+/// 読み取りスレッドと共有するセッションの状態
+struct ReaderState {
+    id: Arc<Mutex<String>>,
+    scrollback: Arc<Mutex<Scrollback>>,
+    title: Arc<Mutex<Option<String>>>,
+    last_activity: Arc<Mutex<Instant>>,
+    focus_reporting: Arc<AtomicBool>,
+    replaced: Arc<AtomicBool>,
+    exited: Arc<ExitedFlag>,
+    hangup: Arc<AtomicBool>,
+    output_seq: Arc<AtomicU64>,
+    metrics: Arc<OutputMetrics>,
+    /// 出力が閉じた後に終了コードを記録するプロセス（Noneなら記録しない）
+    child: Option<SharedChild>,
+    exit: ExitWaiters,
+}
 
-        session.size = PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
+/// 出力読み取りスレッドを起動
+/// スクロールバックやタイトルなどセッションの共有状態を更新しつつ、
+/// その時点のセッションIDとともに `emit` に渡す。
+/// 再起動で置き換えられたセッションの出力・終了は通知しない
+fn spawn_reader_thread<F>(
+    mut reader: Box<dyn Read + Send>,
+    state: ReaderState,
+    mut first_output: Option<Sender<()>>,
+    emit: F,
+) where
+    F: Fn(&str, PtyOutput) + Send + 'static,
+{
+    let ReaderState {
+        id,
+        scrollback,
+        title,
+        last_activity,
+        focus_reporting,
+        replaced,
+        exited,
+        hangup,
+        output_seq,
+        metrics,
+        child,
+        exit,
+    } = state;
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        let emit = |output: PtyOutput| {
+            if !replaced.load(Ordering::Relaxed) {
+                let session_id = id.lock().map(|id| id.clone()).unwrap_or_default();
+                metrics.record_event();
+                emit(&session_id, output);
+            }
         };
 
-        // Note: portable-ptyではresizeはmasterから行う必要がある
-        // 現在の実装ではsizeを保存するのみ
+        let mut retries = 0;
+        let mut last_cwd: Option<String> = None;
+        let (code, error) = loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break (0, false),
+                Ok(n) => {
+                    retries = 0;
+                    metrics.record_read(n, Instant::now());
+                    if let Some(tx) = first_output.take() {
+                        let _ = tx.send(());
+                    }
+                    if let Ok(mut scrollback) = scrollback.lock() {
+                        scrollback.push(&buffer[..n]);
+                    }
+                    if let Ok(mut last_activity) = last_activity.lock() {
+                        *last_activity = Instant::now();
+                    }
+                    if let Some(enabled) = parse_focus_reporting(&buffer[..n]) {
+                        focus_reporting.store(enabled, Ordering::Relaxed);
+                    }
+                    if let Some(new_title) = parse_osc_title(&buffer[..n]) {
+                        if let Ok(mut title) = title.lock() {
+                            *title = Some(new_title);
+                        }
+                    }
+                    // 読み取ったデータを即座に送信
+                    emit(PtyOutput::Data {
+                        seq: output_seq.fetch_add(1, Ordering::Relaxed),
+                        data: buffer[..n].to_vec(),
+                    });
+                    if let Some(cwd) = parse_osc_cwd(&buffer[..n]) {
+                        if last_cwd.as_ref() != Some(&cwd) {
+                            last_cwd = Some(cwd.clone());
+                            emit(PtyOutput::Cwd(cwd));
+                        }
+                    }
+                    for marker in parse_prompt_markers(&buffer[..n]) {
+                        emit(PtyOutput::PromptMarker(marker));
+                    }
+                }
+                Err(e) => match classify_read_error(&e) {
+                    ReadFailure::Transient if retries < READ_RETRY_LIMIT => {
+                        retries += 1;
+                        thread::sleep(READ_RETRY_DELAY);
+                    }
+                    ReadFailure::Closed => {
+                        hangup.store(true, Ordering::Relaxed);
+                        break (0, false);
+                    }
+                    ReadFailure::Transient | ReadFailure::Fatal => break (1, true),
+                },
+            }
+        };
 
-        Ok(())
-    }
+        exited.set();
+        emit(PtyOutput::Exit { code, error });
+        if let Some(child) = child {
+            spawn_exit_recorder(child, exit);
+        }
+    });
+}
 
-    /// セッションを終了
-    pub fn kill(&mut self, session_id: &str) -> Result<(), String> {
-        self.sessions
-            .remove(session_id)
-            .ok_or_else(|| format!("Session not found: {}", session_id))?;
-        Ok(())
+/// 出力の送り先
+#[derive(Clone)]
+enum OutputSink {
+    /// `pty_data` / `pty_exit` イベントとして通知
+    Events(AppHandle),
+    /// 出力のバイト列をそのまま送る（Tauriを介さずに読む埋め込み先向け。終了は通知しない）
+    Channel(Sender<Vec<u8>>),
+}
+
+impl OutputSink {
+    fn send(&self, session_id: &str, output: PtyOutput) {
+        match (self, output) {
+            (OutputSink::Events(app_handle), PtyOutput::Data { seq, data }) => {
+                let data = String::from_utf8_lossy(&data);
+                let _ = app_handle.emit(events::PTY_DATA, events::PtyData(session_id, &data, seq));
+            }
+            (OutputSink::Events(app_handle), PtyOutput::Exit { code, error }) => {
+                let _ = app_handle.emit(events::PTY_EXIT, events::PtyExit(session_id, code, error));
+            }
+            (OutputSink::Channel(tx), PtyOutput::Data { data, .. }) => {
+                let _ = tx.send(data);
+            }
+            (OutputSink::Events(app_handle), PtyOutput::Cwd(path)) => {
+                let _ = app_handle.emit(events::PTY_CWD, events::PtyCwd(session_id, &path));
+            }
+            (OutputSink::Events(app_handle), PtyOutput::PromptMarker(marker)) => {
+                let _ = app_handle.emit(
+                    events::PTY_PROMPT_MARKER,
+                    events::PtyPromptMarker(session_id, marker.letter(), marker.exit_code()),
+                );
+            }
+            (
+                OutputSink::Channel(_),
+                PtyOutput::Exit { .. } | PtyOutput::Cwd(_) | PtyOutput::PromptMarker(_),
+            ) => {}
+        }
     }
 }
 
-/// グローバルなTerminalManagerへのアクセス用
-pub type SharedTerminalManager = Arc<Mutex<TerminalManager>>;
+/// `pty_resized` イベントのペイロード（実際に適用したサイズ）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PtyResized {
+    pub session_id: String,
+    pub cols: u16,
+    pub rows: u16,
+}
 
-pub fn create_terminal_manager() -> SharedTerminalManager {
-    Arc::new(Mutex::new(TerminalManager::new()))
+/// `spawn` の引数（フロントエンドからはcamelCaseで渡す。省略したものは既定値）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpawnOptions {
+    pub cwd: Option<String>,
+    /// None = 設定・$SHELLから自動検出
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub shell_args: Vec<String>,
+    #[serde(default)]
+    pub shell_fallbacks: Vec<String>,
+    pub cols: u16,
+    pub rows: u16,
+    pub startup_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub keep_alive: bool,
+    pub keepalive_secs: Option<u64>,
+    #[serde(default)]
+    pub shell_integration: bool,
+    #[serde(default)]
+    pub safe_mode: bool,
+    #[serde(default)]
+    pub defer_slave_drop: bool,
+    pub accent: Option<String>,
+    /// シェルに追加する環境変数（TERM / COLORTERM / SHELLより優先）
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// セッションを起動（再起動）するための情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SessionSpec {
+    /// None = 設定・$SHELLから自動検出
+    shell: Option<String>,
+    /// シェルの引数（空ならログインシェルの `-l`）
+    shell_args: Vec<String>,
+    /// shellがNoneの場合に順に試すシェル
+    shell_fallbacks: Vec<String>,
+    cwd: Option<String>,
+    cols: u16,
+    rows: u16,
+    /// 起動直後のタイトル（再起動時に引き継ぐ）
+    title: Option<String>,
+    keep_alive: bool,
+    keepalive_interval: Option<Duration>,
+    /// 起動直後にOSC 133を出力する設定を書き込む（bash / zshのみ）
+    shell_integration: bool,
+    /// rcファイルを読まずに起動する（壊れたrcの修復用）
+    safe_mode: bool,
+    /// slaveを閉じるのを最初の読み取りまで遅らせる（`SlaveDrop` を参照）
+    defer_slave_drop: bool,
+    /// UI用のアクセントカラー（検証・正規化済み）
+    accent: Option<String>,
+    color_scheme: Option<ColorScheme>,
+    /// シェルに追加する環境変数
+    env: HashMap<String, String>,
+}
 
-    #[test]
-    fn test_terminal_manager_creation() {
-        let manager = TerminalManager::new();
-        assert!(manager.sessions.is_empty());
+/// rcファイルを読まずに起動するフラグ（シェルのファイル名で判定、未知のシェルは空）
+/// bashの長いオプションは1文字のオプションより前に置く必要がある
+fn safe_mode_flags(shell_path: &str) -> &'static [&'static str] {
+    let name = Path::new(shell_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match name.trim_start_matches('-') {
+        "bash" => &["--norc", "--noprofile"],
+        "zsh" => &["-f"],
+        "fish" => &["--no-config"],
+        _ => &[],
     }
+}
 
-    #[test]
-    fn test_write_to_nonexistent_session() {
-        let mut manager = TerminalManager::new();
-        let result = manager.write("nonexistent", b"test");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Session not found"));
+/// シェルの起動引数（追加の引数がなければログインシェルの `-l`）
+/// bashは1文字のオプションの後ろに長いオプションを置けないため、
+/// 追加の引数があれば `-l` は付けない（ログインシェルにする場合は引数に含める）
+/// safe_modeならrcファイルを読まないフラグを先頭に付ける
+fn shell_argv(shell_path: &str, shell_args: &[String], safe_mode: bool) -> Vec<String> {
+    let mut argv = Vec::with_capacity(shell_args.len() + 2);
+    if safe_mode {
+        argv.extend(
+            safe_mode_flags(shell_path)
+                .iter()
+                .map(|flag| flag.to_string()),
+        );
     }
-
-    #[test]
-    fn test_resize_nonexistent_session() {
-        let mut manager = TerminalManager::new();
-        let result = manager.resize("nonexistent", 80, 24);
-        assert!(result.is_err());
+    if shell_args.is_empty() {
+        argv.push("-l".to_string());
     }
+    argv.extend(shell_args.iter().cloned());
+    argv
+}
 
-    #[test]
-    fn test_kill_nonexistent_session() {
-        let mut manager = TerminalManager::new();
-        let result = manager.kill("nonexistent");
-        assert!(result.is_err());
+/// セッションに設定するTERM / COLORTERM
+pub const SESSION_TERM: &str = "xterm-256color";
+pub const SESSION_COLORTERM: &str = "truecolor";
+
+/// セッションのシェルに設定する環境変数（親プロセスの環境を上書きする）
+fn session_env(shell_path: &str) -> [(&'static str, &str); 3] {
+    [
+        ("TERM", SESSION_TERM),
+        ("COLORTERM", SESSION_COLORTERM),
+        ("SHELL", shell_path),
+    ]
+}
+
+/// 起動するシェルに渡る環境変数を、起動せずに計算する（venvが有効にならない原因の調査用）
+/// 親プロセスの環境 < TERM / COLORTERM / SHELL < extra_env（`SpawnOptions::env`）の順に上書きする。
+/// 値は伏せないため、トークンなどの秘密が含まれることがある
+pub fn effective_spawn_env(
+    process_env: impl IntoIterator<Item = (String, String)>,
+    shell_path: &str,
+    extra_env: &HashMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut env: BTreeMap<String, String> = process_env.into_iter().collect();
+    for (key, value) in session_env(shell_path) {
+        env.insert(key.to_string(), value.to_string());
     }
+    env.extend(extra_env.iter().map(|(k, v)| (k.clone(), v.clone())));
+    env
+}
 
-    #[test]
+/// PTYのslave（子プロセス側）を親で閉じるタイミング
+///
+/// - `Immediate`（既定）: spawn直後に閉じる。子プロセスが終了するとmasterの読み取りが
+///   EOFになり、終了をすぐ検出できる。一部の環境では子プロセスの最初の出力より前に
+///   閉じると、その出力を取りこぼしたり読み取りがEIOで終わったりすることがある
+/// - `AfterFirstRead`: 最初の読み取りが返るまで保持する。起動直後の出力は失われないが、
+///   何も出力せずに終了したシェルではslaveが開いたままになりEOFが届かない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlaveDrop {
+    Immediate,
+    AfterFirstRead,
+}
+
+impl SlaveDrop {
+    fn from_flag(defer: bool) -> Self {
+        if defer {
+            SlaveDrop::AfterFirstRead
+        } else {
+            SlaveDrop::Immediate
+        }
+    }
+
+    /// 方針に従ってslaveを閉じ、読み取りに使うreaderを返す
+    /// `AfterFirstRead` ではslaveをreaderに持たせ、最初の読み取りの後で閉じる
+    fn apply<S: Send + 'static>(
+        self,
+        slave: S,
+        reader: Box<dyn Read + Send>,
+    ) -> Box<dyn Read + Send> {
+        match self {
+            SlaveDrop::Immediate => {
+                drop(slave);
+                reader
+            }
+            SlaveDrop::AfterFirstRead => Box::new(SlaveHoldingReader {
+                reader,
+                slave: Some(slave),
+            }),
+        }
+    }
+}
+
+/// 最初の読み取りが返るまでslaveを保持するreader
+struct SlaveHoldingReader<S> {
+    reader: Box<dyn Read + Send>,
+    slave: Option<S>,
+}
+
+impl<S> Read for SlaveHoldingReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let result = self.reader.read(buf);
+        // 成否にかかわらず閉じる（エラーの再試行やEOFの検出を妨げない）
+        self.slave.take();
+        result
+    }
+}
+
+/// TERM / COLORTERM の組み合わせから、24bitカラーが使えると期待できるか判定する
+pub fn check_truecolor_support(term: &str, colorterm: Option<&str>) -> bool {
+    let colorterm = colorterm.unwrap_or_default().to_ascii_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return true;
+    }
+    let term = term.to_ascii_lowercase();
+    term.ends_with("-direct") || term.contains("truecolor") || term.contains("24bit")
+}
+
+/// 設定が24bitカラーを使うのにTERMが対応していない場合の警告
+pub fn truecolor_warning(
+    requested: bool,
+    term: &str,
+    colorterm: Option<&str>,
+) -> Option<events::TerminalColorWarning> {
+    if !requested || check_truecolor_support(term, colorterm) {
+        return None;
+    }
+    Some(events::TerminalColorWarning {
+        term: term.to_string(),
+        colorterm: colorterm.map(str::to_string),
+    })
+}
+
+/// PTYを開いてシェルを起動し、セッションと出力の読み取り口を返す
+fn open_session(spec: &SessionSpec) -> Result<(PtySession, Box<dyn Read + Send>), AppError> {
+    let pty_system = native_pty_system();
+
+    let size = PtySize {
+        rows: spec.rows,
+        cols: spec.cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+
+    let pair = pty_system
+        .openpty(size)
+        .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+    // シェルを検出してログインシェルとして起動
+    let shell_path = detect_shell(spec.shell.as_deref(), &spec.shell_fallbacks);
+    check_shell(&shell_path)?;
+    let mut cmd = CommandBuilder::new(&shell_path);
+    let argv = shell_argv(&shell_path, &spec.shell_args, spec.safe_mode);
+    if logging::enabled(LogLevel::Debug) {
+        eprintln!(
+            "Spawning shell: {} {:?} (cwd: {:?})",
+            shell_path, argv, spec.cwd
+        );
+    }
+    cmd.args(argv);
+
+    if let Some(ref dir) = spec.cwd {
+        cmd.cwd(dir);
+    }
+
+    for (key, value) in session_env(&shell_path) {
+        cmd.env(key, value);
+    }
+    for (key, value) in &spec.env {
+        cmd.env(key, value);
+    }
+
+    let mut child = pair.slave.spawn_command(cmd).map_err(|e| {
+        let kind = e.downcast_ref::<std::io::Error>().map(|io| io.kind());
+        spawn_error(&shell_path, kind, e.to_string())
+    })?;
+
+    // macOS: spawn後の短いスリープでレースコンディション回避
+    thread::sleep(Duration::from_millis(50));
+
+    let reader = match pair.master.try_clone_reader() {
+        Ok(reader) => reader,
+        Err(e) => {
+            // 出力を読めないシェルを残さない
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Failed to clone reader: {}", e).into());
+        }
+    };
+    // slaveを閉じる（親で保持し続けるとEOFが届かない）
+    let reader = SlaveDrop::from_flag(spec.defer_slave_drop).apply(pair.slave, reader);
+
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to take writer: {}", e))?;
+
+    let session = PtySession {
+        writer: spawn_writer_thread(writer),
+        size,
+        scrollback: Arc::new(Mutex::new(Scrollback::default())),
+        shell: shell_path,
+        shell_args: spec.shell_args.clone(),
+        cwd: spec.cwd.clone(),
+        title: Arc::new(Mutex::new(spec.title.clone())),
+        focus_reporting: Arc::new(AtomicBool::new(false)),
+        id: Arc::new(Mutex::new(String::new())),
+        replaced: Arc::new(AtomicBool::new(false)),
+        closing: false,
+        exited: Arc::new(ExitedFlag::default()),
+        hangup: Arc::new(AtomicBool::new(false)),
+        output_seq: Arc::new(AtomicU64::new(0)),
+        metrics: Arc::new(OutputMetrics::default()),
+        started_at: SystemTime::now(),
+        last_activity: Arc::new(Mutex::new(Instant::now())),
+        keep_alive: spec.keep_alive,
+        keepalive_interval: spec.keepalive_interval,
+        shell_integration: spec.shell_integration,
+        safe_mode: spec.safe_mode,
+        defer_slave_drop: spec.defer_slave_drop,
+        accent: spec.accent.clone(),
+        color_scheme: spec.color_scheme.clone(),
+        env: spec.env.clone(),
+        sink: None,
+        child: SharedChild::new(child),
+        exit: ExitWaiters::default(),
+        master: pair.master,
+    };
+    Ok((session, reader))
+}
+
+/// 1回だけ実行するコマンドの出力の上限
+const CAPTURE_OUTPUT_LIMIT_BYTES: usize = 1024 * 1024;
+/// 1回だけ実行するコマンドの実行時間の上限
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `capture_command` の結果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandCapture {
+    /// 終了コード（時間切れで強制終了した場合はNone）
+    pub exit_code: Option<u32>,
+    pub output: String,
+    /// 上限を超えた出力を切り捨てたか
+    pub truncated: bool,
+    pub timed_out: bool,
+}
+
+/// 最小限の環境で実行する場合に親プロセスから引き継ぐ環境変数
+const MINIMAL_ENV_ALLOWLIST: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "LANG", "LC_ALL", "LC_CTYPE", "TMPDIR",
+];
+
+/// コマンドをPTYで実行し、終了までの出力をまとめて返す（ストリーミングしない）
+/// ログインシェルを介さず、TERM=dumbと `env` を親プロセスの環境に追加する。
+/// minimal_envなら親プロセスの環境は `MINIMAL_ENV_ALLOWLIST` のものだけを引き継ぐ
+pub fn capture_command(
+    program: &str,
+    args: &[String],
+    cwd: Option<&str>,
+    env: &HashMap<String, String>,
+    minimal_env: bool,
+) -> Result<CommandCapture, AppError> {
+    capture_command_with_limits(
+        program,
+        args,
+        cwd,
+        env,
+        minimal_env,
+        CAPTURE_OUTPUT_LIMIT_BYTES,
+        CAPTURE_TIMEOUT,
+    )
+}
+
+fn capture_command_with_limits(
+    program: &str,
+    args: &[String],
+    cwd: Option<&str>,
+    env: &HashMap<String, String>,
+    minimal_env: bool,
+    output_limit: usize,
+    timeout: Duration,
+) -> Result<CommandCapture, AppError> {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+    check_shell(program)?;
+    let mut cmd = CommandBuilder::new(program);
+    cmd.args(args);
+    if let Some(dir) = cwd {
+        cmd.cwd(dir);
+    }
+    if minimal_env {
+        cmd.env_clear();
+        for key in MINIMAL_ENV_ALLOWLIST {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+    }
+    cmd.env("TERM", "dumb");
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+
+    let mut child = pair.slave.spawn_command(cmd).map_err(|e| {
+        let kind = e.downcast_ref::<std::io::Error>().map(|io| io.kind());
+        spawn_error(program, kind, e.to_string())
+    })?;
+    drop(pair.slave);
+
+    let mut reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to clone reader: {}", e))?;
+
+    // 上限までを保持し、超えた分は読み捨てる（子プロセスが書き込みで詰まらないように）
+    let (output_tx, output_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut truncated = false;
+        let mut buffer = [0u8; 4096];
+        while let Ok(n) = reader.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+            let room = output_limit.saturating_sub(output.len());
+            truncated |= n > room;
+            output.extend_from_slice(&buffer[..n.min(room)]);
+        }
+        let _ = output_tx.send((output, truncated));
+    });
+
+    let deadline = Instant::now() + timeout;
+    let mut timed_out = false;
+    let exit_code = loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            break Some(status.exit_code());
+        }
+        if Instant::now() >= deadline {
+            timed_out = true;
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    // masterを閉じて読み取りスレッドを終わらせる
+    drop(pair.master);
+    let (output, truncated) = output_rx
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap_or_default();
+
+    Ok(CommandCapture {
+        exit_code,
+        output: String::from_utf8_lossy(&output).to_string(),
+        truncated,
+        timed_out,
+    })
+}
+
+/// 全PTYセッションを管理するマネージャー
+pub struct TerminalManager {
+    sessions: HashMap<String, PtySession>,
+    /// 読み取りエラーで終了したセッションIDの通知先（Noneなら通知せずに残す）
+    failed_tx: Option<Sender<String>>,
+}
+
+impl Default for TerminalManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PtySession {
+    /// シェルのプロセスID
+    fn pid(&self) -> Option<u32> {
+        self.child.process_id()
+    }
+
+    /// シェルが動作中か
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// 起動時刻（UNIXエポックからのミリ秒）
+    fn started_at_millis(&self) -> u64 {
+        self.started_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    /// 入出力があったことを記録
+    fn touch(&self) {
+        if let Ok(mut last_activity) = self.last_activity.lock() {
+            *last_activity = Instant::now();
+        }
+    }
+
+    /// `now` 時点でidle_limit以上入出力がなく、自動終了の対象か
+    fn is_idle(&self, now: Instant, idle_limit: Duration) -> bool {
+        if self.keep_alive {
+            return false;
+        }
+        self.last_activity
+            .lock()
+            .map(|last| now.saturating_duration_since(*last) >= idle_limit)
+            .unwrap_or(false)
+    }
+
+    /// 同じ設定で起動し直すための情報
+    fn spec(&self) -> SessionSpec {
+        SessionSpec {
+            shell: Some(self.shell.clone()),
+            shell_args: self.shell_args.clone(),
+            shell_fallbacks: Vec::new(),
+            cwd: self.cwd.clone(),
+            cols: self.size.cols,
+            rows: self.size.rows,
+            title: self.title(),
+            keep_alive: self.keep_alive,
+            keepalive_interval: self.keepalive_interval,
+            shell_integration: self.shell_integration,
+            safe_mode: self.safe_mode,
+            defer_slave_drop: self.defer_slave_drop,
+            accent: self.accent.clone(),
+            color_scheme: self.color_scheme.clone(),
+            env: self.env.clone(),
+        }
+    }
+
+    fn title(&self) -> Option<String> {
+        self.title.lock().ok().and_then(|title| title.clone())
+    }
+
+    /// 読み取りスレッドと共有する状態
+    fn reader_state(&self) -> ReaderState {
+        ReaderState {
+            id: Arc::clone(&self.id),
+            scrollback: Arc::clone(&self.scrollback),
+            title: Arc::clone(&self.title),
+            last_activity: Arc::clone(&self.last_activity),
+            focus_reporting: Arc::clone(&self.focus_reporting),
+            replaced: Arc::clone(&self.replaced),
+            exited: Arc::clone(&self.exited),
+            hangup: Arc::clone(&self.hangup),
+            output_seq: Arc::clone(&self.output_seq),
+            metrics: Arc::clone(&self.metrics),
+            child: Some(self.child.clone()),
+            exit: self.exit.clone(),
+        }
+    }
+
+    fn info(&mut self) -> TerminalInfo {
+        TerminalInfo {
+            pid: self.pid(),
+            shell: self.shell.clone(),
+            cols: self.size.cols,
+            rows: self.size.rows,
+            cwd: self.cwd.clone(),
+            title: self.title(),
+            started_at: self.started_at_millis(),
+            alive: self.is_alive(),
+            hangup: self.hangup.load(Ordering::Relaxed),
+            accent: self.accent.clone(),
+            color_scheme: self.color_scheme.clone(),
+        }
+    }
+}
+
+impl TerminalManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            failed_tx: None,
+        }
+    }
+
+    /// 読み取りエラーで終了したセッションを破棄する
+    /// 同じIDで起動し直したセッションは、出力が閉じていない限り残す
+    fn remove_failed_session(&mut self, session_id: &str) {
+        let exited = self
+            .sessions
+            .get(session_id)
+            .is_some_and(|session| session.exited.is_set());
+        if exited {
+            if let Some(session) = self.sessions.remove(session_id) {
+                let _ = session.child.kill();
+                let _ = session.child.wait();
+            }
+        }
+    }
+
+    /// 新しいPTYセッションを生成
+    /// startup_timeout_ms内に出力がなければ `pty_startup_slow` を通知する（セッションは維持）
+    /// keep_aliveのセッションはアイドル時の自動終了の対象外
+    /// keepalive_secsを指定するとその間隔でNULを書き込む（SSHの無通信切断対策）
+    /// shell_argsを指定するとログインシェルの `-l` の代わりに使う
+    /// shellがNoneならshell_fallbacksから実行できるものを選ぶ（選んだシェルは `info` の `shell`）
+    /// shell_integrationならbash / zshの起動直後にOSC 133を出力する設定を書き込む
+    /// safe_modeならrcファイルを読まないフラグを付けて起動する（未知のシェルはそのまま）
+    /// defer_slave_dropならslaveを最初の読み取りまで閉じない（`SlaveDrop` を参照）
+    pub fn spawn(
+        &mut self,
+        session_id: String,
+        options: SpawnOptions,
+        app_handle: AppHandle,
+    ) -> Result<(), AppError> {
+        let SpawnOptions {
+            cwd,
+            shell,
+            shell_args,
+            shell_fallbacks,
+            cols,
+            rows,
+            startup_timeout_ms,
+            keep_alive,
+            keepalive_secs,
+            shell_integration,
+            safe_mode,
+            defer_slave_drop,
+            accent,
+            env,
+        } = options;
+        let accent = accent.map(|accent| validate_accent(&accent)).transpose()?;
+        // 既に同じセッションが存在する場合はスキップ（React StrictMode対策）
+        if self.sessions.contains_key(&session_id) {
+            return Ok(());
+        }
+
+        let spec = SessionSpec {
+            shell,
+            shell_args,
+            shell_fallbacks,
+            cwd,
+            cols,
+            rows,
+            title: None,
+            keep_alive,
+            keepalive_interval: keepalive_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            shell_integration,
+            safe_mode,
+            defer_slave_drop,
+            accent,
+            color_scheme: None,
+            env,
+        };
+
+        // rcファイルで止まっているシェルの診断用
+        let mut first_output: Option<Sender<()>> = None;
+        let mut watchdog = None;
+        if let Some(timeout_ms) = startup_timeout_ms {
+            let (tx, rx) = mpsc::channel();
+            first_output = Some(tx);
+            watchdog = Some((Duration::from_millis(timeout_ms), rx));
+        }
+
+        self.start_session(
+            session_id.clone(),
+            &spec,
+            first_output,
+            OutputSink::Events(app_handle.clone()),
+        )?;
+
+        if let Some((timeout, rx)) = watchdog {
+            let id = Arc::clone(&self.sessions[&session_id].id);
+            spawn_startup_watchdog(timeout, rx, id, move |session_id| {
+                let _ = app_handle.emit(events::PTY_STARTUP_SLOW, events::SessionId(session_id));
+            });
+        }
+        Ok(())
+    }
+
+    /// 出力をイベントではなくチャネルに送るセッションを起動
+    /// 出力のバイト列は読み取った単位でそのまま送られる
+    pub fn spawn_with_sink(
+        &mut self,
+        session_id: String,
+        cwd: Option<String>,
+        shell: Option<String>,
+        cols: u16,
+        rows: u16,
+        sink: Sender<Vec<u8>>,
+    ) -> Result<(), AppError> {
+        if self.sessions.contains_key(&session_id) {
+            return Ok(());
+        }
+
+        let spec = SessionSpec {
+            shell,
+            shell_args: Vec::new(),
+            shell_fallbacks: Vec::new(),
+            cwd,
+            cols,
+            rows,
+            title: None,
+            keep_alive: false,
+            keepalive_interval: None,
+            shell_integration: false,
+            safe_mode: false,
+            defer_slave_drop: false,
+            accent: None,
+            color_scheme: None,
+            env: HashMap::new(),
+        };
+        self.start_session(session_id, &spec, None, OutputSink::Channel(sink))
+    }
+
+    /// セッションを起動して登録し、出力読み取りスレッドを開始
+    fn start_session(
+        &mut self,
+        session_id: String,
+        spec: &SessionSpec,
+        first_output: Option<Sender<()>>,
+        sink: OutputSink,
+    ) -> Result<(), AppError> {
+        let (mut session, reader) = open_session(spec)?;
+        session.sink = Some(sink.clone());
+        session.id = Arc::new(Mutex::new(session_id.clone()));
+
+        if let Some(interval) = spec.keepalive_interval {
+            let writer = session.writer.clone();
+            let exited = Arc::clone(&session.exited);
+            let replaced = Arc::clone(&session.replaced);
+            spawn_keepalive_thread(interval, move || {
+                if exited.is_set() || replaced.load(Ordering::Relaxed) {
+                    return false;
+                }
+                // キューが詰まっているときは次の間隔まで待つ
+                !matches!(
+                    writer.try_send(KEEPALIVE_BYTES.to_vec()),
+                    Err(TrySendError::Disconnected(_))
+                )
+            });
+        }
+
+        if spec.shell_integration {
+            if let Some(kind) = ShellKind::detect(&session.shell) {
+                let _ = session
+                    .writer
+                    .try_send(kind.integration_script().as_bytes().to_vec());
+            }
+        }
+
+        // 出力読み取りスレッド（即時送信）
+        let failed_tx = self.failed_tx.clone();
+        spawn_reader_thread(
+            reader,
+            session.reader_state(),
+            first_output,
+            move |sid, output| {
+                if let (PtyOutput::Exit { error: true, .. }, Some(failed_tx)) =
+                    (&output, &failed_tx)
+                {
+                    let _ = failed_tx.send(sid.to_string());
+                }
+                sink.send(sid, output)
+            },
+        );
+        if logging::enabled(LogLevel::Info) {
+            eprintln!(
+                "Started PTY session {} (shell: {}, pid: {:?})",
+                session_id,
+                session.shell,
+                session.pid()
+            );
+        }
+        self.sessions.insert(session_id, session);
+
+        Ok(())
+    }
+
+    /// 全セッションを同じシェル・作業ディレクトリ・サイズ・タイトルで起動し直す
+    /// セッションIDは維持し、それぞれ `pty_restarted` を通知する
+    /// 起動に失敗したセッションがあっても残りのセッションは起動し直す
+    pub fn restart_all(&mut self, app_handle: AppHandle) -> RestartAllResult {
+        let sink = OutputSink::Events(app_handle.clone());
+        self.restart_all_with(sink, |session_id| {
+            let _ = app_handle.emit(events::PTY_RESTARTED, events::SessionId(session_id));
+        })
+    }
+
+    /// `restart_all` の本体
+    /// 出力の送り先が未設定のセッションはdefault_sinkに送り、起動し直すたびにon_restartedを呼ぶ
+    fn restart_all_with<F>(
+        &mut self,
+        default_sink: OutputSink,
+        mut on_restarted: F,
+    ) -> RestartAllResult
+    where
+        F: FnMut(&str),
+    {
+        let mut session_ids: Vec<String> = self.sessions.keys().cloned().collect();
+        session_ids.sort();
+
+        let mut result = RestartAllResult::default();
+        for session_id in session_ids {
+            let sink = self
+                .sessions
+                .get(&session_id)
+                .and_then(|session| session.sink.clone())
+                .unwrap_or_else(|| default_sink.clone());
+            let Some(spec) = self.retire_session(&session_id) else {
+                continue;
+            };
+            // 削除済みなので重複起動のスキップ（StrictMode対策）には当たらない
+            match self.start_session(session_id.clone(), &spec, None, sink) {
+                Ok(()) => {
+                    on_restarted(&session_id);
+                    result.restarted.push(session_id);
+                }
+                Err(error) => {
+                    if logging::enabled(LogLevel::Warn) {
+                        eprintln!("Failed to restart PTY session {}: {}", session_id, error);
+                    }
+                    result.failed.push(RestartFailure { session_id, error });
+                }
+            }
+        }
+
+        result
+    }
+
+    /// 再起動のためにセッションを終了し、起動し直すための情報を返す
+    /// 古いプロセスの終了は `pty_exit` として通知しない
+    fn retire_session(&mut self, session_id: &str) -> Option<SessionSpec> {
+        let session = self.sessions.remove(session_id)?;
+        session.replaced.store(true, Ordering::Relaxed);
+        let spec = session.spec();
+        let _ = session.child.kill();
+        Some(spec)
+    }
+
+    /// PTYにデータを書き込む
+    /// 書き込みキューが満杯の場合は待たずに `AppError::WriteBackpressure` を返す
+    pub fn write(&mut self, session_id: &str, data: &[u8]) -> Result<(), AppError> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if session.closing {
+            return Err(format!("Session is closing: {}", session_id).into());
+        }
+
+        match session.writer.try_send(data.to_vec()) {
+            Ok(()) => {
+                session.touch();
+                Ok(())
+            }
+            Err(TrySendError::Full(_)) => Err(AppError::WriteBackpressure {
+                session_id: session_id.to_string(),
+            }),
+            Err(TrySendError::Disconnected(_)) => {
+                Err(format!("Failed to write: writer closed ({})", session_id).into())
+            }
+        }
+    }
+
+    /// 貼り付けを書き込む
+    /// 上限を超える場合は一部だけ送ることはせず、何も書き込まずに `AppError::PasteTooLarge` を返す
+    pub fn write_paste(
+        &mut self,
+        session_id: &str,
+        data: &[u8],
+        max_paste_bytes: usize,
+    ) -> Result<(), AppError> {
+        if data.len() > max_paste_bytes {
+            return Err(AppError::PasteTooLarge {
+                session_id: session_id.to_string(),
+                size: data.len(),
+                limit: max_paste_bytes,
+            });
+        }
+        self.write(session_id, data)
+    }
+
+    /// フォアグラウンドのプロセスに割り込み（Ctrl-C）を送る
+    /// 端末の行規律がフォアグラウンドのプロセスグループへSIGINTを送るため、
+    /// シェル自体ではなく実行中のジョブが中断される
+    pub fn interrupt(&mut self, session_id: &str) -> Result<(), AppError> {
+        self.write(session_id, INTERRUPT)
+    }
+
+    /// フォアグラウンドのジョブを強制終了し、シェルは残す
+    /// Unixではmasterのフォアグラウンドプロセスグループ（tcgetpgrp）へSIGKILLを送る。
+    /// シェル自身がフォアグラウンドなら何もしない。取得できない環境では割り込み（Ctrl-C）を送る
+    pub fn kill_foreground(&mut self, session_id: &str) -> Result<(), AppError> {
+        #[cfg(unix)]
+        {
+            let session = self
+                .sessions
+                .get(session_id)
+                .ok_or_else(|| format!("Session not found: {}", session_id))?;
+            if let Some(pgrp) = session.master.process_group_leader() {
+                if pgrp <= 0 || session.pid() == Some(pgrp as u32) {
+                    return Ok(());
+                }
+                // SAFETY: 負のPIDはプロセスグループ全体へのシグナル送信
+                if unsafe { libc::kill(-pgrp, libc::SIGKILL) } != 0 {
+                    let e = std::io::Error::last_os_error();
+                    return Err(format!("Failed to kill foreground job: {}", e).into());
+                }
+                return Ok(());
+            }
+        }
+        self.interrupt(session_id)
+    }
+
+    /// ペインのフォーカス変化をPTYへ伝える
+    /// 実行中のアプリがフォーカスレポートを有効にしていない場合は何も送らない
+    /// （シェルの入力に `^[[I` が混ざるのを防ぐ）
+    pub fn focus(&mut self, session_id: &str, focused: bool) -> Result<(), AppError> {
+        let enabled = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?
+            .focus_reporting
+            .load(Ordering::Relaxed);
+        if !enabled {
+            return Ok(());
+        }
+        self.write(session_id, if focused { FOCUS_IN } else { FOCUS_OUT })
+    }
+
+    /// スクロールバックの内容を取得
+    pub fn scrollback(&self, session_id: &str) -> Result<Vec<u8>, String> {
+        let session = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        let scrollback = session.scrollback.lock().map_err(|e| e.to_string())?;
+        Ok(scrollback.to_bytes())
+    }
+
+    /// スクロールバックをgzipで圧縮したもの
+    pub fn scrollback_gz(&self, session_id: &str) -> Result<Vec<u8>, String> {
+        let bytes = self.scrollback(session_id)?;
+        gzip(&bytes).map_err(|e| format!("Failed to compress scrollback: {}", e))
+    }
+
+    /// 出力量の累計と直近の読み取り速度
+    pub fn metrics(&self, session_id: &str) -> Result<TerminalMetrics, String> {
+        let session = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        Ok(session.metrics.snapshot(Instant::now()))
+    }
+
+    /// 読み取りスレッド以外から `pty_data` を送る際の連番を払い出す
+    pub fn next_output_seq(&self, session_id: &str) -> Option<u64> {
+        self.sessions
+            .get(session_id)
+            .map(|session| session.output_seq.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// スクロールバックを破棄
+    pub fn clear_scrollback(&mut self, session_id: &str) -> Result<(), String> {
+        let session = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        session
+            .scrollback
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clear();
+        Ok(())
+    }
+
+    /// PTYのサイズを変更
+    /// 現在と同じサイズなら何もせずNoneを返す（フォント変更などで余計なSIGWINCHを送らない）
+    pub fn resize(
+        &mut self,
+        session_id: &str,
+        cols: u16,
+        rows: u16,
+    ) -> Result<Option<PtyResized>, AppError> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if (session.size.cols, session.size.rows) == (cols, rows) {
+            return Ok(None);
+        }
+
+        let size = PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        session
+            .master
+            .resize(size)
+            .map_err(|e| AppError::Other(format!("Failed to resize pty: {}", e)))?;
+        session.size = size;
+
+        Ok(Some(PtyResized {
+            session_id: session_id.to_string(),
+            cols,
+            rows,
+        }))
+    }
+
+    /// セッションIDの一覧（ID順）
+    pub fn session_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.sessions.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// 全セッションの情報（セッションID順）
+    pub fn list(&mut self) -> Vec<TerminalListEntry> {
+        let mut entries: Vec<TerminalListEntry> = self
+            .sessions
+            .iter_mut()
+            .map(|(session_id, session)| TerminalListEntry {
+                session_id: session_id.clone(),
+                info: session.info(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        entries
+    }
+
+    /// 全セッションにカラースキームを記録し、記録したセッションID（ID順）を返す
+    /// 記録したスキームは `info` で返し、再起動時にも引き継ぐ
+    pub fn set_all_color_scheme(&mut self, scheme: &ColorScheme) -> Vec<String> {
+        for session in self.sessions.values_mut() {
+            session.color_scheme = Some(scheme.clone());
+        }
+        self.session_ids()
+    }
+
+    /// セッションの情報をまとめて取得
+    pub fn info(&mut self, session_id: &str) -> Result<TerminalInfo, String> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        Ok(session.info())
+    }
+
+    /// idle_limit以上入出力のないセッション（keep_aliveを除く）
+    fn idle_sessions(&self, now: Instant, idle_limit: Duration) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.is_idle(now, idle_limit))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// 終了処理を開始し、以後の入力を拒否する
+    /// 出力が閉じたかどうかのフラグを返す（残りの出力の読み取り待ちに使う）
+    pub fn begin_close(&mut self, session_id: &str) -> Result<Arc<ExitedFlag>, String> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        session.closing = true;
+        Ok(Arc::clone(&session.exited))
+    }
+
+    /// セッションを終了させずにIDを付け替える（以後のイベントは新しいIDで通知される）
+    pub fn rename(&mut self, old_id: &str, new_id: &str) -> Result<(), String> {
+        if old_id == new_id {
+            return if self.sessions.contains_key(old_id) {
+                Ok(())
+            } else {
+                Err(format!("Session not found: {}", old_id))
+            };
+        }
+        if self.sessions.contains_key(new_id) {
+            return Err(format!("Session already exists: {}", new_id));
+        }
+        let session = self
+            .sessions
+            .remove(old_id)
+            .ok_or_else(|| format!("Session not found: {}", old_id))?;
+        if let Ok(mut id) = session.id.lock() {
+            *id = new_id.to_string();
+        }
+        self.sessions.insert(new_id.to_string(), session);
+        Ok(())
+    }
+
+    /// シェルの終了コードを受け取る受信側を登録する（終了済みならすぐ受け取れる）
+    /// 読み取りスレッドが終了を記録するため、出力を読んでいないセッションでは届かない
+    pub fn wait_for_exit(&self, session_id: &str) -> Result<oneshot::Receiver<ExitResult>, String> {
+        let session = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        Ok(session.exit.subscribe())
+    }
+
+    /// セッションを終了
+    pub fn kill(&mut self, session_id: &str) -> Result<(), String> {
+        self.sessions
+            .remove(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if logging::enabled(LogLevel::Info) {
+            eprintln!("Closed PTY session {}", session_id);
+        }
+        Ok(())
+    }
+}
+
+/// グローバルなTerminalManagerへのアクセス用
+pub type SharedTerminalManager = Arc<Mutex<TerminalManager>>;
+
+/// 出力が閉じたことの記録（読み取りスレッドが設定し、待っている側を起こす）
+#[derive(Default)]
+pub struct ExitedFlag {
+    exited: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl ExitedFlag {
+    fn set(&self) {
+        if let Ok(mut exited) = self.exited.lock() {
+            *exited = true;
+        }
+        self.changed.notify_all();
+    }
+
+    fn is_set(&self) -> bool {
+        self.exited.lock().map(|exited| *exited).unwrap_or(false)
+    }
+
+    /// 出力が閉じるか、timeoutが経過するまで待つ
+    fn wait(&self, timeout: Duration) {
+        if let Ok(exited) = self.exited.lock() {
+            let _ = self
+                .changed
+                .wait_timeout_while(exited, timeout, |exited| !*exited);
+        }
+    }
+}
+
+/// 終了前の残り出力の読み取りを待つ
+/// 出力が閉じた時点で戻り、閉じなければdrainが経過するまで待つ（その間も読み取りスレッドが通知を続ける）
+pub fn wait_for_drain(exited: &ExitedFlag, drain: Duration) {
+    exited.wait(drain);
+}
+
+/// `TerminalManager::wait_for_exit` の受信側で終了を待つ（timeoutを過ぎたらエラー）
+pub async fn await_exit(
+    session_id: &str,
+    rx: oneshot::Receiver<ExitResult>,
+    timeout: Duration,
+) -> Result<u32, String> {
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(format!("Exit waiter stopped: {}", session_id)),
+        Err(_) => Err(format!("Timed out waiting for exit: {}", session_id)),
+    }
+}
+
+/// アイドルセッションを確認する最大間隔
+const IDLE_REAPER_INTERVAL: Duration = Duration::from_secs(10);
+
+/// idle_limit以上入出力のないセッションを定期的に終了するスレッドを起動
+/// 終了前に `pty_idle_killed` を通知する
+pub fn start_idle_reaper(
+    manager: SharedTerminalManager,
+    idle_limit: Duration,
+    app_handle: AppHandle,
+) {
+    let interval = IDLE_REAPER_INTERVAL
+        .min(idle_limit / 2)
+        .max(Duration::from_secs(1));
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let Ok(mut inner) = manager.lock() else {
+            return;
+        };
+        for session_id in inner.idle_sessions(Instant::now(), idle_limit) {
+            let _ = app_handle.emit(events::PTY_IDLE_KILLED, events::SessionId(&session_id));
+            let _ = inner.kill(&session_id);
+        }
+    });
+}
+
+/// 読み取りエラーで終了したセッションを、通知を受けた時点で破棄するスレッドを起動
+/// （マネージャが破棄されると通知元がなくなり終了する）
+fn spawn_failed_session_remover(
+    manager: Weak<Mutex<TerminalManager>>,
+    failed_rx: Receiver<String>,
+) {
+    thread::spawn(move || {
+        for session_id in failed_rx {
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            let Ok(mut inner) = manager.lock() else {
+                return;
+            };
+            inner.remove_failed_session(&session_id);
+        }
+    });
+}
+
+pub fn create_terminal_manager() -> SharedTerminalManager {
+    let (failed_tx, failed_rx) = mpsc::channel();
+    let mut inner = TerminalManager::new();
+    inner.failed_tx = Some(failed_tx);
+    let manager = Arc::new(Mutex::new(inner));
+    spawn_failed_session_remover(Arc::downgrade(&manager), failed_rx);
+    manager
+}
+
+/// 他のモジュールのテストからも使うテスト用の処理（シェルスクリプトを使うためunixのみ）
+#[cfg(all(test, unix))]
+pub(crate) mod test_support {
+    use super::TerminalManager;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// scriptを実行権限つきで一時ディレクトリに書き出し、シェルとして使うパスを返す
+    /// （TempDirをdropするまで有効）
+    pub(crate) fn fake_shell(script: &str) -> (tempfile::TempDir, String) {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fake-shell");
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (dir, path.to_string_lossy().to_string())
+    }
+
+    /// セッションのスクロールバックにtextが届くまで待つ（5秒で失敗）
+    pub(crate) fn wait_for_scrollback(manager: &TerminalManager, session_id: &str, text: &str) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !String::from_utf8_lossy(&manager.scrollback(session_id).unwrap()).contains(text) {
+            assert!(Instant::now() < deadline, "{:?} did not arrive", text);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use test_support::fake_shell;
+
+    #[test]
+    fn test_terminal_manager_creation() {
+        let manager = TerminalManager::new();
+        assert!(manager.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_write_to_nonexistent_session() {
+        let mut manager = TerminalManager::new();
+        let result = manager.write("nonexistent", b"test");
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Session not found"));
+    }
+
+    #[test]
+    fn test_resize_nonexistent_session() {
+        let mut manager = TerminalManager::new();
+        let err = manager.resize("nonexistent", 80, 24).unwrap_err();
+        assert!(err.to_string().contains("Session not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_resize_applies_size() {
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "test");
+
+        let resized = manager.resize("test", 132, 50).unwrap();
+        assert_eq!(
+            resized,
+            Some(PtyResized {
+                session_id: "test".to_string(),
+                cols: 132,
+                rows: 50,
+            })
+        );
+        let applied = manager.sessions["test"].master.get_size().unwrap();
+        assert_eq!((applied.cols, applied.rows), (132, 50));
+        assert_eq!(manager.info("test").unwrap().cols, 132);
+    }
+
+    #[test]
+    fn test_resize_same_size_is_noop() {
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "test");
+
+        // 同じサイズでは `pty_resized` を通知しない
+        assert_eq!(manager.resize("test", 80, 24).unwrap(), None);
+        assert!(manager.resize("test", 100, 24).unwrap().is_some());
+        assert_eq!(manager.resize("test", 100, 24).unwrap(), None);
+        // 存在しないセッションは同じサイズでもエラー
+        assert!(manager.resize("nonexistent", 80, 24).is_err());
+    }
+
+    #[test]
+    fn test_kill_nonexistent_session() {
+        let mut manager = TerminalManager::new();
+        let result = manager.kill("nonexistent");
+        assert!(result.is_err());
+    }
+
+    /// 書き込まれたバイト列を記録するWriter
+    #[derive(Clone, Default)]
+    struct RecordingWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl RecordingWriter {
+        /// 書き込みスレッドが `len` バイト以上書き込むまで待って内容を返す
+        fn wait_for(&self, len: usize) -> Vec<u8> {
+            for _ in 0..100 {
+                let written = self.0.lock().unwrap().clone();
+                if written.len() >= len {
+                    return written;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    /// 書き込みが完了しないWriter（読み手が詰まったPTYを模擬）
+    struct StuckWriter(mpsc::Receiver<()>);
+
+    impl Write for StuckWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            // 送信側がdropされるまでブロック
+            let _ = self.0.recv();
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// テスト用セッションを登録し、書き込み内容の記録先を返す
+    fn insert_recording_session(
+        manager: &mut TerminalManager,
+        session_id: &str,
+    ) -> RecordingWriter {
+        let recorder = RecordingWriter::default();
+        insert_test_session(manager, session_id, Box::new(recorder.clone()));
+        recorder
+    }
+
+    /// 任意のWriterでテスト用セッションを登録
+    fn insert_test_session(
+        manager: &mut TerminalManager,
+        session_id: &str,
+        writer: Box<dyn Write + Send>,
+    ) {
+        insert_command_session(manager, session_id, writer, CommandBuilder::new("true"));
+    }
+
+    /// 指定コマンドを実行するテスト用セッションを登録
+    fn insert_command_session(
+        manager: &mut TerminalManager,
+        session_id: &str,
+        writer: Box<dyn Write + Send>,
+        command: CommandBuilder,
+    ) {
+        let size = PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let pair = native_pty_system().openpty(size).unwrap();
+        let child = pair.slave.spawn_command(command).unwrap();
+        manager.sessions.insert(
+            session_id.to_string(),
+            PtySession {
+                writer: spawn_writer_thread(writer),
+                size,
+                scrollback: Arc::new(Mutex::new(Scrollback::default())),
+                shell: "true".to_string(),
+                shell_args: Vec::new(),
+                cwd: None,
+                title: Arc::new(Mutex::new(None)),
+                focus_reporting: Arc::new(AtomicBool::new(false)),
+                id: Arc::new(Mutex::new(session_id.to_string())),
+                replaced: Arc::new(AtomicBool::new(false)),
+                closing: false,
+                exited: Arc::new(ExitedFlag::default()),
+                hangup: Arc::new(AtomicBool::new(false)),
+                output_seq: Arc::new(AtomicU64::new(0)),
+                metrics: Arc::new(OutputMetrics::default()),
+                started_at: SystemTime::now(),
+                last_activity: Arc::new(Mutex::new(Instant::now())),
+                keep_alive: false,
+                keepalive_interval: None,
+                shell_integration: false,
+                safe_mode: false,
+                defer_slave_drop: false,
+                accent: None,
+                color_scheme: None,
+                env: HashMap::new(),
+                sink: None,
+                child: SharedChild::new(child),
+                exit: ExitWaiters::default(),
+                master: pair.master,
+            },
+        );
+    }
+
+    /// 指定コマンドをPTYで起動し、最初の出力で通知するウォッチドッグの結果を返す
+    fn run_startup_watchdog(script: &str, timeout: Duration) -> bool {
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 80,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .unwrap();
+        let mut cmd = CommandBuilder::new("sh");
+        cmd.args(["-c", script]);
+        let mut child = pair.slave.spawn_command(cmd).unwrap();
+        drop(pair.slave);
+
+        let (tx, rx) = mpsc::channel();
+        let mut reader = pair.master.try_clone_reader().unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 64];
+            if let Ok(n) = reader.read(&mut buffer) {
+                if n > 0 {
+                    let _ = tx.send(());
+                }
+            }
+        });
+
+        let (slow_tx, slow_rx) = mpsc::channel();
+        let id = Arc::new(Mutex::new("test".to_string()));
+        spawn_startup_watchdog(timeout, rx, id, move |_| {
+            let _ = slow_tx.send(());
+        });
+        let fired = slow_rx
+            .recv_timeout(timeout + Duration::from_secs(2))
+            .is_ok();
+        let _ = child.kill();
+        fired
+    }
+
+    #[test]
+    fn test_startup_watchdog_reports_renamed_id() {
+        let (_first_tx, first_rx) = mpsc::channel();
+        let id = Arc::new(Mutex::new("old".to_string()));
+        let (slow_tx, slow_rx) = mpsc::channel();
+        spawn_startup_watchdog(
+            Duration::from_millis(200),
+            first_rx,
+            Arc::clone(&id),
+            move |session_id| {
+                let _ = slow_tx.send(session_id.to_string());
+            },
+        );
+
+        // 監視中にrenameされた場合は新しいIDで通知する
+        *id.lock().unwrap() = "new".to_string();
+        assert_eq!(
+            slow_rx.recv_timeout(Duration::from_secs(5)),
+            Ok("new".to_string())
+        );
+    }
+
+    #[test]
+    fn test_startup_watchdog_fires_for_slow_shell() {
+        assert!(run_startup_watchdog(
+            "sleep 2; echo ready",
+            Duration::from_millis(200)
+        ));
+    }
+
+    #[test]
+    fn test_startup_watchdog_quiet_when_output_arrives() {
+        assert!(!run_startup_watchdog(
+            "echo ready; sleep 2",
+            Duration::from_millis(1000)
+        ));
+    }
+
+    #[test]
+    fn test_terminal_info() {
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "test");
+        let session = manager.sessions.get_mut("test").unwrap();
+        session.cwd = Some("/tmp".to_string());
+        *session.title.lock().unwrap() = Some("vim".to_string());
+
+        let info = manager.info("test").unwrap();
+        assert!(info.pid.is_some());
+        assert_eq!(info.shell, "true");
+        assert_eq!((info.cols, info.rows), (80, 24));
+        assert_eq!(info.cwd, Some("/tmp".to_string()));
+        assert_eq!(info.title, Some("vim".to_string()));
+        assert!(info.started_at > 0);
+
+        // `true` はすぐに終了する
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while manager.info("test").unwrap().alive && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!manager.info("test").unwrap().alive);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_restart_all_continues_after_failure() {
+        let (_dir, script) = fake_shell("#!/bin/sh\nsleep 30\n");
+        let mut manager = TerminalManager::new();
+        for session_id in ["a", "b", "c"] {
+            let (session, _reader) = open_session(&shell_spec(&script)).unwrap();
+            manager.sessions.insert(session_id.to_string(), session);
+        }
+        // bだけ起動し直せないシェルにする
+        manager.sessions.get_mut("b").unwrap().shell = "/nonexistent/bin/sh".to_string();
+        let old_pid = manager.sessions["c"].pid();
+
+        let (tx, _rx) = mpsc::channel();
+        let mut notified = Vec::new();
+        let result = manager.restart_all_with(OutputSink::Channel(tx), |session_id| {
+            notified.push(session_id.to_string())
+        });
+
+        assert_eq!(result.restarted, ["a", "c"]);
+        assert_eq!(
+            result.failed,
+            [RestartFailure {
+                session_id: "b".to_string(),
+                error: AppError::ShellNotFound {
+                    path: "/nonexistent/bin/sh".to_string()
+                },
+            }]
+        );
+        assert_eq!(notified, ["a", "c"]);
+        // 失敗したセッションは破棄され、後続のセッションも起動し直されている
+        assert!(!manager.sessions.contains_key("b"));
+        assert_ne!(manager.sessions["c"].pid(), old_pid);
+        manager.kill("a").unwrap();
+        manager.kill("c").unwrap();
+    }
+
+    #[test]
+    fn test_restart_preserves_metadata() {
+        let mut manager = TerminalManager::new();
+        let (session, _reader) = open_session(&SessionSpec {
+            shell: Some("/bin/sh".to_string()),
+            shell_args: vec!["-i".to_string()],
+            shell_fallbacks: Vec::new(),
+            cwd: Some("/tmp".to_string()),
+            cols: 120,
+            rows: 40,
+            title: None,
+            keep_alive: true,
+            keepalive_interval: Some(Duration::from_secs(30)),
+            shell_integration: false,
+            safe_mode: false,
+            defer_slave_drop: false,
+            accent: None,
+            color_scheme: None,
+            env: HashMap::from([("ORTHRUS_TEST".to_string(), "1".to_string())]),
+        })
+        .unwrap();
+        manager.sessions.insert("test".to_string(), session);
+        *manager.sessions["test"].title.lock().unwrap() = Some("build".to_string());
+        let before = manager.info("test").unwrap();
+
+        let spec = manager.retire_session("test").unwrap();
+        assert!(!manager.sessions.contains_key("test"));
+        let (session, _reader) = open_session(&spec).unwrap();
+        manager.sessions.insert("test".to_string(), session);
+
+        let after = manager.info("test").unwrap();
+        assert_eq!(after.shell, before.shell);
+        assert_eq!(after.cwd, Some("/tmp".to_string()));
+        assert_eq!((after.cols, after.rows), (120, 40));
+        assert_eq!(after.title, Some("build".to_string()));
+        assert!(manager.sessions["test"].keep_alive);
+        assert_eq!(manager.sessions["test"].shell_args, ["-i"]);
+        assert_eq!(manager.sessions["test"].env["ORTHRUS_TEST"], "1");
+        assert_eq!(
+            manager.sessions["test"].keepalive_interval,
+            Some(Duration::from_secs(30))
+        );
+        // プロセスは新しくなる
+        assert_ne!(after.pid, before.pid);
+        let _ = manager.kill("test");
+    }
+
+    /// テスト用の起動情報
+    fn shell_spec(shell: &str) -> SessionSpec {
+        SessionSpec {
+            shell: Some(shell.to_string()),
+            shell_args: Vec::new(),
+            shell_fallbacks: Vec::new(),
+            cwd: None,
+            cols: 80,
+            rows: 24,
+            title: None,
+            keep_alive: false,
+            keepalive_interval: None,
+            shell_integration: false,
+            safe_mode: false,
+            defer_slave_drop: false,
+            accent: None,
+            color_scheme: None,
+            env: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_spawn_options_from_camel_case() {
+        let options: SpawnOptions = serde_json::from_str(
+            r#"{"cols": 80, "rows": 24, "shellArgs": ["-i"], "safeMode": true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            SpawnOptions {
+                shell_args: vec!["-i".to_string()],
+                cols: 80,
+                rows: 24,
+                safe_mode: true,
+                ..Default::default()
+            }
+        );
+        assert!(serde_json::from_str::<SpawnOptions>(r#"{"cols": 80}"#).is_err());
+    }
+
+    #[test]
+    fn test_shell_argv() {
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(shell_argv("/bin/bash", &[], false), ["-l"]);
+        // 追加の引数は `-l` の代わり
+        assert_eq!(
+            shell_argv("/bin/bash", &args(&["--rcfile", "/tmp/rc", "-i"]), false),
+            ["--rcfile", "/tmp/rc", "-i"]
+        );
+        assert_eq!(
+            shell_argv("/bin/bash", &args(&["--login", "-i"]), false),
+            ["--login", "-i"]
+        );
+        assert_eq!(
+            shell_argv("/bin/bash", &args(&["--rcfile", "/tmp/rc"]), true),
+            ["--norc", "--noprofile", "--rcfile", "/tmp/rc"]
+        );
+    }
+
+    #[test]
+    fn test_shell_argv_example_rcfile_starts_bash() {
+        if !Path::new("/bin/bash").exists() {
+            return;
+        }
+        // config.toml.exampleの `shell_args = ["--rcfile", ...]` と同じ形
+        let dir = tempfile::tempdir().unwrap();
+        let rcfile = dir.path().join(".orthrus-bashrc");
+        std::fs::write(&rcfile, "echo orthrus-rc-loaded\n").unwrap();
+        let shell_args = vec!["--rcfile".to_string(), rcfile.to_string_lossy().to_string()];
+
+        // stdinが端末でないため、対話シェルとして起動するよう `-i` を足す
+        let output = std::process::Command::new("/bin/bash")
+            .args(shell_argv("/bin/bash", &shell_args, false))
+            .arg("-i")
+            .stdin(std::process::Stdio::null())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(String::from_utf8_lossy(&output.stdout).contains("orthrus-rc-loaded"));
+    }
+
+    #[test]
+    fn test_shell_argv_safe_mode() {
+        assert_eq!(
+            shell_argv("/bin/bash", &[], true),
+            ["--norc", "--noprofile", "-l"]
+        );
+        assert_eq!(shell_argv("/usr/local/bin/zsh", &[], true), ["-f", "-l"]);
+        assert_eq!(
+            shell_argv("/opt/homebrew/bin/fish", &[], true),
+            ["--no-config", "-l"]
+        );
+        assert_eq!(shell_argv("-zsh", &[], true), ["-f", "-l"]);
+        // 未知のシェルはそのまま起動する
+        assert_eq!(shell_argv("/bin/dash", &[], true), ["-l"]);
+        assert_eq!(shell_argv("/bin/bash", &[], false), ["-l"]);
+    }
+
+    /// 閉じた時点を記録するslaveの代わり
+    struct RecordingSlave(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Drop for RecordingSlave {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push("slave dropped");
+        }
+    }
+
+    /// 読み取りを記録するreader
+    struct RecordingReader(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Read for RecordingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().push("read");
+            buf[0] = b'$';
+            Ok(1)
+        }
+    }
+
+    fn slave_drop_order(policy: SlaveDrop) -> Vec<&'static str> {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut reader = policy.apply(
+            RecordingSlave(log.clone()),
+            Box::new(RecordingReader(log.clone())),
+        );
+        log.lock().unwrap().push("spawned");
+        let mut buf = [0u8; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        let order = log.lock().unwrap().clone();
+        order
+    }
+
+    #[test]
+    fn test_slave_drop_ordering() {
+        assert_eq!(SlaveDrop::from_flag(false), SlaveDrop::Immediate);
+        assert_eq!(SlaveDrop::from_flag(true), SlaveDrop::AfterFirstRead);
+        assert_eq!(
+            slave_drop_order(SlaveDrop::Immediate),
+            ["slave dropped", "spawned", "read", "read"]
+        );
+        assert_eq!(
+            slave_drop_order(SlaveDrop::AfterFirstRead),
+            ["spawned", "read", "slave dropped", "read"]
+        );
+    }
+
+    #[test]
+    fn test_check_truecolor_support() {
+        assert!(check_truecolor_support("xterm-256color", Some("truecolor")));
+        assert!(check_truecolor_support(
+            SESSION_TERM,
+            Some(SESSION_COLORTERM)
+        ));
+        assert!(check_truecolor_support("xterm-direct", None));
+        assert!(!check_truecolor_support("xterm-256color", None));
+        assert!(!check_truecolor_support("vt100", None));
+    }
+
+    #[test]
+    fn test_truecolor_warning() {
+        assert_eq!(
+            truecolor_warning(true, "xterm-256color", Some("truecolor")),
+            None
+        );
+        assert_eq!(
+            truecolor_warning(true, "vt100", None),
+            Some(events::TerminalColorWarning {
+                term: "vt100".to_string(),
+                colorterm: None,
+            })
+        );
+        // 24bitカラーを使わない設定なら警告しない
+        assert_eq!(truecolor_warning(false, "vt100", None), None);
+    }
+
+    #[test]
+    fn test_keepalive_writes_within_interval() {
+        let writer = RecordingWriter::default();
+        let tx = spawn_writer_thread(Box::new(writer.clone()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_thread = Arc::clone(&stop);
+        spawn_keepalive_thread(Duration::from_millis(20), move || {
+            !stop_thread.load(Ordering::Relaxed) && tx.try_send(KEEPALIVE_BYTES.to_vec()).is_ok()
+        });
+
+        let written = writer.wait_for(1);
+        stop.store(true, Ordering::Relaxed);
+        assert!(!written.is_empty());
+        assert!(written.iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_capture_command() {
+        let result =
+            capture_command("echo", &["hi".to_string()], None, &HashMap::new(), true).unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(result.output.trim_end(), "hi");
+        assert!(!result.truncated);
+        assert!(!result.timed_out);
+    }
+
+    #[test]
+    fn test_capture_command_exit_code_and_env() {
+        let env = HashMap::from([("GREETING".to_string(), "hello".to_string())]);
+        let args = ["-c".to_string(), "echo $GREETING; exit 3".to_string()];
+        let result = capture_command("sh", &args, None, &env, true).unwrap();
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.output.trim_end(), "hello");
+    }
+
+    #[test]
+    fn test_capture_command_minimal_env() {
+        std::env::set_var("ORTHRUS_CAPTURE_INHERITED", "leaked");
+        let args = [
+            "-c".to_string(),
+            "echo \"inherited=$ORTHRUS_CAPTURE_INHERITED path=${PATH:+set} term=$TERM\""
+                .to_string(),
+        ];
+
+        // 許可リストにない変数は引き継がない
+        let result = capture_command("sh", &args, None, &HashMap::new(), true).unwrap();
+        assert_eq!(result.output.trim_end(), "inherited= path=set term=dumb");
+
+        let result = capture_command("sh", &args, None, &HashMap::new(), false).unwrap();
+        assert_eq!(
+            result.output.trim_end(),
+            "inherited=leaked path=set term=dumb"
+        );
+        std::env::remove_var("ORTHRUS_CAPTURE_INHERITED");
+    }
+
+    #[test]
+    fn test_capture_command_limits() {
+        let args = ["-c".to_string(), "echo 0123456789; sleep 5".to_string()];
+        let result = capture_command_with_limits(
+            "sh",
+            &args,
+            None,
+            &HashMap::new(),
+            true,
+            4,
+            Duration::from_millis(500),
+        )
+        .unwrap();
+        assert!(result.timed_out);
+        assert_eq!(result.exit_code, None);
+        assert!(result.truncated);
+        assert_eq!(result.output, "0123");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_close_drains_output_before_kill() {
+        let (_dir, script) = fake_shell("#!/bin/sh\nsleep 0.2\necho bye\nsleep 30\n");
+
+        let mut manager = TerminalManager::new();
+        let (session, reader) = open_session(&shell_spec(&script)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(reader, session.reader_state(), None, move |_, output| {
+            if let PtyOutput::Data { data, .. } = output {
+                let _ = tx.send(data);
+            }
+        });
+        manager.sessions.insert("test".to_string(), session);
+
+        // 終了処理中は入力を受け付けない
+        let exited = manager.begin_close("test").unwrap();
+        assert!(manager.write("test", b"ls\n").is_err());
+
+        wait_for_drain(&exited, Duration::from_millis(1000));
+        manager.kill("test").unwrap();
+
+        let output: Vec<u8> = rx.try_iter().flatten().collect();
+        let output = String::from_utf8_lossy(&output);
+        assert!(output.contains("bye"), "{:?}", output);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_close_returns_when_output_closes() {
+        let (_dir, script) = fake_shell("#!/bin/sh\necho bye\n");
+        let mut manager = TerminalManager::new();
+        let (session, reader) = open_session(&shell_spec(&script)).unwrap();
+        spawn_reader_thread(reader, session.reader_state(), None, |_, _| {});
+        manager.sessions.insert("test".to_string(), session);
+
+        // シェルが終了して出力が閉じたら、drainの経過を待たずに戻る
+        let exited = manager.begin_close("test").unwrap();
+        let started = Instant::now();
+        wait_for_drain(&exited, Duration::from_secs(30));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(exited.is_set());
+        manager.kill("test").unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_spawn_env_reaches_shell() {
+        let (_dir, script) = fake_shell("#!/bin/sh\necho \"env=$ORTHRUS_TEST\"\nsleep 30\n");
+
+        let mut spec = shell_spec(&script);
+        spec.env = HashMap::from([("ORTHRUS_TEST".to_string(), "from-spawn".to_string())]);
+        let (session, reader) = open_session(&spec).unwrap();
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(reader, session.reader_state(), None, move |_, output| {
+            if let PtyOutput::Data { data, .. } = output {
+                let _ = tx.send(data);
+            }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut output = Vec::new();
+        while !String::from_utf8_lossy(&output).contains("env=from-spawn") {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok(data) => output.extend(data),
+                Err(_) => panic!("{:?}", String::from_utf8_lossy(&output)),
+            }
+        }
+        let _ = session.child.kill();
+    }
+
+    #[test]
+    fn test_rename_switches_event_session_id() {
+        let mut manager = TerminalManager::new();
+        let (session, reader) = open_session(&shell_spec("/bin/sh")).unwrap();
+        *session.id.lock().unwrap() = "old".to_string();
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(reader, session.reader_state(), None, move |sid, output| {
+            if let PtyOutput::Data { data, .. } = output {
+                let _ = tx.send((sid.to_string(), String::from_utf8_lossy(&data).to_string()));
+            }
+        });
+        manager.sessions.insert("old".to_string(), session);
+
+        // 指定した文字列を含む出力が届くまで待ち、そのときのIDを返す
+        let wait_for = |needle: &str| {
+            let mut output = String::new();
+            while let Ok((sid, data)) = rx.recv_timeout(Duration::from_secs(5)) {
+                output.push_str(&data);
+                if output.contains(needle) {
+                    return sid;
+                }
+            }
+            panic!("{:?} not found in {:?}", needle, output);
+        };
+
+        manager.write("old", b"echo first\n").unwrap();
+        assert_eq!(wait_for("first"), "old");
+
+        manager.rename("old", "new").unwrap();
+        assert!(!manager.sessions.contains_key("old"));
+        manager.write("new", b"echo second\n").unwrap();
+        assert_eq!(wait_for("second"), "new");
+
+        // 既存のIDには付け替えられない
+        insert_recording_session(&mut manager, "other");
+        assert!(manager.rename("new", "other").is_err());
+        assert!(manager.rename("missing", "x").is_err());
+        let _ = manager.kill("new");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_foreground_keeps_shell() {
+        let mut manager = TerminalManager::new();
+        let (session, reader) = open_session(&shell_spec("/bin/sh")).unwrap();
+        let shell_pid = session.pid();
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(reader, session.reader_state(), None, move |_, output| {
+            if let PtyOutput::Data { data, .. } = output {
+                let _ = tx.send(data);
+            }
+        });
+        manager.sessions.insert("test".to_string(), session);
+
+        manager.write("test", b"sleep 30\n").unwrap();
+        // sleepがフォアグラウンドになるまで待つ
+        let foreground = || manager.sessions["test"].master.process_group_leader();
+        for _ in 0..100 {
+            if foreground().is_some_and(|pgrp| Some(pgrp as u32) != shell_pid) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_ne!(foreground().map(|pgrp| pgrp as u32), shell_pid);
+
+        manager.kill_foreground("test").unwrap();
+        manager.write("test", b"echo still-$((1+1))\n").unwrap();
+        let mut output = Vec::new();
+        while let Ok(data) = rx.recv_timeout(Duration::from_secs(5)) {
+            output.extend_from_slice(&data);
+            if String::from_utf8_lossy(&output).contains("still-2") {
+                break;
+            }
+        }
+        assert!(
+            String::from_utf8_lossy(&output).contains("still-2"),
+            "{:?}",
+            String::from_utf8_lossy(&output)
+        );
+        assert!(manager.info("test").unwrap().alive);
+        let _ = manager.kill("test");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_seq_increments_per_chunk() {
+        let (_dir, script) =
+            fake_shell("#!/bin/sh\nprintf a\nsleep 0.1\nprintf b\nsleep 0.1\nprintf c\n");
+
+        let mut manager = TerminalManager::new();
+        let (session, reader) = open_session(&shell_spec(&script)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(reader, session.reader_state(), None, move |_, output| {
+            let _ = tx.send(output);
+        });
+        manager.sessions.insert("test".to_string(), session);
+
+        let mut seqs = Vec::new();
+        let mut output = String::new();
+        for received in rx.iter() {
+            match received {
+                PtyOutput::Data { seq, data } => {
+                    seqs.push(seq);
+                    output.push_str(&String::from_utf8_lossy(&data));
+                }
+                PtyOutput::Exit { .. } => break,
+                PtyOutput::Cwd(_) | PtyOutput::PromptMarker(_) => {}
+            }
+        }
+        assert_eq!(output, "abc");
+        assert!(seqs.len() >= 3, "{:?}", seqs);
+        assert_eq!(seqs, (0..seqs.len() as u64).collect::<Vec<_>>());
+
+        // 読み取りスレッド外からの送信も同じ連番の続き
+        assert_eq!(manager.next_output_seq("test"), Some(seqs.len() as u64));
+        assert_eq!(manager.next_output_seq("missing"), None);
+    }
+
+    #[test]
+    fn test_effective_spawn_env_precedence() {
+        let process_env = [
+            ("PATH", "/usr/bin"),
+            ("TERM", "dumb"),
+            ("VIRTUAL_ENV", "/old/.venv"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let config_env = HashMap::from([
+            ("VIRTUAL_ENV".to_string(), "/proj/.venv".to_string()),
+            ("COLORTERM".to_string(), "24bit".to_string()),
+        ]);
+
+        let env = effective_spawn_env(process_env.clone(), "/bin/zsh", &config_env);
+        assert_eq!(env["PATH"], "/usr/bin");
+        // セッションの設定が親プロセスの環境より優先
+        assert_eq!(env["TERM"], SESSION_TERM);
+        assert_eq!(env["SHELL"], "/bin/zsh");
+        // 指定した環境変数が最優先
+        assert_eq!(env["VIRTUAL_ENV"], "/proj/.venv");
+        assert_eq!(env["COLORTERM"], "24bit");
+
+        let env = effective_spawn_env(process_env, "/bin/sh", &HashMap::new());
+        assert_eq!(env["COLORTERM"], SESSION_COLORTERM);
+        assert_eq!(env["VIRTUAL_ENV"], "/old/.venv");
+    }
+
+    #[test]
+    fn test_accent_round_trips_through_listing() {
+        let mut manager = TerminalManager::new();
+        insert_test_session(&mut manager, "b", Box::new(std::io::sink()));
+        insert_test_session(&mut manager, "a", Box::new(std::io::sink()));
+        manager.sessions.get_mut("a").unwrap().accent = Some(validate_accent(" #F0A ").unwrap());
+
+        let entries = manager.list();
+        let ids: Vec<&str> = entries.iter().map(|e| e.session_id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(entries[0].info.accent.as_deref(), Some("#ff00aa"));
+        assert_eq!(entries[1].info.accent, None);
+        assert_eq!(
+            manager.info("a").unwrap().accent.as_deref(),
+            Some("#ff00aa")
+        );
+        // 再起動しても引き継ぐ
+        assert_eq!(
+            manager.sessions["a"].spec().accent.as_deref(),
+            Some("#ff00aa")
+        );
+
+        let value = serde_json::to_value(&entries[0]).unwrap();
+        assert_eq!(value["session_id"], "a");
+        assert_eq!(value["accent"], "#ff00aa");
+    }
+
+    #[test]
+    fn test_set_all_color_scheme_updates_every_session() {
+        let mut manager = TerminalManager::new();
+        insert_test_session(&mut manager, "b", Box::new(std::io::sink()));
+        insert_test_session(&mut manager, "a", Box::new(std::io::sink()));
+        assert_eq!(manager.info("a").unwrap().color_scheme, None);
+
+        let dark = ColorScheme {
+            background: Some("#000000".to_string()),
+            ..ColorScheme::default()
+        };
+        let light = ColorScheme {
+            background: Some("#ffffff".to_string()),
+            ..ColorScheme::default()
+        };
+        assert_eq!(manager.set_all_color_scheme(&dark), ["a", "b"]);
+        assert_eq!(manager.set_all_color_scheme(&light), ["a", "b"]);
+
+        for entry in manager.list() {
+            assert_eq!(entry.info.color_scheme.as_ref(), Some(&light));
+        }
+        // 再起動しても引き継ぐ
+        assert_eq!(manager.sessions["b"].spec().color_scheme, Some(light));
+    }
+
+    #[test]
+    fn test_validate_accent_rejects_invalid_hex() {
+        assert_eq!(validate_accent("#1e90ff").unwrap(), "#1e90ff");
+        assert_eq!(validate_accent("1E90FF80").unwrap(), "#1e90ff80");
+        for invalid in ["", "blue", "#12345g", "rgb(0, 0, 0)", "#1234567890"] {
+            assert!(validate_accent(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    /// 登録済みのテスト用セッションの出力を読み捨てる読み取りスレッドを起動
+    fn start_test_reader(manager: &TerminalManager, session_id: &str) {
+        let session = &manager.sessions[session_id];
+        let reader = session.master.try_clone_reader().unwrap();
+        spawn_reader_thread(reader, session.reader_state(), None, |_, _| {});
+    }
+
+    #[test]
+    fn test_wait_exit_returns_exit_code() {
+        let manager = create_terminal_manager();
+        let mut command = CommandBuilder::new("sh");
+        command.args(["-c", "sleep 0.1; exit 3"]);
+        insert_command_session(
+            &mut manager.lock().unwrap(),
+            "job",
+            Box::new(std::io::sink()),
+            command,
+        );
+        start_test_reader(&manager.lock().unwrap(), "job");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let rx = manager.lock().unwrap().wait_for_exit("job").unwrap();
+        let code = runtime.block_on(await_exit("job", rx, Duration::from_secs(5)));
+        assert_eq!(code, Ok(3));
+
+        // 終了後に登録し、受け取る前にセッションを閉じても終了コードが届く
+        let rx = manager.lock().unwrap().wait_for_exit("job").unwrap();
+        manager.lock().unwrap().kill("job").unwrap();
+        let code = runtime.block_on(await_exit("job", rx, Duration::from_secs(5)));
+        assert_eq!(code, Ok(3));
+
+        let err = manager
+            .lock()
+            .unwrap()
+            .wait_for_exit("missing")
+            .unwrap_err();
+        assert!(err.contains("Session not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_wait_exit_timeout_stops_waiter() {
+        let manager = create_terminal_manager();
+        let mut command = CommandBuilder::new("sh");
+        command.args(["-c", "sleep 30"]);
+        insert_command_session(
+            &mut manager.lock().unwrap(),
+            "job",
+            Box::new(std::io::sink()),
+            command,
+        );
+        start_test_reader(&manager.lock().unwrap(), "job");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let rx = manager.lock().unwrap().wait_for_exit("job").unwrap();
+        let err = runtime
+            .block_on(await_exit("job", rx, Duration::from_millis(100)))
+            .unwrap_err();
+        assert!(err.contains("Timed out"), "{}", err);
+
+        // 待ち受けはマネージャーを保持しない
+        assert_eq!(Arc::strong_count(&manager), 1);
+        manager.lock().unwrap().kill("job").unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_spawn_with_sink_receives_output_bytes() {
+        let (_dir, script) = fake_shell("#!/bin/sh\nprintf 'hello \\343\\201\\202'\nsleep 30\n");
+
+        let mut manager = TerminalManager::new();
+        let (tx, rx) = mpsc::channel();
+        manager
+            .spawn_with_sink("sink".to_string(), None, Some(script), 80, 24, tx)
+            .unwrap();
+
+        let expected = "hello あ".as_bytes();
+        let mut output = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !output.ends_with(expected) && Instant::now() < deadline {
+            if let Ok(chunk) = rx.recv_timeout(Duration::from_millis(100)) {
+                output.extend(chunk);
+            }
+        }
+        assert!(output.ends_with(expected), "{:?}", output);
+        manager.kill("sink").unwrap();
+    }
+
+    /// 指定した結果を順に返すリーダー（読み切ったらEOF）
+    struct ScriptedReader(VecDeque<std::io::Result<Vec<u8>>>);
+
+    impl Read for ScriptedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.pop_front() {
+                Some(Ok(bytes)) => {
+                    buf[..bytes.len()].copy_from_slice(&bytes);
+                    Ok(bytes.len())
+                }
+                Some(Err(e)) => Err(e),
+                None => Ok(0),
+            }
+        }
+    }
+
+    /// プロセスを持たない読み取りスレッドの共有状態
+    fn detached_reader_state() -> ReaderState {
+        ReaderState {
+            id: Arc::new(Mutex::new("test".to_string())),
+            scrollback: Arc::new(Mutex::new(Scrollback::default())),
+            title: Arc::new(Mutex::new(None)),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            focus_reporting: Arc::new(AtomicBool::new(false)),
+            replaced: Arc::new(AtomicBool::new(false)),
+            exited: Arc::new(ExitedFlag::default()),
+            hangup: Arc::new(AtomicBool::new(false)),
+            output_seq: Arc::new(AtomicU64::new(0)),
+            metrics: Arc::new(OutputMetrics::default()),
+            child: None,
+            exit: ExitWaiters::default(),
+        }
+    }
+
+    /// ScriptedReaderで読み取りスレッドを動かし、通知をすべて集める
+    fn run_scripted_reader(script: Vec<std::io::Result<Vec<u8>>>) -> Vec<PtyOutput> {
+        run_scripted_reader_with(detached_reader_state(), script)
+    }
+
+    fn run_scripted_reader_with(
+        state: ReaderState,
+        script: Vec<std::io::Result<Vec<u8>>>,
+    ) -> Vec<PtyOutput> {
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(
+            Box::new(ScriptedReader(script.into())),
+            state,
+            None,
+            move |_, output| {
+                let _ = tx.send(output);
+            },
+        );
+        rx.iter().collect()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_reader_records_hangup() {
+        let state = detached_reader_state();
+        let hangup = Arc::clone(&state.hangup);
+        let outputs = run_scripted_reader_with(
+            state,
+            vec![
+                Ok(b"abc".to_vec()),
+                Err(std::io::Error::from_raw_os_error(libc::EIO)),
+            ],
+        );
+        assert!(matches!(
+            outputs.last(),
+            Some(PtyOutput::Exit {
+                code: 0,
+                error: false
+            })
+        ));
+        assert!(hangup.load(Ordering::Relaxed));
+
+        // EOFで閉じた場合は記録しない
+        let state = detached_reader_state();
+        let hangup = Arc::clone(&state.hangup);
+        run_scripted_reader_with(state, vec![Ok(b"abc".to_vec())]);
+        assert!(!hangup.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_metrics_count_output() {
+        let mut manager = TerminalManager::new();
+        let (session, _reader) = open_session(&shell_spec("/bin/sh")).unwrap();
+        let (tx, rx) = mpsc::channel();
+        spawn_reader_thread(
+            Box::new(ScriptedReader(
+                vec![Ok(b"hello\r\n".to_vec()), Ok(b"world\r\n".to_vec())].into(),
+            )),
+            session.reader_state(),
+            None,
+            move |_, output| {
+                let _ = tx.send(output);
+            },
+        );
+        manager.sessions.insert("test".to_string(), session);
+        // 出力2回と終了を受け取るまで待つ
+        assert_eq!(rx.iter().count(), 3);
+
+        let metrics = manager.metrics("test").unwrap();
+        assert_eq!(metrics.bytes_read, 14);
+        assert_eq!(metrics.events_emitted, 3);
+        assert!(metrics.bytes_per_sec > 0.0);
+        assert!(manager.metrics("missing").is_err());
+        let _ = manager.kill("test");
+    }
+
+    #[test]
+    fn test_output_metrics_window() {
+        let metrics = OutputMetrics::default();
+        let start = Instant::now();
+        metrics.record_read(1000, start);
+        metrics.record_read(500, start + Duration::from_secs(4));
+        let snapshot = metrics.snapshot(start + Duration::from_secs(4));
+        assert_eq!(snapshot.bytes_read, 1500);
+        assert_eq!(snapshot.bytes_per_sec, 300.0);
+
+        // 窓を過ぎた読み取りは速度に含めない（累計には残る）
+        let snapshot = metrics.snapshot(start + Duration::from_secs(8));
+        assert_eq!(snapshot.bytes_read, 1500);
+        assert_eq!(snapshot.bytes_per_sec, 100.0);
+    }
+
+    #[test]
+    fn test_reader_retries_transient_errors() {
+        use std::io::{Error, ErrorKind};
+        let outputs = run_scripted_reader(vec![
+            Ok(b"abc".to_vec()),
+            Err(Error::from(ErrorKind::Interrupted)),
+            Err(Error::from(ErrorKind::WouldBlock)),
+            Ok(b"def".to_vec()),
+        ]);
+        let data: Vec<u8> = outputs
+            .iter()
+            .filter_map(|output| match output {
+                PtyOutput::Data { data, .. } => Some(data.clone()),
+                PtyOutput::Exit { .. } | PtyOutput::Cwd(_) | PtyOutput::PromptMarker(_) => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(data, b"abcdef");
+        assert!(matches!(
+            outputs.last(),
+            Some(PtyOutput::Exit {
+                code: 0,
+                error: false
+            })
+        ));
+    }
+
+    #[test]
+    fn test_reader_emits_cwd_changes_once() {
+        let outputs = run_scripted_reader(vec![
+            Ok(b"\x1b]7;file://host/home/user\x07$ ".to_vec()),
+            Ok(b"\x1b]7;file://host/home/user/my%20docs\x1b\\$ ".to_vec()),
+            // 同じディレクトリの再通知は送らない
+            Ok(b"\x1b]7;file://host/home/user/my%20docs\x07$ ".to_vec()),
+        ]);
+        let cwds: Vec<&str> = outputs
+            .iter()
+            .filter_map(|output| match output {
+                PtyOutput::Cwd(path) => Some(path.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(cwds, ["/home/user", "/home/user/my docs"]);
+    }
+
+    #[test]
+    fn test_parse_osc_cwd() {
+        assert_eq!(
+            parse_osc_cwd(b"\x1b]7;file:///tmp\x07"),
+            Some("/tmp".to_string())
+        );
+        assert_eq!(
+            parse_osc_cwd(b"\x1b]7;file://host/a\x07\x1b]7;file://host/b%2Fc%zz\x1b\\"),
+            Some("/b/c%zz".to_string())
+        );
+        assert_eq!(parse_osc_cwd(b"\x1b]0;title\x07"), None);
+        assert_eq!(parse_osc_cwd(b"\x1b]7;/tmp\x07"), None);
+    }
+
+    #[test]
+    fn test_parse_prompt_markers() {
+        assert_eq!(
+            parse_prompt_markers(b"\x1b]133;D;0\x07\x1b]133;A\x07$ \x1b]133;B\x07"),
+            [
+                PromptMarker::CommandFinished(Some(0)),
+                PromptMarker::PromptStart,
+                PromptMarker::CommandStart,
+            ]
+        );
+        assert_eq!(
+            parse_prompt_markers(b"ls\r\n\x1b]133;C\x1b\\out\x1b]133;D;127;aid=1\x1b\\"),
+            [
+                PromptMarker::CommandExecuted,
+                PromptMarker::CommandFinished(Some(127)),
+            ]
+        );
+        // 終了コードの省略・不正、未知の種別、閉じていないシーケンス
+        assert_eq!(
+            parse_prompt_markers(b"\x1b]133;D\x07\x1b]133;D;x\x07\x1b]133;P;k=i\x07\x1b]133;A"),
+            [
+                PromptMarker::CommandFinished(None),
+                PromptMarker::CommandFinished(None),
+            ]
+        );
+        assert!(parse_prompt_markers(b"\x1b]7;file:///tmp\x07plain").is_empty());
+    }
+
+    #[test]
+    fn test_reader_emits_prompt_markers() {
+        let outputs = run_scripted_reader(vec![
+            Ok(b"\x1b]133;A\x07$ \x1b]133;B\x07".to_vec()),
+            Ok(b"\x1b]133;C\x07hi\r\n\x1b]133;D;3\x07".to_vec()),
+        ]);
+        let markers: Vec<PromptMarker> = outputs
+            .iter()
+            .filter_map(|output| match output {
+                PtyOutput::PromptMarker(marker) => Some(*marker),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            markers,
+            [
+                PromptMarker::PromptStart,
+                PromptMarker::CommandStart,
+                PromptMarker::CommandExecuted,
+                PromptMarker::CommandFinished(Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shell_kind_detect() {
+        assert_eq!(ShellKind::detect("/bin/bash"), Some(ShellKind::Bash));
+        assert_eq!(ShellKind::detect("-zsh"), Some(ShellKind::Zsh));
+        assert_eq!(ShellKind::detect("/usr/local/bin/fish"), None);
+        for kind in [ShellKind::Bash, ShellKind::Zsh] {
+            let script = kind.integration_script();
+            assert!(script.starts_with(' '));
+            assert!(script.ends_with('\n'));
+            assert!(script.contains("133;C"));
+        }
+    }
+
+    #[test]
+    fn test_reader_reports_fatal_error() {
+        use std::io::{Error, ErrorKind};
+        let outputs = run_scripted_reader(vec![
+            Ok(b"abc".to_vec()),
+            Err(Error::from(ErrorKind::BrokenPipe)),
+            Ok(b"never".to_vec()),
+        ]);
+        assert_eq!(outputs.len(), 2);
+        assert!(matches!(&outputs[0], PtyOutput::Data { data, .. } if data == b"abc"));
+        assert!(matches!(
+            outputs[1],
+            PtyOutput::Exit {
+                code: 1,
+                error: true
+            }
+        ));
+
+        // 一時的なエラーでも上限を超えて続けば致命的として扱う
+        let outputs = run_scripted_reader(
+            (0..=READ_RETRY_LIMIT)
+                .map(|_| Err(Error::from(ErrorKind::Interrupted)))
+                .collect(),
+        );
+        assert!(matches!(
+            outputs.last(),
+            Some(PtyOutput::Exit { error: true, .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_classify_read_error_eio_is_closed() {
+        assert_eq!(
+            classify_read_error(&std::io::Error::from_raw_os_error(libc::EIO)),
+            ReadFailure::Closed
+        );
+    }
+
+    #[test]
+    fn test_resolve_paste_limit() {
+        assert_eq!(resolve_paste_limit(Some(10), Some(20)), 10);
+        assert_eq!(resolve_paste_limit(None, Some(20)), 20);
+        assert_eq!(resolve_paste_limit(None, None), DEFAULT_MAX_PASTE_BYTES);
+    }
+
+    #[test]
+    fn test_write_paste_rejects_oversized() {
+        let mut manager = TerminalManager::new();
+        let recorder = insert_recording_session(&mut manager, "test");
+
+        let err = manager.write_paste("test", b"0123456789", 8).unwrap_err();
+        assert_eq!(
+            err,
+            AppError::PasteTooLarge {
+                session_id: "test".to_string(),
+                size: 10,
+                limit: 8,
+            }
+        );
+
+        manager.write_paste("test", b"01234567", 8).unwrap();
+        // 拒否した貼り付けは書き込まれていない
+        assert_eq!(recorder.wait_for(8), b"01234567");
+        let _ = manager.kill("test");
+    }
+
+    #[test]
+    fn test_failed_session_is_removed() {
+        let manager = create_terminal_manager();
+        let failed_tx = {
+            let mut inner = manager.lock().unwrap();
+            insert_recording_session(&mut inner, "failed");
+            insert_recording_session(&mut inner, "alive");
+            inner.sessions["failed"].exited.set();
+            inner.failed_tx.clone().unwrap()
+        };
+        // 出力が閉じていないセッションは通知されても残す
+        failed_tx.send("alive".to_string()).unwrap();
+        failed_tx.send("failed".to_string()).unwrap();
+
+        // マネージャを操作しなくても通知を受けた時点で破棄される
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.lock().unwrap().sessions.contains_key("failed") {
+            assert!(Instant::now() < deadline, "failed session was not removed");
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut inner = manager.lock().unwrap();
+        assert!(inner.sessions.contains_key("alive"));
+        let _ = inner.kill("alive");
+    }
+
+    #[test]
+    fn test_open_session_shell_not_found() {
+        let err = open_session(&shell_spec("/nonexistent/bin/zsh"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            AppError::ShellNotFound {
+                path: "/nonexistent/bin/zsh".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_open_session_shell_not_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let shell = dir.path().join("shell");
+        std::fs::write(&shell, "#!/bin/sh\n").unwrap();
+        let shell = shell.to_string_lossy().to_string();
+
+        let err = open_session(&shell_spec(&shell)).err().unwrap();
+        assert_eq!(err, AppError::PermissionDenied { path: shell });
+    }
+
+    #[test]
+    fn test_terminal_info_nonexistent_session() {
+        let mut manager = TerminalManager::new();
+        assert!(manager.info("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_idle_sessions() {
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "idle");
+        insert_recording_session(&mut manager, "active");
+        insert_recording_session(&mut manager, "pinned");
+
+        let start = Instant::now();
+        let set_activity = |manager: &TerminalManager, id: &str, at: Instant| {
+            *manager.sessions[id].last_activity.lock().unwrap() = at;
+        };
+        set_activity(&manager, "idle", start);
+        set_activity(&manager, "active", start + Duration::from_secs(50));
+        set_activity(&manager, "pinned", start);
+        manager.sessions.get_mut("pinned").unwrap().keep_alive = true;
+
+        let limit = Duration::from_secs(60);
+        assert!(manager
+            .idle_sessions(start + Duration::from_secs(30), limit)
+            .is_empty());
+        assert_eq!(
+            manager.idle_sessions(start + Duration::from_secs(60), limit),
+            vec!["idle".to_string()]
+        );
+        assert_eq!(
+            manager.idle_sessions(start + Duration::from_secs(120), limit),
+            vec!["active".to_string(), "idle".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_write_updates_last_activity() {
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "test");
+        let past = Instant::now() - Duration::from_secs(120);
+        *manager.sessions["test"].last_activity.lock().unwrap() = past;
+
+        manager.write("test", b"ls\n").unwrap();
+        let now = Instant::now();
+        assert!(manager
+            .idle_sessions(now, Duration::from_secs(60))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_osc_title() {
+        assert_eq!(
+            parse_osc_title(b"\x1b]0;user@host: ~\x07$ "),
+            Some("user@host: ~".to_string())
+        );
+        assert_eq!(
+            parse_osc_title(b"\x1b]2;first\x1b\\\x1b]2;second\x07"),
+            Some("second".to_string())
+        );
+        // OSC 7 などタイトル以外は無視
+        assert_eq!(parse_osc_title(b"\x1b]7;file:///tmp\x07"), None);
+        assert_eq!(parse_osc_title(b"plain output"), None);
+    }
+
+    #[test]
+    fn test_interrupt_writes_etx() {
+        let mut manager = TerminalManager::new();
+        let recorder = insert_recording_session(&mut manager, "test");
+
+        manager.interrupt("test").unwrap();
+        assert_eq!(recorder.wait_for(1), vec![0x03]);
+    }
+
+    #[test]
+    fn test_focus_writes_focus_sequences() {
+        let mut manager = TerminalManager::new();
+        let recorder = insert_recording_session(&mut manager, "test");
+        manager.sessions["test"]
+            .focus_reporting
+            .store(true, Ordering::Relaxed);
+
+        manager.focus("test", true).unwrap();
+        assert_eq!(recorder.wait_for(3), b"\x1b[I");
+        manager.focus("test", false).unwrap();
+        assert_eq!(recorder.wait_for(6), b"\x1b[I\x1b[O");
+    }
+
+    #[test]
+    fn test_focus_skipped_without_focus_reporting() {
+        let mut manager = TerminalManager::new();
+        let recorder = insert_recording_session(&mut manager, "test");
+
+        manager.focus("test", true).unwrap();
+        manager.interrupt("test").unwrap();
+        // フォーカス通知は送られず、後続の書き込みだけが届く
+        assert_eq!(recorder.wait_for(1), vec![0x03]);
+    }
+
+    #[test]
+    fn test_parse_focus_reporting() {
+        assert_eq!(parse_focus_reporting(b"\x1b[?1004h"), Some(true));
+        assert_eq!(
+            parse_focus_reporting(b"\x1b[?1004hvim\x1b[?1004l"),
+            Some(false)
+        );
+        assert_eq!(parse_focus_reporting(b"\x1b[?1049h"), None);
+    }
+
+    #[test]
+    fn test_write_reaches_pty_writer() {
+        let mut manager = TerminalManager::new();
+        let recorder = insert_recording_session(&mut manager, "test");
+
+        manager.write("test", b"ls\r").unwrap();
+        manager.write("test", b"pwd\r").unwrap();
+        assert_eq!(recorder.wait_for(7), b"ls\rpwd\r");
+    }
+
+    #[test]
+    fn test_write_backpressure() {
+        let mut manager = TerminalManager::new();
+        let (_unblock, blocked) = mpsc::channel();
+        insert_test_session(&mut manager, "test", Box::new(StuckWriter(blocked)));
+
+        // 書き込みスレッドが1チャンク抱えて止まり、残りでキューが埋まる
+        let mut result = Ok(());
+        for _ in 0..WRITE_QUEUE_CAPACITY + 2 {
+            result = manager.write("test", b"x");
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(
+            result,
+            Err(AppError::WriteBackpressure {
+                session_id: "test".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_interrupt_nonexistent_session() {
+        let mut manager = TerminalManager::new();
+        assert!(manager.interrupt("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_scrollback_keeps_tail_within_limit() {
+        let mut scrollback = Scrollback::with_limit(8);
+        scrollback.push(b"hello");
+        scrollback.push(b" world");
+        assert_eq!(scrollback.to_bytes(), b"lo world");
+
+        // 上限より大きい書き込みは末尾のみ保持
+        scrollback.push(b"0123456789");
+        assert_eq!(scrollback.to_bytes(), b"23456789");
+    }
+
+    #[test]
+    fn test_clear_scrollback() {
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "test");
+        manager.sessions["test"]
+            .scrollback
+            .lock()
+            .unwrap()
+            .push(b"$ make html\r\n");
+        assert!(!manager.scrollback("test").unwrap().is_empty());
+
+        manager.clear_scrollback("test").unwrap();
+        assert!(manager.scrollback("test").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scrollback_gz_roundtrip() {
+        use flate2::read::GzDecoder;
+
+        let mut manager = TerminalManager::new();
+        insert_recording_session(&mut manager, "test");
+        let output = b"reading sources... [ 50%] index\r\n".repeat(64);
+        manager.sessions["test"]
+            .scrollback
+            .lock()
+            .unwrap()
+            .push(&output);
+
+        let compressed = manager.scrollback_gz("test").unwrap();
+        assert!(compressed.len() < output.len());
+        let mut decompressed = Vec::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, output);
+
+        assert!(manager.scrollback_gz("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_clear_scrollback_nonexistent_session() {
+        let mut manager = TerminalManager::new();
+        assert!(manager.clear_scrollback("nonexistent").is_err());
+    }
+
+    #[test]
     fn test_detect_shell_with_config() {
         // 設定値が優先される
-        let shell = detect_shell(Some("/opt/homebrew/bin/fish"));
+        let shell = detect_shell(Some("/opt/homebrew/bin/fish"), &[]);
         assert_eq!(shell, "/opt/homebrew/bin/fish");
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_detect_shell_picks_first_usable_fallback() {
+        let (dir, shell) = fake_shell("#!/bin/sh\n");
+        let fallbacks = [
+            "/nonexistent/bin/fish".to_string(),
+            dir.path().join("zsh").to_string_lossy().to_string(),
+            shell.clone(),
+        ];
+        assert_eq!(detect_shell(None, &fallbacks), shell);
+        // 設定値があればフォールバックは使わない
+        assert_eq!(detect_shell(Some("/bin/bash"), &fallbacks), "/bin/bash");
+    }
+
     #[test]
     fn test_detect_shell_from_env() {
         // 設定がない場合は $SHELL を使用
         let original = std::env::var("SHELL").ok();
         std::env::set_var("SHELL", "/usr/local/bin/zsh");
-        let shell = detect_shell(None);
+        let shell = detect_shell(None, &[]);
         assert_eq!(shell, "/usr/local/bin/zsh");
 
         // 環境変数を元に戻す
@@ -264,7 +3693,7 @@ mod tests {
         // $SHELL がない場合は /bin/sh
         let original = std::env::var("SHELL").ok();
         std::env::remove_var("SHELL");
-        let shell = detect_shell(None);
+        let shell = detect_shell(None, &[]);
         assert_eq!(shell, "/bin/sh");
 
         // 環境変数を元に戻す